@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER good_phrases_fts_after_update_person;
+DROP TRIGGER good_phrases_fts_after_delete;
+DROP TRIGGER good_phrases_fts_after_update;
+DROP TRIGGER good_phrases_fts_after_insert;
+DROP TABLE good_phrases_fts;
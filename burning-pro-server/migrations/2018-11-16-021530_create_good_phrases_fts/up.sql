@@ -0,0 +1,40 @@
+-- Full-text search index for good_phrases.
+--
+-- `rowid` of the index is `good_phrases.good_phrase_id`.
+-- Triggers below keep the index in sync with `good_phrases` and `persons`.
+CREATE VIRTUAL TABLE good_phrases_fts USING fts5(
+    title,
+    phrase,
+    display_name,
+    real_name
+);
+
+INSERT INTO good_phrases_fts(rowid, title, phrase, display_name, real_name)
+    SELECT good_phrases.good_phrase_id, good_phrases.title, good_phrases.phrase, persons.display_name, persons.real_name
+    FROM good_phrases INNER JOIN persons ON good_phrases.person_id = persons.person_id;
+
+CREATE TRIGGER good_phrases_fts_after_insert AFTER INSERT ON good_phrases
+BEGIN
+    INSERT INTO good_phrases_fts(rowid, title, phrase, display_name, real_name)
+        SELECT NEW.good_phrase_id, NEW.title, NEW.phrase, persons.display_name, persons.real_name
+        FROM persons WHERE persons.person_id = NEW.person_id;
+END;
+
+CREATE TRIGGER good_phrases_fts_after_update AFTER UPDATE ON good_phrases
+BEGIN
+    DELETE FROM good_phrases_fts WHERE rowid = OLD.good_phrase_id;
+    INSERT INTO good_phrases_fts(rowid, title, phrase, display_name, real_name)
+        SELECT NEW.good_phrase_id, NEW.title, NEW.phrase, persons.display_name, persons.real_name
+        FROM persons WHERE persons.person_id = NEW.person_id;
+END;
+
+CREATE TRIGGER good_phrases_fts_after_delete AFTER DELETE ON good_phrases
+BEGIN
+    DELETE FROM good_phrases_fts WHERE rowid = OLD.good_phrase_id;
+END;
+
+CREATE TRIGGER good_phrases_fts_after_update_person AFTER UPDATE OF display_name, real_name ON persons
+BEGIN
+    UPDATE good_phrases_fts SET display_name = NEW.display_name, real_name = NEW.real_name
+        WHERE rowid IN (SELECT good_phrase_id FROM good_phrases WHERE person_id = NEW.person_id);
+END;
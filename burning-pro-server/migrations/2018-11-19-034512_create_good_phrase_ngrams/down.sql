@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER good_phrase_ngrams_after_delete;
+DROP TABLE good_phrase_ngrams;
@@ -0,0 +1,15 @@
+-- N-gram index of `good_phrases.phrase` for Japanese-aware search.
+--
+-- `rowid` of the index is `good_phrases.good_phrase_id`.
+-- `phrase` column has whitespace-separated bigrams of normalized phrase.
+-- The index is maintained by the application (normalization is done outside
+-- of SQLite), and missing rows are indexed on server startup.
+CREATE VIRTUAL TABLE good_phrase_ngrams USING fts5(
+    phrase,
+    tokenize = "unicode61 remove_diacritics 0"
+);
+
+CREATE TRIGGER good_phrase_ngrams_after_delete AFTER DELETE ON good_phrases
+BEGIN
+    DELETE FROM good_phrase_ngrams WHERE rowid = OLD.good_phrase_id;
+END;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = ON;
+
+DROP TABLE mylist_entries;
+DROP TABLE mylists;
+DROP INDEX good_phrase_uses_good_phrase_id;
+DROP TABLE good_phrase_uses;
+DROP TABLE good_phrase_favorites;
+DROP TABLE users;
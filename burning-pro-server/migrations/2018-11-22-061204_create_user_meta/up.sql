@@ -0,0 +1,56 @@
+-- Your SQL goes here
+PRAGMA foreign_keys = ON;
+
+-- App users identified by anonymous device tokens.
+CREATE TABLE users (
+    user_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    device_token VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE good_phrase_favorites (
+    good_phrase_favorite_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    user_id INTEGER NOT NULL,
+    good_phrase_id INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(user_id),
+    FOREIGN KEY(good_phrase_id) REFERENCES good_phrases(good_phrase_id),
+    UNIQUE(user_id, good_phrase_id)
+);
+
+-- Usage events (copy, send, etc.) of phrases.
+--
+-- `user_id` is `NULL` for uses by unidentified clients.
+CREATE TABLE good_phrase_uses (
+    good_phrase_use_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    user_id INTEGER,
+    good_phrase_id INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(user_id),
+    FOREIGN KEY(good_phrase_id) REFERENCES good_phrases(good_phrase_id)
+);
+CREATE INDEX good_phrase_uses_good_phrase_id ON good_phrase_uses(good_phrase_id);
+
+CREATE TABLE mylists (
+    mylist_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    user_id INTEGER NOT NULL,
+    name VARCHAR NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(user_id),
+    UNIQUE(user_id, name)
+);
+
+CREATE TABLE mylist_entries (
+    mylist_entry_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    mylist_id INTEGER NOT NULL,
+    good_phrase_id INTEGER NOT NULL,
+    FOREIGN KEY(mylist_id) REFERENCES mylists(mylist_id),
+    FOREIGN KEY(good_phrase_id) REFERENCES good_phrases(good_phrase_id),
+    UNIQUE(mylist_id, good_phrase_id)
+);
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+
+-- Renaming `mylists` itself would rewrite the foreign key of `mylist_entries`,
+-- so create the new table first and rename it after the old one is dropped.
+CREATE TABLE new_mylists (
+    mylist_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    user_id INTEGER NOT NULL,
+    name VARCHAR NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(user_id),
+    UNIQUE(user_id, name)
+);
+INSERT INTO new_mylists(mylist_id,created_at,modified_at,user_id,name) SELECT mylist_id,created_at,modified_at,user_id,name FROM mylists;
+DROP TABLE mylists;
+ALTER TABLE new_mylists RENAME TO mylists;
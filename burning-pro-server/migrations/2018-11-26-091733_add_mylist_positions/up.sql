@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- Order of mylists for each user (ascending).
+ALTER TABLE mylists ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE mylists SET position = mylist_id;
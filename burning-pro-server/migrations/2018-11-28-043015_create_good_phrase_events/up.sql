@@ -0,0 +1,23 @@
+-- Your SQL goes here
+
+-- Events of phrases used for rankings.
+--
+-- Uses are also recorded to `good_phrase_uses`, which counts uses in the feed.
+-- This table is not one of the feed tables, so views do not change the feed
+-- version.
+CREATE TABLE good_phrase_events (
+    good_phrase_event_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    good_phrase_id INTEGER NOT NULL,
+    -- NULL for the event from unknown users.
+    user_id INTEGER,
+    -- `view` or `use`.
+    kind VARCHAR NOT NULL,
+    FOREIGN KEY(good_phrase_id) REFERENCES good_phrases(good_phrase_id),
+    FOREIGN KEY(user_id) REFERENCES users(user_id)
+);
+CREATE INDEX good_phrase_events_created_at ON good_phrase_events(created_at);
+
+INSERT INTO good_phrase_events(created_at, modified_at, good_phrase_id, user_id, kind)
+    SELECT created_at, modified_at, good_phrase_id, user_id, 'use' FROM good_phrase_uses;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER person_urls_tombstone;
+DROP TRIGGER good_phrases_and_tags_tombstone;
+DROP TRIGGER good_phrase_tags_tombstone;
+DROP TRIGGER persons_tombstone;
+DROP TRIGGER good_phrases_tombstone;
+DROP TABLE tombstones;
@@ -0,0 +1,38 @@
+-- Your SQL goes here
+
+-- Rows deleted from tables, used for incremental sync.
+CREATE TABLE tombstones (
+    tombstone_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now')),
+    -- Name of the table the row is deleted from.
+    table_name VARCHAR NOT NULL,
+    -- Primary key of the deleted row.
+    row_id INTEGER NOT NULL,
+    -- Parent ID of the deleted row (`person_id` for `good_phrases` and
+    -- `person_urls`, `good_phrase_id` for `good_phrases_and_tags`).
+    parent_id INTEGER,
+    -- Linked ID of the deleted row (`good_phrase_tag_id` for
+    -- `good_phrases_and_tags`).
+    linked_id INTEGER
+);
+CREATE INDEX tombstones_created_at ON tombstones(created_at);
+
+CREATE TRIGGER good_phrases_tombstone AFTER DELETE ON good_phrases BEGIN
+    INSERT INTO tombstones(table_name, row_id, parent_id)
+        VALUES ('good_phrases', old.good_phrase_id, old.person_id);
+END;
+CREATE TRIGGER persons_tombstone AFTER DELETE ON persons BEGIN
+    INSERT INTO tombstones(table_name, row_id) VALUES ('persons', old.person_id);
+END;
+CREATE TRIGGER good_phrase_tags_tombstone AFTER DELETE ON good_phrase_tags BEGIN
+    INSERT INTO tombstones(table_name, row_id) VALUES ('good_phrase_tags', old.good_phrase_tag_id);
+END;
+CREATE TRIGGER good_phrases_and_tags_tombstone AFTER DELETE ON good_phrases_and_tags BEGIN
+    INSERT INTO tombstones(table_name, row_id, parent_id, linked_id)
+        VALUES ('good_phrases_and_tags', old.good_phrase_and_tag_id, old.good_phrase_id, old.good_phrase_tag_id);
+END;
+CREATE TRIGGER person_urls_tombstone AFTER DELETE ON person_urls BEGIN
+    INSERT INTO tombstones(table_name, row_id, parent_id)
+        VALUES ('person_urls', old.person_url_id, old.person_id);
+END;
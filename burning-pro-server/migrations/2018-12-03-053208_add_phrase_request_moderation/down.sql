@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX good_phrase_requests_status;
+CREATE TABLE new_good_phrase_requests (
+    good_phrase_request_id INTEGER NOT NULL PRIMARY KEY,
+    phrase VARCHAR NOT NULL,
+    person VARCHAR NOT NULL,
+    url VARCHAR,
+    deleted BOOLEAN NOT NULL DEFAULT 0,
+    published_at TIMESTAMP
+);
+INSERT INTO new_good_phrase_requests(good_phrase_request_id,phrase,person,url,deleted,published_at) SELECT good_phrase_request_id,phrase,person,url,deleted,published_at FROM good_phrase_requests;
+DROP TABLE good_phrase_requests;
+ALTER TABLE new_good_phrase_requests RENAME TO good_phrase_requests;
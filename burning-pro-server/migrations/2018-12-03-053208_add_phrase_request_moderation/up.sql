@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+-- Moderation status of the request (`pending`, `accepted`, or `rejected`).
+ALTER TABLE good_phrase_requests ADD COLUMN status VARCHAR NOT NULL DEFAULT 'pending'
+    CHECK (status IN ('pending', 'accepted', 'rejected'));
+-- Phrase registered from the accepted request.
+ALTER TABLE good_phrase_requests ADD COLUMN good_phrase_id INTEGER
+    REFERENCES good_phrases(good_phrase_id) ON DELETE SET NULL;
+-- Reason why the request is rejected.
+ALTER TABLE good_phrase_requests ADD COLUMN reject_reason VARCHAR;
+-- UTC datetime the request is accepted or rejected at.
+ALTER TABLE good_phrase_requests ADD COLUMN moderated_at TIMESTAMP;
+CREATE INDEX good_phrase_requests_status ON good_phrase_requests(status);
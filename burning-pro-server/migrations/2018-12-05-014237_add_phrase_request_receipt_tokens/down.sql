@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX good_phrase_requests_status;
+CREATE TABLE new_good_phrase_requests (
+    good_phrase_request_id INTEGER NOT NULL PRIMARY KEY,
+    phrase VARCHAR NOT NULL,
+    person VARCHAR NOT NULL,
+    url VARCHAR,
+    deleted BOOLEAN NOT NULL DEFAULT 0,
+    published_at TIMESTAMP,
+    status VARCHAR NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'rejected')),
+    good_phrase_id INTEGER
+        REFERENCES good_phrases(good_phrase_id) ON DELETE SET NULL,
+    reject_reason VARCHAR,
+    moderated_at TIMESTAMP
+);
+INSERT INTO new_good_phrase_requests(good_phrase_request_id,phrase,person,url,deleted,published_at,status,good_phrase_id,reject_reason,moderated_at) SELECT good_phrase_request_id,phrase,person,url,deleted,published_at,status,good_phrase_id,reject_reason,moderated_at FROM good_phrase_requests;
+DROP TABLE good_phrase_requests;
+ALTER TABLE new_good_phrase_requests RENAME TO good_phrase_requests;
+CREATE INDEX good_phrase_requests_status ON good_phrase_requests(status);
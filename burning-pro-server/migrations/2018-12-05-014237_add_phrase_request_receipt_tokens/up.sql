@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- Base64-encoded SHA-256 digest of the receipt token given to the submitter,
+-- used to check the status of the request.
+-- Requests submitted before this column is added have no tokens.
+ALTER TABLE good_phrase_requests ADD COLUMN receipt_token_digest VARCHAR;
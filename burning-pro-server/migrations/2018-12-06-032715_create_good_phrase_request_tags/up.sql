@@ -0,0 +1,20 @@
+-- Your SQL goes here
+PRAGMA foreign_keys = ON;
+
+-- Tags proposed by phrase requests.
+--
+-- Either an existing tag (`good_phrase_tag_id`) or a free-text tag name
+-- (`name`) is proposed.
+CREATE TABLE good_phrase_request_tags (
+    good_phrase_request_tag_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    good_phrase_request_id INTEGER NOT NULL,
+    good_phrase_tag_id INTEGER,
+    name VARCHAR,
+    FOREIGN KEY(good_phrase_request_id) REFERENCES good_phrase_requests(good_phrase_request_id) ON DELETE CASCADE,
+    FOREIGN KEY(good_phrase_tag_id) REFERENCES good_phrase_tags(good_phrase_tag_id) ON DELETE CASCADE,
+    CHECK (good_phrase_tag_id IS NOT NULL OR name IS NOT NULL)
+);
+CREATE INDEX good_phrase_request_tags_good_phrase_request_id
+    ON good_phrase_request_tags(good_phrase_request_id);
@@ -0,0 +1,97 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER good_phrase_tags_restore;
+DROP TRIGGER good_phrase_tags_retract;
+DROP TRIGGER persons_retract_or_restore;
+DROP TRIGGER persons_restore;
+DROP TRIGGER persons_retract;
+DROP TRIGGER good_phrases_retract_or_restore;
+DROP TRIGGER good_phrases_retract;
+
+-- Triggers referring to the recreated tables are dropped, and created again
+-- after the tables are recreated.
+DROP TRIGGER good_phrase_tags_tombstone;
+DROP TRIGGER persons_tombstone;
+DROP TRIGGER good_phrases_tombstone;
+DROP TRIGGER good_phrase_ngrams_after_delete;
+DROP TRIGGER good_phrases_fts_after_update_person;
+DROP TRIGGER good_phrases_fts_after_delete;
+DROP TRIGGER good_phrases_fts_after_update;
+DROP TRIGGER good_phrases_fts_after_insert;
+
+CREATE TABLE new_persons (
+    person_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    real_name VARCHAR,
+    display_name VARCHAR NOT NULL UNIQUE,
+    twitter VARCHAR UNIQUE
+);
+INSERT INTO new_persons(person_id,created_at,modified_at,real_name,display_name,twitter) SELECT person_id,created_at,modified_at,real_name,display_name,twitter FROM persons;
+DROP TABLE persons;
+ALTER TABLE new_persons RENAME TO persons;
+
+CREATE TABLE new_good_phrases (
+    good_phrase_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    title VARCHAR UNIQUE NOT NULL,
+    phrase VARCHAR NOT NULL,
+    person_id INTEGER NOT NULL,
+    url VARCHAR,
+    deleted BOOLEAN NOT NULL DEFAULT 0,
+    published_at TIMESTAMP,
+    FOREIGN KEY(person_id) REFERENCES persons(person_id),
+    UNIQUE(phrase, person_id)
+);
+INSERT INTO new_good_phrases(good_phrase_id,created_at,modified_at,title,phrase,person_id,url,deleted,published_at) SELECT good_phrase_id,created_at,modified_at,title,phrase,person_id,url,deleted,published_at FROM good_phrases;
+DROP TABLE good_phrases;
+ALTER TABLE new_good_phrases RENAME TO good_phrases;
+
+CREATE TABLE new_good_phrase_tags (
+    good_phrase_tag_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    name VARCHAR UNIQUE NOT NULL,
+    description VARCHAR
+);
+INSERT INTO new_good_phrase_tags(good_phrase_tag_id,created_at,modified_at,name,description) SELECT good_phrase_tag_id,created_at,modified_at,name,description FROM good_phrase_tags;
+DROP TABLE good_phrase_tags;
+ALTER TABLE new_good_phrase_tags RENAME TO good_phrase_tags;
+
+CREATE TRIGGER good_phrases_fts_after_insert AFTER INSERT ON good_phrases
+BEGIN
+    INSERT INTO good_phrases_fts(rowid, title, phrase, display_name, real_name)
+        SELECT NEW.good_phrase_id, NEW.title, NEW.phrase, persons.display_name, persons.real_name
+        FROM persons WHERE persons.person_id = NEW.person_id;
+END;
+CREATE TRIGGER good_phrases_fts_after_update AFTER UPDATE ON good_phrases
+BEGIN
+    DELETE FROM good_phrases_fts WHERE rowid = OLD.good_phrase_id;
+    INSERT INTO good_phrases_fts(rowid, title, phrase, display_name, real_name)
+        SELECT NEW.good_phrase_id, NEW.title, NEW.phrase, persons.display_name, persons.real_name
+        FROM persons WHERE persons.person_id = NEW.person_id;
+END;
+CREATE TRIGGER good_phrases_fts_after_delete AFTER DELETE ON good_phrases
+BEGIN
+    DELETE FROM good_phrases_fts WHERE rowid = OLD.good_phrase_id;
+END;
+CREATE TRIGGER good_phrases_fts_after_update_person AFTER UPDATE OF display_name, real_name ON persons
+BEGIN
+    UPDATE good_phrases_fts SET display_name = NEW.display_name, real_name = NEW.real_name
+        WHERE rowid IN (SELECT good_phrase_id FROM good_phrases WHERE person_id = NEW.person_id);
+END;
+CREATE TRIGGER good_phrase_ngrams_after_delete AFTER DELETE ON good_phrases
+BEGIN
+    DELETE FROM good_phrase_ngrams WHERE rowid = OLD.good_phrase_id;
+END;
+CREATE TRIGGER good_phrases_tombstone AFTER DELETE ON good_phrases BEGIN
+    INSERT INTO tombstones(table_name, row_id, parent_id)
+        VALUES ('good_phrases', old.good_phrase_id, old.person_id);
+END;
+CREATE TRIGGER persons_tombstone AFTER DELETE ON persons BEGIN
+    INSERT INTO tombstones(table_name, row_id) VALUES ('persons', old.person_id);
+END;
+CREATE TRIGGER good_phrase_tags_tombstone AFTER DELETE ON good_phrase_tags BEGIN
+    INSERT INTO tombstones(table_name, row_id) VALUES ('good_phrase_tags', old.good_phrase_tag_id);
+END;
@@ -0,0 +1,67 @@
+-- Your SQL goes here
+
+-- UTC datetime the row is retracted (hidden from clients) at.
+--
+-- Retracted rows are kept in the tables and can be restored by setting the
+-- column to NULL.
+-- Phrases of retracted persons are hidden as well.
+ALTER TABLE good_phrases ADD COLUMN retracted_at TIMESTAMP;
+ALTER TABLE persons ADD COLUMN retracted_at TIMESTAMP;
+ALTER TABLE good_phrase_tags ADD COLUMN retracted_at TIMESTAMP;
+
+-- Retracted rows are reported to clients as tombstones, and restored rows are
+-- returned as updated entries.
+-- Tag links of the affected phrases are touched so that phrase counts of tags
+-- are updated by incremental sync.
+CREATE TRIGGER good_phrases_retract AFTER UPDATE OF retracted_at ON good_phrases
+    WHEN OLD.retracted_at IS NULL AND NEW.retracted_at IS NOT NULL
+BEGIN
+    INSERT INTO tombstones(table_name, row_id, parent_id)
+        VALUES ('good_phrases', NEW.good_phrase_id, NEW.person_id);
+END;
+CREATE TRIGGER good_phrases_retract_or_restore AFTER UPDATE OF retracted_at ON good_phrases
+    WHEN OLD.retracted_at IS NOT NEW.retracted_at
+BEGIN
+    UPDATE good_phrases_and_tags SET modified_at = NEW.modified_at
+        WHERE good_phrase_id = NEW.good_phrase_id;
+END;
+
+CREATE TRIGGER persons_retract AFTER UPDATE OF retracted_at ON persons
+    WHEN OLD.retracted_at IS NULL AND NEW.retracted_at IS NOT NULL
+BEGIN
+    INSERT INTO tombstones(table_name, row_id) VALUES ('persons', NEW.person_id);
+    INSERT INTO tombstones(table_name, row_id, parent_id)
+        SELECT 'good_phrases', good_phrase_id, person_id FROM good_phrases
+        WHERE person_id = NEW.person_id AND retracted_at IS NULL;
+END;
+CREATE TRIGGER persons_restore AFTER UPDATE OF retracted_at ON persons
+    WHEN OLD.retracted_at IS NOT NULL AND NEW.retracted_at IS NULL
+BEGIN
+    UPDATE good_phrases SET modified_at = NEW.modified_at
+        WHERE person_id = NEW.person_id AND retracted_at IS NULL;
+END;
+CREATE TRIGGER persons_retract_or_restore AFTER UPDATE OF retracted_at ON persons
+    WHEN OLD.retracted_at IS NOT NEW.retracted_at
+BEGIN
+    UPDATE good_phrases_and_tags SET modified_at = NEW.modified_at
+        WHERE good_phrase_id IN (
+            SELECT good_phrase_id FROM good_phrases
+            WHERE person_id = NEW.person_id AND retracted_at IS NULL
+        );
+END;
+
+CREATE TRIGGER good_phrase_tags_retract AFTER UPDATE OF retracted_at ON good_phrase_tags
+    WHEN OLD.retracted_at IS NULL AND NEW.retracted_at IS NOT NULL
+BEGIN
+    INSERT INTO tombstones(table_name, row_id)
+        VALUES ('good_phrase_tags', NEW.good_phrase_tag_id);
+    INSERT INTO tombstones(table_name, row_id, parent_id, linked_id)
+        SELECT 'good_phrases_and_tags', good_phrase_and_tag_id, good_phrase_id, good_phrase_tag_id
+        FROM good_phrases_and_tags WHERE good_phrase_tag_id = NEW.good_phrase_tag_id;
+END;
+CREATE TRIGGER good_phrase_tags_restore AFTER UPDATE OF retracted_at ON good_phrase_tags
+    WHEN OLD.retracted_at IS NOT NULL AND NEW.retracted_at IS NULL
+BEGIN
+    UPDATE good_phrases_and_tags SET modified_at = NEW.modified_at
+        WHERE good_phrase_tag_id = NEW.good_phrase_tag_id;
+END;
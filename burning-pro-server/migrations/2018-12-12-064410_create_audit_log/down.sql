@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX audit_log_table_name_row_id;
+DROP INDEX audit_log_created_at;
+DROP TABLE audit_log;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+
+-- Changes of entries made by admin users.
+CREATE TABLE audit_log (
+    audit_log_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    -- Name of the admin user who made the change.
+    user_name VARCHAR NOT NULL,
+    -- Kind of the change (`create`, `update`, `retract`, `restore`,
+    -- `approve`, or `reject`).
+    action VARCHAR NOT NULL,
+    -- Name of the table the changed row belongs to.
+    table_name VARCHAR NOT NULL,
+    -- Primary key of the changed row.
+    row_id INTEGER NOT NULL,
+    -- JSON object of the changed fields before the change (`NULL` for
+    -- created rows).
+    old_values VARCHAR,
+    -- JSON object of the changed fields after the change.
+    new_values VARCHAR
+);
+CREATE INDEX audit_log_created_at ON audit_log(created_at);
+CREATE INDEX audit_log_table_name_row_id ON audit_log(table_name, row_id);
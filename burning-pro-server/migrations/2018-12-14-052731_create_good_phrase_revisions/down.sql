@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX good_phrase_revisions_good_phrase_id;
+DROP TABLE good_phrase_revisions;
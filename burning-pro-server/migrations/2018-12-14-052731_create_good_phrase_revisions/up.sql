@@ -0,0 +1,42 @@
+-- Your SQL goes here
+
+-- Revisions (snapshots) of phrases and their tags.
+CREATE TABLE good_phrase_revisions (
+    good_phrase_revision_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    good_phrase_id INTEGER NOT NULL,
+    -- Name of the admin user who made the revision (empty if unknown).
+    user_name VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    phrase VARCHAR NOT NULL,
+    person_id INTEGER NOT NULL,
+    url VARCHAR,
+    deleted BOOLEAN NOT NULL,
+    published_at TIMESTAMP,
+    -- Comma-separated IDs of tags of the phrase.
+    tag_ids VARCHAR NOT NULL,
+    FOREIGN KEY(good_phrase_id) REFERENCES good_phrases(good_phrase_id),
+    FOREIGN KEY(person_id) REFERENCES persons(person_id)
+);
+CREATE INDEX good_phrase_revisions_good_phrase_id
+    ON good_phrase_revisions(good_phrase_id);
+
+-- Current contents of existing phrases are the first revisions.
+INSERT INTO good_phrase_revisions (
+    created_at, modified_at, good_phrase_id, user_name, title, phrase, person_id, url, deleted,
+    published_at, tag_ids
+)
+SELECT
+    p.modified_at, p.modified_at, p.good_phrase_id, '', p.title, p.phrase, p.person_id, p.url,
+    p.deleted, p.published_at,
+    COALESCE(
+        (
+            SELECT GROUP_CONCAT(pt.good_phrase_tag_id, ',')
+            FROM good_phrases_and_tags AS pt
+            WHERE pt.good_phrase_id = p.good_phrase_id
+        ),
+        ''
+    )
+FROM good_phrases AS p
+ORDER BY p.good_phrase_id;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+-- Users of the admin web UI.
+CREATE TABLE admin_users (
+    admin_user_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    name VARCHAR UNIQUE NOT NULL,
+    -- Password hash (Argon2id PHC string, `$argon2id$v=19$...`).
+    password_hash VARCHAR NOT NULL,
+    -- Role (`viewer`, `editor`, or `moderator`).
+    role VARCHAR NOT NULL CHECK (role IN ('viewer', 'editor', 'moderator'))
+);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+-- Bearer tokens of admin users for the admin API.
+CREATE TABLE admin_api_tokens (
+    admin_api_token_id INTEGER NOT NULL PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    modified_at TIMESTAMP NOT NULL DEFAULT (DATETIME('now')),
+    admin_user_id INTEGER NOT NULL,
+    -- Digest (base64-encoded SHA-256) of the token.
+    token_digest VARCHAR UNIQUE NOT NULL,
+    -- Free-text label to tell what the token is used for.
+    label VARCHAR NOT NULL,
+    -- UTC datetime the token is last used at (if used).
+    last_used_at TIMESTAMP,
+    -- UTC datetime the token is revoked at (if revoked).
+    revoked_at TIMESTAMP,
+    FOREIGN KEY(admin_user_id) REFERENCES admin_users(admin_user_id)
+);
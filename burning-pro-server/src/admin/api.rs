@@ -0,0 +1,247 @@
+//! Handler module for the admin JSON API.
+//!
+//! The API is for scripts to edit entries in bulk, and authenticated by the
+//! bearer tokens of admin users.
+
+use actix::dev::ToEnvelope;
+use actix::{Actor, Handler, Message};
+use actix_web::http::StatusCode;
+use actix_web::{AsyncResponder, Error, FutureResponse, HttpRequest, HttpResponse, Json, Path};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures::future::{self, Future};
+
+use app::{admin_user, AppState, FieldError, JsonError};
+use db::upsert_entry::{self, UpsertResult};
+use db::{self, Audited, DbExecutor, RetractTarget, SetRetracted};
+
+/// Response of the created or updated entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+struct UpsertResponse {
+    /// Row ID of the entry.
+    id: i32,
+}
+
+/// Adds a validation error if the text is empty.
+fn validate_required(errors: &mut Vec<FieldError>, field: &'static str, text: &str) {
+    if text.is_empty() {
+        errors.push(FieldError::new(field, "required", "Field must not be empty"));
+    }
+}
+
+/// Converts the DB error into the HTTP error.
+fn db_error(context: &str, e: db::Error) -> Error {
+    match e {
+        db::Error::Db(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            debug!("`{}`: Duplicate entry.", context);
+            JsonError::conflict("Entry with the same key already exists").into()
+        }
+        db::Error::Db(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+            debug!("`{}`: Reference to a missing entry.", context);
+            JsonError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Entry refers to a missing entry",
+            ).with_code("unknown_reference")
+            .into()
+        }
+        e => {
+            error!("`{}`: {}", context, e);
+            JsonError::internal("DB error").into()
+        }
+    }
+}
+
+/// Inserts or updates the entry, and responds its row ID.
+///
+/// `status` is the status code of successful responses, and `kind` is the
+/// name of the entry used in error messages.
+fn upsert_impl<M, R>(
+    req: &HttpRequest<AppState>,
+    msg: M,
+    errors: Vec<FieldError>,
+    status: StatusCode,
+    kind: &'static str,
+) -> FutureResponse<HttpResponse>
+where
+    M: Message<Result = Result<R, db::Error>> + Send + 'static,
+    R: Into<UpsertResult> + Send + 'static,
+    DbExecutor: Handler<Audited<M>>,
+    <DbExecutor as Actor>::Context: ToEnvelope<DbExecutor, Audited<M>>,
+{
+    if !errors.is_empty() {
+        debug!("Validation failed: {:?}", errors);
+        return Box::new(future::err(JsonError::validation(&errors).into()));
+    }
+    req.state()
+        .db()
+        .send(Audited::new(admin_user(req), msg))
+        .from_err()
+        .and_then(move |res| match res.map(Into::into) {
+            Ok(UpsertResult::Upserted(id)) => {
+                Ok(HttpResponse::build(status).json(UpsertResponse { id }))
+            }
+            Ok(UpsertResult::NotFound) => {
+                debug!("{} to be updated is not found.", kind);
+                Err(JsonError::not_found(format!("{} not found", kind)).into())
+            }
+            Ok(UpsertResult::UnknownPerson(person_id)) => {
+                let msg = format!("Person {} does not exist", person_id);
+                let errors = [FieldError::new("person_id", "unknown_person", msg)];
+                Err(JsonError::validation(&errors).into())
+            }
+            Ok(UpsertResult::UnknownTag(tag_id)) => {
+                let msg = format!("Tag {} does not exist", tag_id);
+                let errors = [FieldError::new("tag_ids", "unknown_tag", msg)];
+                Err(JsonError::validation(&errors).into())
+            }
+            Err(e) => Err(db_error("admin::api::upsert_impl()", e)),
+        }).responder()
+}
+
+/// Retracts the entry, and responds no content.
+fn retract_impl(
+    req: &HttpRequest<AppState>,
+    target: RetractTarget,
+) -> FutureResponse<HttpResponse> {
+    let msg = Audited::new(
+        admin_user(req),
+        SetRetracted {
+            target,
+            retracted: true,
+        },
+    );
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(true) => Ok(HttpResponse::NoContent().finish()),
+            Ok(false) => {
+                debug!("Retract target not found: {:?}", target);
+                Err(JsonError::not_found("Entry not found").into())
+            }
+            Err(e) => Err(db_error("admin::api::retract_impl()", e)),
+        }).responder()
+}
+
+/// Validates and normalizes the phrase.
+fn phrase_msg(mut msg: upsert_entry::GoodPhrase) -> (upsert_entry::GoodPhrase, Vec<FieldError>) {
+    let mut errors = Vec::new();
+    msg.title = msg.title.trim().to_owned();
+    validate_required(&mut errors, "title", &msg.title);
+    msg.phrase = msg.phrase.trim().to_owned();
+    validate_required(&mut errors, "phrase", &msg.phrase);
+    (msg, errors)
+}
+
+/// Processes the request to create a phrase.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn create_phrase(
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::GoodPhrase>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::create_phrase()`: {:?}", req);
+    let (mut msg, errors) = phrase_msg(body.into_inner());
+    msg.good_phrase_id = None;
+    upsert_impl(&req, msg, errors, StatusCode::CREATED, "Phrase")
+}
+
+/// Processes the request to update a phrase.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn update_phrase(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::GoodPhrase>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::update_phrase()`: {:?}", req);
+    let (mut msg, errors) = phrase_msg(body.into_inner());
+    msg.good_phrase_id = Some(path.into_inner());
+    upsert_impl(&req, msg, errors, StatusCode::OK, "Phrase")
+}
+
+/// Processes the request to retract a phrase.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn delete_phrase(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::delete_phrase()`: {:?}", req);
+    retract_impl(&req, RetractTarget::GoodPhrase(path.into_inner()))
+}
+
+/// Validates and normalizes the person.
+fn person_msg(mut msg: upsert_entry::Person) -> (upsert_entry::Person, Vec<FieldError>) {
+    let mut errors = Vec::new();
+    msg.display_name = msg.display_name.trim().to_owned();
+    validate_required(&mut errors, "display_name", &msg.display_name);
+    (msg, errors)
+}
+
+/// Processes the request to create a person.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn create_person(
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::Person>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::create_person()`: {:?}", req);
+    let (mut msg, errors) = person_msg(body.into_inner());
+    msg.person_id = None;
+    upsert_impl(&req, msg, errors, StatusCode::CREATED, "Person")
+}
+
+/// Processes the request to update a person.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn update_person(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::Person>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::update_person()`: {:?}", req);
+    let (mut msg, errors) = person_msg(body.into_inner());
+    msg.person_id = Some(path.into_inner());
+    upsert_impl(&req, msg, errors, StatusCode::OK, "Person")
+}
+
+/// Processes the request to retract a person.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn delete_person(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::delete_person()`: {:?}", req);
+    retract_impl(&req, RetractTarget::Person(path.into_inner()))
+}
+
+/// Validates and normalizes the tag.
+fn tag_msg(mut msg: upsert_entry::GoodPhraseTag) -> (upsert_entry::GoodPhraseTag, Vec<FieldError>) {
+    let mut errors = Vec::new();
+    msg.name = msg.name.trim().to_owned();
+    validate_required(&mut errors, "name", &msg.name);
+    msg.description = msg.description.map(|x| x.trim().to_owned());
+    (msg, errors)
+}
+
+/// Processes the request to create a tag.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn create_tag(
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::GoodPhraseTag>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::create_tag()`: {:?}", req);
+    let (mut msg, errors) = tag_msg(body.into_inner());
+    msg.good_phrase_tag_id = None;
+    upsert_impl(&req, msg, errors, StatusCode::CREATED, "Tag")
+}
+
+/// Processes the request to update a tag.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn update_tag(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    body: Json<upsert_entry::GoodPhraseTag>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::update_tag()`: {:?}", req);
+    let (mut msg, errors) = tag_msg(body.into_inner());
+    msg.good_phrase_tag_id = Some(path.into_inner());
+    upsert_impl(&req, msg, errors, StatusCode::OK, "Tag")
+}
+
+/// Processes the request to retract a tag.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn delete_tag(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::api::delete_tag()`: {:?}", req);
+    retract_impl(&req, RetractTarget::GoodPhraseTag(path.into_inner()))
+}
@@ -0,0 +1,128 @@
+//! Handler module for audit log browser.
+
+use std::sync::Arc;
+
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Query};
+use chrono::{DateTime, FixedOffset, Local};
+use futures::future::{self, Future};
+use tera::Context;
+use url::form_urlencoded;
+
+use admin::{form, render};
+use app::AppState;
+use db::{AuditAction, AuditLogFilter, AuditTable, GetAuditLog};
+
+/// Number of records in a page.
+const PAGE_SIZE: i64 = 100;
+
+/// Format of datetimes in the filter form.
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Formats the datetime for the filter form.
+fn format_datetime(datetime: &Option<DateTime<Local>>) -> String {
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+    datetime
+        .map(|datetime| {
+            datetime
+                .with_timezone(&tz_offset)
+                .format(DATETIME_FORMAT)
+                .to_string()
+        }).unwrap_or_default()
+}
+
+/// Processes the request for audit log.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(
+    req: HttpRequest<AppState>,
+    query: Query<form::AuditLogQuery>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::audit_log::index()`: {:?}", req);
+
+    let query = query.into_inner();
+    let action = match query.action {
+        Some(ref name) => match AuditAction::ALL.iter().find(|v| v.as_str() == name) {
+            Some(&action) => Some(action),
+            None => return Box::new(future::err(ErrorBadRequest("Unknown action"))),
+        },
+        None => None,
+    };
+    let table = match query.table {
+        Some(ref name) => match AuditTable::ALL.iter().find(|v| v.as_str() == name) {
+            Some(&table) => Some(table),
+            None => return Box::new(future::err(ErrorBadRequest("Unknown table"))),
+        },
+        None => None,
+    };
+    let since = format_datetime(&query.since);
+    let until = format_datetime(&query.until);
+    let msg = GetAuditLog {
+        filter: AuditLogFilter {
+            user_name: query.user.clone(),
+            action,
+            table,
+            row_id: query.row_id,
+            since: query.since.map(|v| v.naive_utc()),
+            until: query.until.map(|v| v.naive_utc()),
+        },
+        before_id: query.before,
+        limit: PAGE_SIZE,
+    };
+
+    let db = req.state().db();
+    let template = Arc::clone(req.state().template());
+    db.send(msg)
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(rows) => {
+                // Link to older records with the same filter.
+                let next_page = if rows.len() as i64 == PAGE_SIZE {
+                    rows.last().map(|last| {
+                        form_urlencoded::Serializer::new(String::new())
+                            .append_pair("user", query.user.as_ref().map_or("", AsRef::as_ref))
+                            .append_pair("action", action.map_or("", AuditAction::as_str))
+                            .append_pair("table", table.map_or("", AuditTable::as_str))
+                            .append_pair(
+                                "row_id",
+                                &query.row_id.map(|v| v.to_string()).unwrap_or_default(),
+                            ).append_pair("since", &since)
+                            .append_pair("until", &until)
+                            .append_pair("before", &last.audit_log_id.to_string())
+                            .finish()
+                    })
+                } else {
+                    None
+                };
+
+                let mut ctx = Context::new();
+                ctx.insert("rows", &rows);
+                ctx.insert("next_page", &next_page);
+                ctx.insert("user", &query.user);
+                ctx.insert("action", &action.map(AuditAction::as_str));
+                ctx.insert("table", &table.map(AuditTable::as_str));
+                ctx.insert("row_id", &query.row_id);
+                ctx.insert("since", &since);
+                ctx.insert("until", &until);
+                ctx.insert(
+                    "all_action",
+                    &AuditAction::ALL
+                        .iter()
+                        .map(|v| v.as_str())
+                        .collect::<Vec<_>>(),
+                );
+                ctx.insert(
+                    "all_table",
+                    &AuditTable::ALL
+                        .iter()
+                        .map(|v| v.as_str())
+                        .collect::<Vec<_>>(),
+                );
+                Ok(render(&template, &ctx, "register/audit_log.html"))
+            }
+            Err(e) => {
+                error!("`admin::audit_log::index()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        }).responder()
+}
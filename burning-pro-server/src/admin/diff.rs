@@ -0,0 +1,226 @@
+//! Text diff shown on the admin pages.
+
+/// Maximum size of the LCS table.
+///
+/// Texts longer than this are shown as entirely deleted and inserted.
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+/// Kind of a diff segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// Text in both the old and the new texts.
+    Equal,
+    /// Text only in the new text.
+    Insert,
+    /// Text only in the old text.
+    Delete,
+}
+
+/// A segment of the diff.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct DiffSegment {
+    /// Kind of the segment.
+    pub kind: DiffKind,
+    /// Text of the segment.
+    pub text: String,
+}
+
+/// Appends the character to the segments, merging it to the last segment if
+/// possible.
+fn push(segments: &mut Vec<DiffSegment>, kind: DiffKind, c: char) {
+    if let Some(last) = segments.last_mut() {
+        if last.kind == kind {
+            last.text.push(c);
+            return;
+        }
+    }
+    segments.push(DiffSegment {
+        kind,
+        text: c.to_string(),
+    });
+}
+
+/// Computes the character-wise diff of the texts.
+pub fn diff_chars(old: &str, new: &str) -> Vec<DiffSegment> {
+    let old = old.chars().collect::<Vec<_>>();
+    let new = new.chars().collect::<Vec<_>>();
+
+    // Common prefix and suffix are not passed to the LCS table.
+    let prefix_len = old
+        .iter()
+        .zip(&new)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix_len = old[prefix_len..]
+        .iter()
+        .rev()
+        .zip(new[prefix_len..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix_len..old.len() - suffix_len];
+    let new_mid = &new[prefix_len..new.len() - suffix_len];
+
+    let mut segments = Vec::new();
+    for &c in &old[..prefix_len] {
+        push(&mut segments, DiffKind::Equal, c);
+    }
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if (n + 1) * (m + 1) > MAX_TABLE_SIZE {
+        for &c in old_mid {
+            push(&mut segments, DiffKind::Delete, c);
+        }
+        for &c in new_mid {
+            push(&mut segments, DiffKind::Insert, c);
+        }
+    } else {
+        // `lcs[i * (m + 1) + j]` is the LCS length of `old_mid[i..]` and
+        // `new_mid[j..]`.
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * (m + 1) + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_mid[i] == new_mid[j] {
+                push(&mut segments, DiffKind::Equal, old_mid[i]);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1] {
+                push(&mut segments, DiffKind::Delete, old_mid[i]);
+                i += 1;
+            } else {
+                push(&mut segments, DiffKind::Insert, new_mid[j]);
+                j += 1;
+            }
+        }
+        for &c in &old_mid[i..] {
+            push(&mut segments, DiffKind::Delete, c);
+        }
+        for &c in &new_mid[j..] {
+            push(&mut segments, DiffKind::Insert, c);
+        }
+    }
+    for &c in &old[old.len() - suffix_len..] {
+        push(&mut segments, DiffKind::Equal, c);
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the segments of the given kinds and texts.
+    fn segments(segments: &[(DiffKind, &str)]) -> Vec<DiffSegment> {
+        segments
+            .iter()
+            .map(|&(kind, text)| DiffSegment {
+                kind,
+                text: text.to_owned(),
+            }).collect()
+    }
+
+    /// Restores the old and the new texts from the diff.
+    fn restore(diff: &[DiffSegment]) -> (String, String) {
+        let mut old = String::new();
+        let mut new = String::new();
+        for segment in diff {
+            if segment.kind != DiffKind::Insert {
+                old.push_str(&segment.text);
+            }
+            if segment.kind != DiffKind::Delete {
+                new.push_str(&segment.text);
+            }
+        }
+        (old, new)
+    }
+
+    #[test]
+    fn equal_texts() {
+        assert_eq!(diff_chars("", ""), vec![]);
+        assert_eq!(
+            diff_chars("phrase", "phrase"),
+            segments(&[(DiffKind::Equal, "phrase")])
+        );
+    }
+
+    #[test]
+    fn pure_insert() {
+        assert_eq!(diff_chars("", "abc"), segments(&[(DiffKind::Insert, "abc")]));
+        assert_eq!(
+            diff_chars("ac", "abbc"),
+            segments(&[
+                (DiffKind::Equal, "a"),
+                (DiffKind::Insert, "bb"),
+                (DiffKind::Equal, "c"),
+            ])
+        );
+    }
+
+    #[test]
+    fn pure_delete() {
+        assert_eq!(diff_chars("abc", ""), segments(&[(DiffKind::Delete, "abc")]));
+        assert_eq!(
+            diff_chars("abbc", "ac"),
+            segments(&[
+                (DiffKind::Equal, "a"),
+                (DiffKind::Delete, "bb"),
+                (DiffKind::Equal, "c"),
+            ])
+        );
+    }
+
+    #[test]
+    fn replacement() {
+        let diff = diff_chars("kitten", "sitting");
+        assert_eq!(restore(&diff), ("kitten".to_owned(), "sitting".to_owned()));
+        let equal = diff
+            .iter()
+            .filter(|s| s.kind == DiffKind::Equal)
+            .map(|s| s.text.as_str())
+            .collect::<String>();
+        assert_eq!(equal, "ittn");
+    }
+
+    #[test]
+    fn multibyte_characters() {
+        assert_eq!(
+            diff_chars("今日は晴れ", "今日も晴れ"),
+            segments(&[
+                (DiffKind::Equal, "今日"),
+                (DiffKind::Delete, "は"),
+                (DiffKind::Insert, "も"),
+                (DiffKind::Equal, "晴れ"),
+            ])
+        );
+        // Characters sharing leading UTF-8 bytes are not split.
+        assert_eq!(
+            diff_chars("あ", "い"),
+            segments(&[(DiffKind::Delete, "あ"), (DiffKind::Insert, "い")])
+        );
+    }
+
+    #[test]
+    fn large_texts_fall_back_to_delete_and_insert() {
+        // The LCS table for the middle parts has `2001 * 2001` cells, which
+        // exceeds `MAX_TABLE_SIZE`.
+        let old = format!("<{}>", "a".repeat(2000));
+        let new = format!("<{}>", "b".repeat(2000));
+        assert_eq!(
+            diff_chars(&old, &new),
+            segments(&[
+                (DiffKind::Equal, "<"),
+                (DiffKind::Delete, &"a".repeat(2000)),
+                (DiffKind::Insert, &"b".repeat(2000)),
+                (DiffKind::Equal, ">"),
+            ])
+        );
+    }
+}
@@ -2,9 +2,68 @@
 
 use std::collections::HashMap;
 use std::fmt;
+use std::ops;
 
+use actix_web::error::{ErrorBadRequest, ErrorForbidden};
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
 use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use futures::future::Future;
 use serde::de;
+use serde_urlencoded;
+use url::form_urlencoded;
+
+use app::verify_csrf_token;
+
+/// Name of the form field for CSRF tokens.
+pub const CSRF_TOKEN_FIELD: &str = "csrf_token";
+
+/// Form data of admin pages, protected by the CSRF token.
+///
+/// Requests are rejected with `403 Forbidden` if the `csrf_token` field does
+/// not match the token of the admin session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CsrfForm<T>(pub T);
+
+impl<T> CsrfForm<T> {
+    /// Returns the inner form data.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for CsrfForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T, S> FromRequest<S> for CsrfForm<T>
+where
+    T: de::DeserializeOwned + 'static,
+    S: 'static,
+{
+    type Config = ();
+    type Result = Box<Future<Item = Self, Error = Error>>;
+
+    fn from_request(req: &HttpRequest<S>, _cfg: &Self::Config) -> Self::Result {
+        let req = req.clone();
+        Box::new(req.body().from_err().and_then(move |body| {
+            let token = form_urlencoded::parse(&body)
+                .find(|(key, _)| key == CSRF_TOKEN_FIELD)
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            if !verify_csrf_token(&req, &token) {
+                debug!("Invalid CSRF token: {:?}", req);
+                return Err(ErrorForbidden("Invalid CSRF token"));
+            }
+            serde_urlencoded::from_bytes::<T>(&body)
+                .map(CsrfForm)
+                .map_err(ErrorBadRequest)
+        }))
+    }
+}
 
 /// A phrase.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +92,13 @@ pub struct Phrase {
     pub extra: HashMap<String, String>,
 }
 
+/// Retraction or restoration of an entry.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Retraction {
+    /// Whether the entry should be retracted (hidden from clients).
+    pub retracted: bool,
+}
+
 /// A phrase request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhraseRequest {
@@ -41,13 +107,71 @@ pub struct PhraseRequest {
     /// Author's name.
     pub person: String,
     /// URL of the phrase if it is posted or published to the WWW.
-    #[serde(deserialize_with = "deserialize_optstr")]
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub url: Option<String>,
+    /// Whether the source web page is deleted or not.
+    #[serde(default)]
+    pub deleted: bool,
+    /// Datetime when the phrase is published.
+    #[serde(default, deserialize_with = "deserialize_isodate")]
+    pub published_at: Option<DateTime<Local>>,
+    /// Proposed tags.
+    #[serde(default)]
+    pub tags: Vec<RequestedTag>,
+}
+
+/// A tag proposed by a phrase request.
+///
+/// This is an existing tag ID or a free-text tag name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RequestedTag {
+    /// Existing tag ID.
+    Id(i32),
+    /// Free-text tag name.
+    Name(String),
+}
+
+/// Receipt of a phrase request, to check its status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseRequestReceipt {
+    /// Receipt token returned on submission.
+    pub token: String,
+}
+
+/// Approval of a phrase request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseApproval {
+    /// Title (short summary).
+    pub title: String,
+    /// Phrase.
+    pub phrase: String,
+    /// Author's person id (`None` to register a new person).
+    #[serde(default, deserialize_with = "deserialize_optid")]
+    pub person_id: Option<i32>,
+    /// Display name of the new person.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub new_person: Option<String>,
+    /// URL of the phrase if it is posted or published to the WWW.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
     pub url: Option<String>,
     /// Whether the source web page is deleted or not.
     pub deleted: bool,
     /// Datetime when the phrase is published.
-    #[serde(deserialize_with = "deserialize_isodate")]
+    #[serde(default, deserialize_with = "deserialize_optdate")]
     pub published_at: Option<DateTime<Local>>,
+    /// Extra form field.
+    ///
+    /// Contains selected tag_ids.
+    #[serde(flatten)]
+    pub extra: HashMap<String, String>,
+}
+
+/// Rejection of a phrase request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseRejection {
+    /// Reason why the request is rejected.
+    pub reason: String,
 }
 
 /// A person.
@@ -81,6 +205,68 @@ pub struct Tag {
     pub description: Option<String>,
 }
 
+/// Filter conditions of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    /// Name of the admin user.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub user: Option<String>,
+    /// Kind of the change.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub action: Option<String>,
+    /// Table of the changed entry.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub table: Option<String>,
+    /// Row ID of the changed entry.
+    #[serde(default, deserialize_with = "deserialize_optid")]
+    pub row_id: Option<i32>,
+    /// Lower bound (inclusive) of the datetime of the change.
+    #[serde(default, deserialize_with = "deserialize_optdate")]
+    pub since: Option<DateTime<Local>>,
+    /// Upper bound (exclusive) of the datetime of the change.
+    #[serde(default, deserialize_with = "deserialize_optdate")]
+    pub until: Option<DateTime<Local>>,
+    /// Returns records older than the record with the given ID if specified.
+    #[serde(default, deserialize_with = "deserialize_optid")]
+    pub before: Option<i32>,
+}
+
+/// Login of an admin user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Login {
+    /// User name.
+    pub name: String,
+    /// Password.
+    pub password: String,
+    /// Path to redirect to after login.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub next: Option<String>,
+    /// CSRF token, which should match the token in the login cookie.
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Query of the login page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginQuery {
+    /// Path to redirect to after login.
+    #[serde(default, deserialize_with = "deserialize_optstr")]
+    pub next: Option<String>,
+}
+
+/// A form without fields except for the CSRF token.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Empty {}
+
+/// Returns tag IDs selected in the form (`tags_*` fields).
+pub fn selected_tag_ids(extra: &HashMap<String, String>) -> Vec<i32> {
+    extra
+        .iter()
+        .filter(|(key, _)| (key.len() >= 5) & (&key[..5] == "tags_"))
+        .filter_map(|(_, value)| value.parse::<i32>().ok())
+        .collect::<Vec<_>>()
+}
+
 /// Custom deserializer for `Option<String>`.
 ///
 /// Convert `""` -> `None`, `$non_empty` -> `Some($non_empty)`.
@@ -143,11 +329,56 @@ where
                 Ok(Some(v.to_string()))
             }
         }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
     }
 
     deserializer.deserialize_any(OptstrVisitor)
 }
 
+/// Custom deserializer for `Option<i32>`.
+///
+/// Convert `""` -> `None`, `$id` -> `Some($id)`.
+fn deserialize_optid<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    struct OptidVisitor;
+
+    impl<'de> de::Visitor<'de> for OptidVisitor {
+        type Value = Option<i32>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an integer string")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if v.is_empty() {
+                Ok(None)
+            } else {
+                v.parse().map(Some).map_err(de::Error::custom)
+            }
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(OptidVisitor)
+}
+
 /// Custom deserializer for `Option<DateTime<Local>>`
 ///
 /// Convert YYYY-MM-DDThh:mm:ss format(maybe empty) -> `Option<DateTime<Local>>`
@@ -211,6 +442,13 @@ where
                 ))
             }
         }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
     }
 
     deserializer.deserialize_any(IsodateVisitor)
@@ -0,0 +1,154 @@
+//! Handler module for login of admin users.
+
+use std::sync::Arc;
+
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{AsyncResponder, Error, Form, FutureResponse, HttpRequest, HttpResponse, Query};
+use futures::future::{self, Future};
+use tera::{Context, Tera};
+use url::form_urlencoded;
+
+use admin::form::{self, CsrfForm};
+use admin::render;
+use app::{
+    end_admin_session, login_csrf_cookie, start_admin_session, verify_login_csrf_token,
+    AdminSession, AppState,
+};
+use db::{AdminRole, VerifyAdminLogin};
+
+/// Path of the login page.
+const LOGIN_PATH: &str = "/register/login/";
+
+/// Path to redirect to after login by default.
+const DEFAULT_NEXT_PATH: &str = "/register/";
+
+/// Returns the URL of the login page, which redirects to `next` after login.
+pub fn login_url(next: &str) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .append_pair("next", next)
+        .finish();
+    format!("{}?{}", LOGIN_PATH, query)
+}
+
+/// Returns the path to redirect to after login.
+///
+/// Only paths of the admin pages are allowed, so that the login page is not
+/// used to redirect users to other sites.
+fn next_path(next: &Option<String>) -> &str {
+    match *next {
+        Some(ref next) if next.starts_with(DEFAULT_NEXT_PATH) => next,
+        _ => DEFAULT_NEXT_PATH,
+    }
+}
+
+/// Renders the login page with a new CSRF token.
+///
+/// The token is also set to the cookie, since there are no admin sessions yet.
+fn render_login(
+    template: &Tera,
+    next: &Option<String>,
+    error: Option<&str>,
+) -> Result<HttpResponse, Error> {
+    let cookie = login_csrf_cookie()?;
+    let mut ctx = Context::new();
+    ctx.insert("next", next_path(next));
+    ctx.insert("error", &error);
+    ctx.insert("csrf_token", cookie.value());
+    let mut resp = render(template, &ctx, "register/login.html");
+    resp.add_cookie(&cookie)?;
+    Ok(resp)
+}
+
+/// Starts the session, and redirects to the next page.
+fn login_succeeded(
+    req: &HttpRequest<AppState>,
+    session: AdminSession,
+    next: &Option<String>,
+) -> Result<HttpResponse, Error> {
+    debug!("Admin user logged in: {:?}", session.name);
+    start_admin_session(req, &session)?;
+    Ok(HttpResponse::SeeOther()
+        .header(header::LOCATION, next_path(next))
+        .finish())
+}
+
+/// Processes the request for the login page.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(
+    req: HttpRequest<AppState>,
+    query: Query<form::LoginQuery>,
+) -> Result<HttpResponse, Error> {
+    debug!("request for `admin::login::index()`: {:?}", req);
+    render_login(req.state().template(), &query.next, None)
+}
+
+/// Processes the login query.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn login(req: HttpRequest<AppState>, form: Form<form::Login>) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::login::login()`: {:?}", req);
+    let form = form.into_inner();
+
+    if !verify_login_csrf_token(&req, &form.csrf_token) {
+        debug!("Invalid CSRF token for login: {:?}", form.name);
+        let res = render_login(
+            req.state().template(),
+            &form.next,
+            Some("ログインページの有効期限が切れました。もう一度入力してください"),
+        ).map(|mut resp| {
+            *resp.status_mut() = StatusCode::FORBIDDEN;
+            resp
+        });
+        return Box::new(future::result(res));
+    }
+
+    if req
+        .state()
+        .admin_auth()
+        .is_env_account(&form.name, &form.password)
+    {
+        let res = AdminSession::new(form.name.clone(), None)
+            .and_then(|session| login_succeeded(&req, session, &form.next));
+        return Box::new(future::result(res));
+    }
+
+    let template = Arc::clone(req.state().template());
+    let verify = req.state().db().send(VerifyAdminLogin {
+        name: form.name.clone(),
+        password: form.password.clone(),
+    });
+    verify
+        .from_err()
+        .and_then(move |res| {
+            let row = res.map_err(|e| {
+                error!("`admin::login::login()`: {}", e);
+                ErrorInternalServerError("DB error")
+            })?;
+            match row {
+                Some(ref row) if AdminRole::from_name(&row.role).is_some() => {
+                    let session = AdminSession::new(form.name.clone(), Some(row.modified_at))?;
+                    login_succeeded(&req, session, &form.next)
+                }
+                _ => {
+                    debug!("Admin login failed: {:?}", form.name);
+                    let mut resp = render_login(
+                        &template,
+                        &form.next,
+                        Some("ユーザ名またはパスワードが違います"),
+                    )?;
+                    *resp.status_mut() = StatusCode::UNAUTHORIZED;
+                    Ok(resp)
+                }
+            }
+        }).responder()
+}
+
+/// Processes the logout query, and redirects to the login page.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn logout(req: HttpRequest<AppState>, _form: CsrfForm<form::Empty>) -> HttpResponse {
+    debug!("request for `admin::login::logout()`: {:?}", req);
+    end_admin_session(&req);
+    HttpResponse::SeeOther()
+        .header(header::LOCATION, LOGIN_PATH)
+        .finish()
+}
@@ -2,13 +2,20 @@
 
 use std::sync::Arc;
 
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound};
+use actix_web::http::header;
 use actix_web::{AsyncResponder, Error, FutureResponse, HttpRequest, HttpResponse};
 use futures::future::Future;
 use tera::{Context, Tera};
 
-use app::AppState;
+use app::{admin_user, csrf_token, AppState};
+use db::{Audited, RetractTarget, SetRetracted};
 
+pub mod api;
+pub mod audit_log;
+mod diff;
 pub mod form;
+pub mod login;
 pub mod person;
 pub mod phrase;
 pub mod phrase_request;
@@ -30,18 +37,18 @@ fn render(template: &Tera, ctx: &Context, path: &str) -> HttpResponse {
 #[allow(unknown_lints, needless_pass_by_value)]
 pub fn index(req: HttpRequest<AppState>) -> HttpResponse {
     debug!("request for `db_update::index()`: {:?}", req);
-    render(
-        req.state().template(),
-        &Context::new(),
-        "register/index.html",
-    )
+    let mut ctx = Context::new();
+    ctx.insert("csrf_token", &csrf_token(&req));
+    render(req.state().template(), &ctx, "register/index.html")
 }
 
 /// Show list of rows for a table.
+///
+/// Rows are tuples of the row ID, the label, and whether the row is retracted.
 fn list_impl(
     template: Arc<Tera>,
     table_name: &str,
-    rows: impl 'static + Future<Item = Vec<(i32, String)>, Error = Error>,
+    rows: impl 'static + Future<Item = Vec<(i32, String, bool)>, Error = Error>,
 ) -> FutureResponse<HttpResponse> {
     let mut ctx = Context::new();
     ctx.insert("table_name", table_name);
@@ -50,3 +57,35 @@ fn list_impl(
         render(&template, &ctx, "register/list.html")
     }).responder()
 }
+
+/// Retracts or restores the entry, and redirects to its update form.
+fn retract_impl(
+    req: &HttpRequest<AppState>,
+    target: RetractTarget,
+    form: &form::Retraction,
+) -> FutureResponse<HttpResponse> {
+    let msg = Audited::new(
+        admin_user(req),
+        SetRetracted {
+            target,
+            retracted: form.retracted,
+        },
+    );
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(true) => Ok(HttpResponse::SeeOther()
+                .header(header::LOCATION, "../")
+                .finish()),
+            Ok(false) => {
+                debug!("Retract target not found: {:?}", target);
+                Err(ErrorNotFound("Not found"))
+            }
+            Err(e) => {
+                error!("`admin::retract_impl()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        }).responder()
+}
@@ -2,14 +2,15 @@
 
 use std::sync::Arc;
 
-use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
-use actix_web::{AsyncResponder, Form, FutureResponse, HttpRequest, HttpResponse, Path};
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Path};
 use futures::future::Future;
 use tera::Context;
 
-use admin::{form, list_impl, render};
-use app::AppState;
-use db::{upsert_entry, PersonQuery, PersonUrlQuery};
+use admin::form::CsrfForm;
+use admin::{form, list_impl, render, retract_impl};
+use app::{admin_user, csrf_token, AppState};
+use db::{upsert_entry, Audited, PersonQuery, PersonUrlQuery, RetractTarget};
 
 /// Processes the request for person registration index.
 #[allow(unknown_lints, needless_pass_by_value)]
@@ -23,7 +24,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
         .and_then(move |res| match res {
             Ok(contents) => Ok(contents
                 .into_iter()
-                .map(|row| (row.person_id, row.display_name))
+                .map(|row| (row.person_id, row.display_name, row.retracted_at.is_some()))
                 .collect::<Vec<_>>()),
             Err(e) => {
                 error!("`db_update::person_index()`: {}", e);
@@ -38,6 +39,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
 fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
+    let csrf_token = csrf_token(req);
 
     let additional: Box<dyn Future<Item = _, Error = _>> = match id {
         Some(person_id) => {
@@ -75,6 +77,7 @@ fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<Http
     additional
         .map(move |additional| {
             let mut ctx = Context::new();
+            ctx.insert("csrf_token", &csrf_token);
             if let Some((person, person_url)) = additional {
                 ctx.insert(person.0, &person.1);
                 ctx.insert(person_url.0, &person_url.1);
@@ -100,7 +103,10 @@ pub fn update(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<Htt
 
 /// Processes the person update query.
 #[allow(unknown_lints, needless_pass_by_value)]
-pub fn post(req: HttpRequest<AppState>, form: Form<form::Person>) -> FutureResponse<HttpResponse> {
+pub fn post(
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Person>,
+) -> FutureResponse<HttpResponse> {
     debug!("request for `admin::person::post()`: {:?}", req);
     let form_content = form.into_inner();
     debug!("receive form:\n{:#?}", &form_content);
@@ -121,17 +127,33 @@ pub fn post(req: HttpRequest<AppState>, form: Form<form::Person>) -> FutureRespo
     };
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
-    db.send(upsert_msg)
+    db.send(Audited::new(admin_user(&req), upsert_msg))
         .from_err()
         .and_then(move |res| match res {
-            Ok(_) => {
+            Ok(Some(_)) => {
                 let mut ctx = Context::new();
                 ctx.insert("person", &form_content);
                 Ok(render(&template, &ctx, "register/person/post.html"))
             }
+            Ok(None) => {
+                debug!("Person to be updated is not found.");
+                Err(ErrorNotFound("Person not found"))
+            }
             Err(e) => {
                 error!("`admin::person::update()`: {}", e);
                 Err(ErrorInternalServerError("DB error"))
             }
         }).responder()
 }
+
+/// Processes the person retraction or restoration query.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn retract(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Retraction>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::person::retract()`: {:?}", req);
+    let person_id = path.into_inner();
+    retract_impl(&req, RetractTarget::Person(person_id), &form)
+}
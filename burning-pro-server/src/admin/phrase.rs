@@ -2,14 +2,62 @@
 
 use std::sync::Arc;
 
-use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
-use actix_web::{AsyncResponder, Form, FutureResponse, HttpRequest, HttpResponse, Path};
-use futures::future::Future;
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound};
+use actix_web::http::header;
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Path};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures::future::{self, Future};
 use tera::Context;
 
-use admin::{form, list_impl, render};
-use app::AppState;
-use db::{upsert_entry, GoodPhraseQuery, GoodPhraseTagQuery, PersonQuery};
+use admin::diff::{diff_chars, DiffSegment};
+use admin::form::CsrfForm;
+use admin::{form, list_impl, render, retract_impl};
+use app::{admin_user, csrf_token, AppState};
+use db::upsert_entry::UpsertResult;
+use db::{
+    self, upsert_entry, Audited, GetGoodPhraseRevision, GetGoodPhraseRevisions, GoodPhraseQuery,
+    GoodPhraseRevision, GoodPhraseTagQuery, PersonQuery, RetractTarget,
+};
+
+/// A revision shown on the phrase update form.
+#[derive(Debug, Clone, Serialize)]
+struct RevisionView {
+    /// Revision.
+    revision: GoodPhraseRevision,
+    /// Diff of the title from the previous revision.
+    title_diff: Vec<DiffSegment>,
+    /// Diff of the phrase from the previous revision.
+    phrase_diff: Vec<DiffSegment>,
+    /// Whether the revision is the latest one.
+    latest: bool,
+}
+
+/// Returns revisions with diffs from their previous revisions.
+///
+/// `revisions` should be ordered from newest to oldest.
+fn revision_views(revisions: Vec<GoodPhraseRevision>) -> Vec<RevisionView> {
+    let diffs = revisions
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| {
+            // The oldest revision is compared with itself.
+            let previous = revisions.get(i + 1).unwrap_or(revision);
+            (
+                diff_chars(&previous.title, &revision.title),
+                diff_chars(&previous.phrase, &revision.phrase),
+            )
+        }).collect::<Vec<_>>();
+    revisions
+        .into_iter()
+        .zip(diffs)
+        .enumerate()
+        .map(|(i, (revision, (title_diff, phrase_diff)))| RevisionView {
+            revision,
+            title_diff,
+            phrase_diff,
+            latest: i == 0,
+        }).collect()
+}
 
 /// Processes the request for phrase registration index.
 #[allow(unknown_lints, needless_pass_by_value)]
@@ -23,7 +71,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
         .and_then(move |res| match res {
             Ok(contents) => Ok(contents
                 .into_iter()
-                .map(|row| (row.good_phrase_id, row.title))
+                .map(|row| (row.good_phrase_id, row.title, row.retracted_at.is_some()))
                 .collect::<Vec<_>>()),
             Err(e) => {
                 error!("`admin::phrase::index()`: {}", e);
@@ -38,6 +86,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
 fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
+    let csrf_token = csrf_token(req);
 
     let all_tag = db
         .send(GoodPhraseTagQuery::All)
@@ -87,7 +136,18 @@ fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<Http
                         Err(ErrorInternalServerError("DB error"))
                     }
                 });
-            Box::new(phrase.join(phrase_tag).map(Some))
+            let revisions = db
+                .send(GetGoodPhraseRevisions {
+                    good_phrase_id: phrase_id,
+                }).from_err()
+                .and_then(|res| match res {
+                    Ok(content) => Ok(("revisions", revision_views(content))),
+                    Err(e) => {
+                        error!("`admin::phrase::get_impl()`: {}", e);
+                        Err(ErrorInternalServerError("DB error"))
+                    }
+                });
+            Box::new(phrase.join3(phrase_tag, revisions).map(Some))
         }
         None => Box::new(futures::future::ok(None)),
     };
@@ -96,11 +156,13 @@ fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<Http
         .join3(all_tag, all_person)
         .map(move |(additional, all_tag, all_person)| {
             let mut ctx = Context::new();
+            ctx.insert("csrf_token", &csrf_token);
             ctx.insert(all_tag.0, &all_tag.1);
             ctx.insert(all_person.0, &all_person.1);
-            if let Some((phrase, phrase_tag)) = additional {
+            if let Some((phrase, phrase_tag, revisions)) = additional {
                 ctx.insert(phrase.0, &phrase.1);
                 ctx.insert(phrase_tag.0, &phrase_tag.1);
+                ctx.insert(revisions.0, &revisions.1);
             }
             render(&template, &ctx, "register/phrase/update.html")
         }).responder()
@@ -123,7 +185,10 @@ pub fn update(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<Htt
 
 /// Processes the phrase update query.
 #[allow(unknown_lints, needless_pass_by_value)]
-pub fn post(req: HttpRequest<AppState>, form: Form<form::Phrase>) -> FutureResponse<HttpResponse> {
+pub fn post(
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Phrase>,
+) -> FutureResponse<HttpResponse> {
     debug!("request for `admin::phrase::post()`: {:?}", req);
 
     let mut form_content = form.into_inner();
@@ -140,11 +205,7 @@ pub fn post(req: HttpRequest<AppState>, form: Form<form::Phrase>) -> FutureRespo
             published_at,
             extra,
         } => {
-            let tag_ids = extra
-                .iter()
-                .filter(|(key, _)| (key.len() >= 5) & (&key[..5] == "tags_"))
-                .filter_map(|(_, value)| value.parse::<i32>().ok())
-                .collect::<Vec<_>>();
+            let tag_ids = form::selected_tag_ids(&extra);
             upsert_entry::GoodPhrase {
                 good_phrase_id,
                 title,
@@ -160,17 +221,94 @@ pub fn post(req: HttpRequest<AppState>, form: Form<form::Phrase>) -> FutureRespo
 
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
-    db.send(upsert_msg)
+    db.send(Audited::new(admin_user(&req), upsert_msg))
         .from_err()
         .and_then(move |res| match res {
-            Ok(_) => {
+            Ok(UpsertResult::Upserted(_)) => {
                 let mut ctx = Context::new();
                 ctx.insert("phrase", &form_content);
                 Ok(render(&template, &ctx, "register/phrase/post.html"))
             }
+            Ok(UpsertResult::NotFound) => {
+                debug!("Phrase to be updated is not found.");
+                Err(ErrorNotFound("Phrase not found"))
+            }
+            Ok(UpsertResult::UnknownPerson(_)) => Err(ErrorBadRequest("Person not found")),
+            Ok(UpsertResult::UnknownTag(_)) => Err(ErrorBadRequest("Tag not found")),
             Err(e) => {
                 error!("`admin::phrase::update()`: {}", e);
                 Err(ErrorInternalServerError("DB error"))
             }
         }).responder()
 }
+
+/// Processes the phrase retraction or restoration query.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn retract(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Retraction>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase::retract()`: {:?}", req);
+    let phrase_id = path.into_inner();
+    retract_impl(&req, RetractTarget::GoodPhrase(phrase_id), &form)
+}
+
+/// Restores the phrase to the revision, and redirects to its update form.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn restore(
+    path: Path<(i32, i32)>,
+    req: HttpRequest<AppState>,
+    _form: CsrfForm<form::Empty>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase::restore()`: {:?}", req);
+    let (phrase_id, revision_id) = path.into_inner();
+
+    let db = req.state().db().clone();
+    let editor = admin_user(&req);
+    req.state()
+        .db()
+        .send(GetGoodPhraseRevision {
+            good_phrase_revision_id: revision_id,
+        }).from_err()
+        .and_then(move |res| match res {
+            Ok(Some(ref revision)) if revision.good_phrase_id == phrase_id => {
+                debug!("restore phrase to revision:\n{:#?}", revision);
+                future::Either::A(
+                    db.send(Audited::new(editor, revision.to_upsert()))
+                        .from_err()
+                        .and_then(|res| match res {
+                            Ok(UpsertResult::Upserted(_)) => Ok(HttpResponse::SeeOther()
+                                .header(header::LOCATION, "../../../")
+                                .finish()),
+                            Ok(UpsertResult::NotFound) => {
+                                debug!("Phrase to be restored is not found.");
+                                Err(ErrorNotFound("Phrase not found"))
+                            }
+                            Ok(UpsertResult::UnknownPerson(_)) => {
+                                Err(ErrorConflict("Person of the revision does not exist"))
+                            }
+                            Ok(UpsertResult::UnknownTag(_)) => {
+                                Err(ErrorConflict("Tag of the revision does not exist"))
+                            }
+                            Err(db::Error::Db(DieselError::DatabaseError(
+                                DatabaseErrorKind::UniqueViolation,
+                                _,
+                            ))) => Err(ErrorConflict("Another phrase has the same contents")),
+                            Err(e) => {
+                                error!("`admin::phrase::restore()`: {}", e);
+                                Err(ErrorInternalServerError("DB error"))
+                            }
+                        }),
+                )
+            }
+            Ok(_) => {
+                debug!("Revision not found.");
+                future::Either::B(future::err(ErrorNotFound("Revision not found")))
+            }
+            Err(e) => {
+                error!("`admin::phrase::restore()`: {}", e);
+                future::Either::B(future::err(ErrorInternalServerError("DB error")))
+            }
+        }).responder()
+}
@@ -2,14 +2,79 @@
 
 use std::sync::Arc;
 
-use actix_web::error::ErrorInternalServerError;
-use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json};
-use futures::future::Future;
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{
+    AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json, Path, Query,
+};
+use chrono::{DateTime, Local, TimeZone};
+use futures::future::{self, Future};
+use url::Url;
 
+use admin::form::CsrfForm;
 use admin::{form, render};
-use app::AppState;
-use db::{upsert_entry, GoodPhraseRequestQuery};
-use tera::Context;
+use app::{
+    admin_user, csrf_token, generate_secret_token, secret_token_digest, verify_secret_token,
+    AppState, FieldError, JsonError,
+};
+use db::upsert_entry::{self, GoodPhraseRequestResult};
+use db::{
+    ApprovePhraseRequest, Audited, GetPhraseRequest, GetPhraseRequestDraft,
+    GoodPhraseRequestQuery, GoodPhraseTagQuery, ModerationResult, PersonQuery,
+    RejectPhraseRequest, RequestedPerson,
+};
+use tera::{Context, Tera};
+
+/// Maximum length (in characters) of requested phrases.
+const MAX_PHRASE_LEN: usize = 1000;
+/// Maximum length (in characters) of requested person names.
+const MAX_PERSON_LEN: usize = 100;
+/// Maximum length (in bytes) of requested URLs.
+const MAX_URL_LEN: usize = 2048;
+/// Maximum number of proposed tags of a request.
+const MAX_TAGS: usize = 10;
+/// Maximum length (in characters) of proposed tag names.
+const MAX_TAG_NAME_LEN: usize = 50;
+/// Length (in characters) of titles pre-filled from requested phrases.
+const PREFILLED_TITLE_LEN: usize = 20;
+
+/// Receipt of a submitted phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+struct PhraseRequestReceipt {
+    /// Internal ID of the request.
+    internal_id: i32,
+    /// Secret token to check the status of the request.
+    receipt_token: String,
+}
+
+/// Status of a submitted phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+struct PhraseRequestStatus {
+    /// Internal ID of the request.
+    internal_id: i32,
+    /// Moderation status (`pending`, `accepted`, or `rejected`).
+    status: String,
+    /// Internal ID of the phrase registered from the request (if accepted).
+    good_phrase_id: Option<i32>,
+}
+
+/// Phrase form pre-filled from a phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+struct PhraseDraft {
+    /// Title (short summary).
+    title: String,
+    /// Phrase.
+    phrase: String,
+    /// Author's person id (`None` if no similar persons are found).
+    person_id: Option<i32>,
+    /// Author's name.
+    new_person: String,
+    /// URL of the phrase.
+    url: Option<String>,
+    /// Whether the source web page is deleted or not.
+    deleted: bool,
+    /// Datetime when the phrase is published, in the form format.
+    published_at: Option<String>,
+}
 
 /// Processes the request for phrase request registration index.
 #[allow(unknown_lints, needless_pass_by_value)]
@@ -39,41 +104,513 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     }).responder()
 }
 
-/// Processes the phrase request query
+/// Processes the request for phrase request moderation form.
+///
+/// The phrase form is pre-filled from the request, and the person similar to
+/// the requested one is selected if found.
 #[allow(unknown_lints, needless_pass_by_value)]
-pub fn post(
+pub fn moderate(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase_request::moderate()`: {:?}", req);
+
+    let db = req.state().db();
+    let template = Arc::clone(req.state().template());
+    let csrf_token = csrf_token(&req);
+
+    let draft = db
+        .send(GetPhraseRequestDraft {
+            good_phrase_request_id: path.into_inner(),
+        }).from_err()
+        .and_then(|res| match res {
+            Ok(Some(draft)) => Ok(draft),
+            Ok(None) => Err(ErrorNotFound("Phrase request not found")),
+            Err(e) => {
+                error!("`admin::phrase_request::moderate()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        });
+    let all_tag = db
+        .send(GoodPhraseTagQuery::All)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(content) => Ok(content),
+            Err(e) => {
+                error!("`admin::phrase_request::moderate()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        });
+    let all_person = db
+        .send(PersonQuery::All)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(content) => Ok(content),
+            Err(e) => {
+                error!("`admin::phrase_request::moderate()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        });
+
+    draft
+        .join3(all_tag, all_person)
+        .map(move |(draft, all_tag, all_person)| {
+            let request = draft.request;
+            let phrase = PhraseDraft {
+                title: request.phrase.chars().take(PREFILLED_TITLE_LEN).collect(),
+                phrase: request.phrase.clone(),
+                person_id: draft.person_id,
+                new_person: request.person.clone(),
+                url: request.url.clone(),
+                deleted: request.deleted,
+                // Formatted in the same timezone as the form is parsed in.
+                published_at: request.published_at.map(|dt| {
+                    Local
+                        .from_utc_datetime(&dt)
+                        .format("%Y-%m-%dT%H:%M:%S")
+                        .to_string()
+                }),
+            };
+            let mut ctx = Context::new();
+            ctx.insert("csrf_token", &csrf_token);
+            ctx.insert("request", &request);
+            ctx.insert("phrase", &phrase);
+            ctx.insert("phrase_tag_ids", &draft.tag_ids);
+            ctx.insert("new_tags", &draft.new_tags);
+            ctx.insert("all_tag", &all_tag);
+            ctx.insert("all_person", &all_person);
+            render(&template, &ctx, "register/phrase_request/moderate.html")
+        }).responder()
+}
+
+/// Processes the approval of a phrase request.
+///
+/// The phrase (and the person if not selected) is registered, and the request
+/// is marked as accepted.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn approve(
+    path: Path<i32>,
     req: HttpRequest<AppState>,
-    form: Json<form::PhraseRequest>,
+    form: CsrfForm<form::PhraseApproval>,
 ) -> FutureResponse<HttpResponse> {
-    debug!("request for `admin::phrase_request()`: {:?}", req);
+    debug!("request for `admin::phrase_request::approve()`: {:?}", req);
 
-    let mut form_content = form.into_inner();
-    form_content.phrase = form_content.phrase.trim().to_string();
-    debug!("receive form:\n{:#?}", &form_content);
-    let form::PhraseRequest {
+    let form::PhraseApproval {
+        title,
         phrase,
-        person,
+        person_id,
+        new_person,
         url,
         deleted,
         published_at,
-    } = form_content.to_owned();
-
-    let upsert_msg = upsert_entry::GoodPhraseRequest {
-        phrase,
+        extra,
+    } = form.into_inner();
+    let person = match (person_id, new_person) {
+        (Some(person_id), _) => RequestedPerson::Existing(person_id),
+        (None, Some(ref name)) if !name.trim().is_empty() => {
+            RequestedPerson::New(name.trim().to_owned())
+        }
+        (None, _) => {
+            return Box::new(future::err(ErrorBadRequest("Person is not specified")));
+        }
+    };
+    let msg = ApprovePhraseRequest {
+        good_phrase_request_id: path.into_inner(),
+        title: title.trim().to_owned(),
+        phrase: phrase.trim().to_owned(),
         person,
         url,
         deleted,
         published_at,
+        tag_ids: form::selected_tag_ids(&extra),
+    };
+    debug!("approve phrase request:\n{:#?}", &msg);
+
+    let db = req.state().db();
+    let template = Arc::clone(req.state().template());
+    db.send(Audited::new(admin_user(&req), msg))
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(result) => moderation_result(&template, result),
+            Err(e) => {
+                error!("`admin::phrase_request::approve()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        }).responder()
+}
+
+/// Processes the rejection of a phrase request.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn reject(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::PhraseRejection>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase_request::reject()`: {:?}", req);
+
+    let reason = form.into_inner().reason.trim().to_owned();
+    if reason.is_empty() {
+        return Box::new(future::err(ErrorBadRequest("Reason is empty")));
+    }
+    let msg = RejectPhraseRequest {
+        good_phrase_request_id: path.into_inner(),
+        reason,
+    };
+    debug!("reject phrase request:\n{:#?}", &msg);
+
+    let db = req.state().db();
+    let template = Arc::clone(req.state().template());
+    db.send(Audited::new(admin_user(&req), msg))
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(result) => moderation_result(&template, result),
+            Err(e) => {
+                error!("`admin::phrase_request::reject()`: {}", e);
+                Err(ErrorInternalServerError("DB error"))
+            }
+        }).responder()
+}
+
+/// Renders the result of moderation.
+fn moderation_result(
+    template: &Tera,
+    result: ModerationResult,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut ctx = Context::new();
+    match result {
+        ModerationResult::Accepted(good_phrase_id) => {
+            ctx.insert("good_phrase_id", &good_phrase_id);
+        }
+        ModerationResult::Rejected => {}
+        ModerationResult::NotFound => return Err(ErrorNotFound("Phrase request not found")),
+        ModerationResult::AlreadyModerated => {
+            return Err(ErrorConflict("Phrase request is already moderated"));
+        }
+        ModerationResult::UnknownPerson(_) => return Err(ErrorBadRequest("Person not found")),
+        ModerationResult::UnknownTag(_) => return Err(ErrorBadRequest("Tag not found")),
+    }
+    Ok(render(template, &ctx, "register/phrase_request/post.html"))
+}
+
+/// Processes the phrase request query
+///
+/// The ID of the request and the receipt token to check its status are
+/// responded.
+/// Invalid requests are rejected with `422 Unprocessable Entity`, and
+/// duplicates of existing phrases or requests are rejected with `409
+/// Conflict`.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn post(
+    req: HttpRequest<AppState>,
+    form: Json<form::PhraseRequest>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase_request()`: {:?}", req);
+
+    let form_content = form.into_inner();
+    debug!("receive form:\n{:#?}", &form_content);
+    let receipt_token = match generate_secret_token() {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let upsert_msg = match validate(form_content, &receipt_token, Local::now()) {
+        Ok(msg) => msg,
+        Err(errors) => {
+            debug!("Invalid phrase request: {:?}", errors);
+            return Box::new(future::err(JsonError::validation(&errors).into()));
+        }
     };
 
     let db = req.state().db();
     db.send(upsert_msg)
         .from_err()
         .and_then(move |res| match res {
-            Ok(_) => Ok(HttpResponse::Ok().into()),
+            Ok(GoodPhraseRequestResult::Inserted(good_phrase_request_id)) => {
+                Ok(HttpResponse::Ok().json(PhraseRequestReceipt {
+                    internal_id: good_phrase_request_id,
+                    receipt_token,
+                }))
+            }
+            Ok(GoodPhraseRequestResult::DuplicateGoodPhrase(good_phrase_id)) => {
+                debug!("Duplicate of good_phrase {}", good_phrase_id);
+                Err(JsonError::conflict("The phrase is already registered")
+                    .with_code("duplicate_good_phrase")
+                    .into())
+            }
+            Ok(GoodPhraseRequestResult::DuplicateRequest(request_id)) => {
+                debug!("Duplicate of phrase request {}", request_id);
+                Err(JsonError::conflict("The phrase is already requested")
+                    .with_code("duplicate_request")
+                    .into())
+            }
+            Ok(GoodPhraseRequestResult::UnknownTag(tag_id)) => {
+                let msg = format!("Tag {} does not exist", tag_id);
+                let errors = [FieldError::new("tags", "unknown_tag", msg)];
+                Err(JsonError::validation(&errors).into())
+            }
             Err(e) => {
                 error!("`admin::phrase_request::post()`: {}", e);
-                Err(ErrorInternalServerError("DB error"))
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for the status of a phrase request.
+///
+/// The receipt token returned on submission is required, and requests with
+/// wrong tokens are responded with `404 Not Found` as well as missing ones.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn status(
+    path: Path<i32>,
+    query: Query<form::PhraseRequestReceipt>,
+    req: HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::phrase_request::status()`: {:?}", req);
+
+    let good_phrase_request_id = path.into_inner();
+    let token = query.into_inner().token;
+    req.state()
+        .db()
+        .send(GetPhraseRequest {
+            good_phrase_request_id,
+        }).from_err()
+        .and_then(move |res| match res {
+            Ok(Some(request)) => {
+                let is_valid = match request.receipt_token_digest {
+                    Some(ref digest) => verify_secret_token(&token, digest),
+                    None => false,
+                };
+                if !is_valid {
+                    debug!("Invalid receipt token for request {}", good_phrase_request_id);
+                    return Err(JsonError::not_found("Phrase request not found").into());
+                }
+                Ok(HttpResponse::Ok().json(PhraseRequestStatus {
+                    internal_id: request.good_phrase_request_id,
+                    status: request.status,
+                    good_phrase_id: request.good_phrase_id,
+                }))
+            }
+            Ok(None) => Err(JsonError::not_found("Phrase request not found").into()),
+            Err(e) => {
+                error!("`admin::phrase_request::status()`: {}", e);
+                Err(JsonError::internal("DB error").into())
             }
         }).responder()
 }
+
+/// Validates the phrase request and converts it into the DB message.
+///
+/// Returns all errors found in the request if it is invalid.
+fn validate(
+    form: form::PhraseRequest,
+    receipt_token: &str,
+    now: DateTime<Local>,
+) -> Result<upsert_entry::GoodPhraseRequest, Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    let phrase = form.phrase.trim().to_owned();
+    validate_text(&mut errors, "phrase", &phrase, MAX_PHRASE_LEN);
+    let person = form.person.trim().to_owned();
+    validate_text(&mut errors, "person", &person, MAX_PERSON_LEN);
+
+    let url = form
+        .url
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty());
+    if let Some(ref url) = url {
+        if url.len() > MAX_URL_LEN {
+            let msg = format!("URL must be at most {} bytes", MAX_URL_LEN);
+            errors.push(FieldError::new("url", "too_long", msg));
+        } else {
+            match Url::parse(url) {
+                Ok(ref parsed)
+                    if (parsed.scheme() == "http" || parsed.scheme() == "https")
+                        && parsed.host().is_some() => {}
+                _ => errors.push(FieldError::new(
+                    "url",
+                    "invalid_url",
+                    "URL must be an absolute http or https URL",
+                )),
+            }
+        }
+    }
+
+    if let Some(published_at) = form.published_at {
+        if published_at > now {
+            errors.push(FieldError::new(
+                "published_at",
+                "future_datetime",
+                "Datetime must not be in the future",
+            ));
+        }
+    }
+
+    let mut tags = Vec::new();
+    if form.tags.len() > MAX_TAGS {
+        let msg = format!("At most {} tags can be proposed", MAX_TAGS);
+        errors.push(FieldError::new("tags", "too_many", msg));
+    }
+    for tag in form.tags {
+        let tag = match tag {
+            form::RequestedTag::Id(id) => upsert_entry::RequestedTag::Existing(id),
+            form::RequestedTag::Name(name) => {
+                let name = name.trim().to_owned();
+                validate_text(&mut errors, "tags", &name, MAX_TAG_NAME_LEN);
+                upsert_entry::RequestedTag::Proposed(name)
+            }
+        };
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(upsert_entry::GoodPhraseRequest {
+        phrase,
+        person,
+        url,
+        deleted: form.deleted,
+        published_at: form.published_at,
+        receipt_token_digest: secret_token_digest(receipt_token),
+        tags,
+    })
+}
+
+/// Validates the text is not empty and not too long.
+fn validate_text(errors: &mut Vec<FieldError>, field: &'static str, text: &str, max_len: usize) {
+    if text.is_empty() {
+        errors.push(FieldError::new(field, "empty", "Must not be empty"));
+    } else if text.chars().count() > max_len {
+        let msg = format!("Must be at most {} characters", max_len);
+        errors.push(FieldError::new(field, "too_long", msg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Duration;
+
+    /// Returns a valid phrase request.
+    fn request() -> form::PhraseRequest {
+        form::PhraseRequest {
+            phrase: " phrase ".to_owned(),
+            person: " person ".to_owned(),
+            url: Some("https://example.com/status/1".to_owned()),
+            deleted: false,
+            published_at: None,
+            tags: vec![
+                form::RequestedTag::Id(1),
+                form::RequestedTag::Name(" tag ".to_owned()),
+                form::RequestedTag::Id(1),
+            ],
+        }
+    }
+
+    /// Returns the field and the code of the errors.
+    fn codes(errors: &[FieldError]) -> Vec<(&'static str, &'static str)> {
+        errors.iter().map(|e| (e.field, e.code)).collect()
+    }
+
+    #[test]
+    fn valid_request_is_trimmed_and_deduplicated() {
+        let now = Local::now();
+        let mut form = request();
+        form.published_at = Some(now);
+        let msg = validate(form, "token", now).unwrap();
+        assert_eq!(msg.phrase, "phrase");
+        assert_eq!(msg.person, "person");
+        assert_eq!(msg.url, Some("https://example.com/status/1".to_owned()));
+        assert_eq!(msg.published_at, Some(now));
+        assert_eq!(msg.receipt_token_digest, secret_token_digest("token"));
+        assert_eq!(
+            msg.tags,
+            vec![
+                upsert_entry::RequestedTag::Existing(1),
+                upsert_entry::RequestedTag::Proposed("tag".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_url_is_omitted() {
+        let mut form = request();
+        form.url = Some("  ".to_owned());
+        assert_eq!(validate(form, "token", Local::now()).unwrap().url, None);
+    }
+
+    #[test]
+    fn empty_texts_are_rejected() {
+        let mut form = request();
+        form.phrase = "  ".to_owned();
+        form.person = String::new();
+        form.tags = vec![form::RequestedTag::Name(" ".to_owned())];
+        let errors = validate(form, "token", Local::now()).unwrap_err();
+        assert_eq!(
+            codes(&errors),
+            vec![("phrase", "empty"), ("person", "empty"), ("tags", "empty")]
+        );
+    }
+
+    #[test]
+    fn long_texts_are_rejected() {
+        let mut form = request();
+        // Lengths are counted in characters, not in bytes.
+        form.phrase = "あ".repeat(MAX_PHRASE_LEN);
+        form.person = "あ".repeat(MAX_PERSON_LEN);
+        assert!(validate(form.clone(), "token", Local::now()).is_ok());
+
+        form.phrase.push('a');
+        form.person.push('a');
+        form.tags = vec![form::RequestedTag::Name("a".repeat(MAX_TAG_NAME_LEN + 1))];
+        let errors = validate(form, "token", Local::now()).unwrap_err();
+        assert_eq!(
+            codes(&errors),
+            vec![
+                ("phrase", "too_long"),
+                ("person", "too_long"),
+                ("tags", "too_long"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_urls_are_rejected() {
+        for url in &[
+            "ftp://example.com/",
+            "javascript:alert(1)",
+            "mailto:someone@example.com",
+            "http:/",
+            "example.com/status/1",
+        ] {
+            let mut form = request();
+            form.url = Some(url.to_string());
+            let errors = validate(form, "token", Local::now()).unwrap_err();
+            assert_eq!(codes(&errors), vec![("url", "invalid_url")], "{}", url);
+        }
+
+        let mut form = request();
+        form.url = Some(format!("https://example.com/{}", "a".repeat(MAX_URL_LEN)));
+        let errors = validate(form, "token", Local::now()).unwrap_err();
+        assert_eq!(codes(&errors), vec![("url", "too_long")]);
+    }
+
+    #[test]
+    fn future_published_at_is_rejected() {
+        let now = Local::now();
+        let mut form = request();
+        form.published_at = Some(now + Duration::seconds(1));
+        let errors = validate(form, "token", now).unwrap_err();
+        assert_eq!(codes(&errors), vec![("published_at", "future_datetime")]);
+    }
+
+    #[test]
+    fn too_many_tags_are_rejected() {
+        let mut form = request();
+        form.tags = (0..MAX_TAGS as i32).map(form::RequestedTag::Id).collect();
+        assert!(validate(form.clone(), "token", Local::now()).is_ok());
+
+        form.tags.push(form::RequestedTag::Id(MAX_TAGS as i32));
+        let errors = validate(form, "token", Local::now()).unwrap_err();
+        assert_eq!(codes(&errors), vec![("tags", "too_many")]);
+    }
+}
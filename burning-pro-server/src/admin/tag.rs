@@ -2,14 +2,15 @@
 
 use std::sync::Arc;
 
-use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
-use actix_web::{AsyncResponder, Form, FutureResponse, HttpRequest, HttpResponse, Path};
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Path};
 use futures::future::Future;
 use tera::Context;
 
-use admin::{form, list_impl, render};
-use app::AppState;
-use db::{upsert_entry, GoodPhraseTagQuery};
+use admin::form::CsrfForm;
+use admin::{form, list_impl, render, retract_impl};
+use app::{admin_user, csrf_token, AppState};
+use db::{upsert_entry, Audited, GoodPhraseTagQuery, RetractTarget};
 
 /// Processes the request for tag registration index.
 #[allow(unknown_lints, needless_pass_by_value)]
@@ -23,7 +24,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
         .and_then(move |res| match res {
             Ok(contents) => Ok(contents
                 .into_iter()
-                .map(|row| (row.good_phrase_tag_id, row.name))
+                .map(|row| (row.good_phrase_tag_id, row.name, row.retracted_at.is_some()))
                 .collect::<Vec<_>>()),
             Err(e) => {
                 error!("`admin::tag::index()`: {}", e);
@@ -38,6 +39,7 @@ pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
 fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
+    let csrf_token = csrf_token(req);
 
     let additional: Box<dyn Future<Item = _, Error = _>> =
         match id {
@@ -64,6 +66,7 @@ fn get_impl(id: Option<i32>, req: &HttpRequest<AppState>) -> FutureResponse<Http
     additional
         .map(move |additional| {
             let mut ctx = Context::new();
+            ctx.insert("csrf_token", &csrf_token);
             if let Some(tag) = additional {
                 ctx.insert(tag.0, &tag.1);
             }
@@ -88,7 +91,10 @@ pub fn update(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<Htt
 
 /// Processes the tag update query.
 #[allow(unknown_lints, needless_pass_by_value)]
-pub fn post(req: HttpRequest<AppState>, form: Form<form::Tag>) -> FutureResponse<HttpResponse> {
+pub fn post(
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Tag>,
+) -> FutureResponse<HttpResponse> {
     debug!("request for `admin::tag::post()`: {:?}", req);
     let mut form_content = form.into_inner();
     form_content.description = form_content.description.map(|x| x.trim().to_string());
@@ -106,17 +112,33 @@ pub fn post(req: HttpRequest<AppState>, form: Form<form::Tag>) -> FutureResponse
     };
     let db = req.state().db();
     let template = Arc::clone(req.state().template());
-    db.send(upsert_msg)
+    db.send(Audited::new(admin_user(&req), upsert_msg))
         .from_err()
         .and_then(move |res| match res {
-            Ok(_) => {
+            Ok(Some(_)) => {
                 let mut ctx = Context::new();
                 ctx.insert("tag", &form_content);
                 Ok(render(&template, &ctx, "register/tag/post.html"))
             }
+            Ok(None) => {
+                debug!("Tag to be updated is not found.");
+                Err(ErrorNotFound("Tag not found"))
+            }
             Err(e) => {
                 error!("`admin::tag::update()`: {}", e);
                 Err(ErrorInternalServerError("DB error"))
             }
         }).responder()
 }
+
+/// Processes the tag retraction or restoration query.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn retract(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    form: CsrfForm<form::Retraction>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `admin::tag::retract()`: {:?}", req);
+    let tag_id = path.into_inner();
+    retract_impl(&req, RetractTarget::GoodPhraseTag(tag_id), &form)
+}
@@ -3,55 +3,319 @@
 use std::env;
 use std::error;
 
+use actix_web::error::Error;
+use actix_web::HttpRequest;
+use base64;
+use argon2;
+use ring::digest::{digest, SHA256};
+use ring::error::Unspecified;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use app::session::SESSION_KEY_MIN_LEN;
+use app::JsonError;
+use db::AdminRole;
+
+/// Name of the HTTP header for the anonymous device token of an app user.
+pub const DEVICE_TOKEN_HEADER: &str = "X-Device-Token";
+
+/// Maximum length of a device token.
+const DEVICE_TOKEN_MAX_LEN: usize = 256;
+
+/// Length (in bytes) of random secret tokens.
+const SECRET_TOKEN_LEN: usize = 32;
+
+/// Returns the device token of the app user sending the request.
+///
+/// The device token is an opaque string generated by the app, and identifies
+/// an app user anonymously.
+/// Returns `None` if the request has no valid device token.
+pub fn device_token<S>(req: &HttpRequest<S>) -> Option<String> {
+    let token = req.headers().get(DEVICE_TOKEN_HEADER)?.to_str().ok()?.trim();
+    if token.is_empty() || token.len() > DEVICE_TOKEN_MAX_LEN {
+        debug!("Invalid device token: {:?}", token);
+        return None;
+    }
+    Some(token.to_owned())
+}
+
+/// Returns the device token of the app user sending the request.
+///
+/// Returns `Err(_)` (bad request) if the request has no valid device token.
+pub fn require_device_token<S>(req: &HttpRequest<S>) -> Result<String, Error> {
+    device_token(req).ok_or_else(|| {
+        let msg = format!("`{}` header is required", DEVICE_TOKEN_HEADER);
+        JsonError::bad_request(msg).into()
+    })
+}
+
+/// Compares byte strings in constant time (for the same length).
+///
+/// This should be used to compare secrets to prevent timing attacks.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generates a random secret token, encoded as an URL-safe base64 string.
+///
+/// Only the digest of the token should be stored to the DB.
+pub fn generate_secret_token() -> Result<String, Error> {
+    let mut bytes = [0; SECRET_TOKEN_LEN];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| {
+        error!("Failed to generate a random token");
+        JsonError::internal("Failed to generate a token")
+    })?;
+    Ok(base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD))
+}
+
+/// Returns the digest (base64-encoded SHA-256) of the secret token.
+pub fn secret_token_digest(token: &str) -> String {
+    base64::encode(digest(&SHA256, token.as_bytes()).as_ref())
+}
+
+/// Checks whether the secret token matches the stored digest.
+pub fn verify_secret_token(token: &str, token_digest: &str) -> bool {
+    constant_time_eq(secret_token_digest(token).as_bytes(), token_digest.as_bytes())
+}
+
+/// Length (in bytes) of salts of password hashes.
+const PASSWORD_SALT_LEN: usize = 16;
+
+/// Returns the Argon2 parameters for new password hashes.
+///
+/// Argon2id with 19 MiB of memory and 2 passes.
+fn password_hash_config() -> argon2::Config<'static> {
+    argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        mem_cost: 19 * 1024,
+        time_cost: 2,
+        ..argon2::Config::default()
+    }
+}
+
+/// Hashes the password with the Argon2 parameters and a random salt.
+fn hash_password_with(password: &str, config: &argon2::Config) -> Result<String, Unspecified> {
+    let mut salt = [0; PASSWORD_SALT_LEN];
+    SystemRandom::new().fill(&mut salt)?;
+    argon2::hash_encoded(password.as_bytes(), &salt, config).map_err(|e| {
+        error!("Failed to hash the password: {}", e);
+        Unspecified
+    })
+}
+
+/// Hashes the password with Argon2id and a random salt.
+///
+/// The result is the standard PHC string
+/// (`$argon2id$v=19$m=<memory>,t=<passes>,p=<lanes>$<salt>$<hash>`), which
+/// has the parameters, so that they can be changed later.
+pub fn hash_password(password: &str) -> Result<String, Unspecified> {
+    hash_password_with(password, &password_hash_config())
+}
+
+/// Checks whether the password matches the hash made by `hash_password()`.
+///
+/// Returns `false` if the hash is malformed.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match argon2::verify_encoded(password_hash, password.as_bytes()) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Malformed password hash: {}", e);
+            false
+        }
+    }
+}
+
+/// Checks whether the password matches the hash of the user.
+///
+/// If the user is not found (`password_hash` is `None`), a password hash is
+/// computed anyway and `false` is returned, so that existence of users is not
+/// leaked by the response time.
+pub fn verify_user_password(password: &str, password_hash: Option<&str>) -> bool {
+    match password_hash {
+        Some(password_hash) => verify_password(password, password_hash),
+        None => {
+            let salt = [0; PASSWORD_SALT_LEN];
+            let _ = argon2::hash_raw(password.as_bytes(), &salt, &password_hash_config());
+            false
+        }
+    }
+}
+
+/// Authenticated admin user, stored in the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AdminUser {
+    /// User name.
+    pub name: String,
+    /// Role.
+    pub role: AdminRole,
+}
+
+/// Returns the name of the admin user sending the request.
+///
+/// Returns an empty string if the request is not authenticated as an admin
+/// user.
+pub fn admin_user<S>(req: &HttpRequest<S>) -> String {
+    req.extensions()
+        .get::<AdminUser>()
+        .map(|user| user.name.clone())
+        .unwrap_or_default()
+}
+
 /// Admin authenticator.
+///
+/// Users are stored in the `admin_users` table.
+/// Additionally, an account with the `moderator` role can be given by the
+/// environment variables, for example to create the first users.
 #[derive(Debug, Clone)]
 pub struct AdminAuthenticator {
-    /// Realm.
-    realm: String,
-    /// User name.
-    user: String,
-    /// Password.
-    password: Option<String>,
+    /// User name and password digest of the account given by the environment
+    /// variables.
+    env_account: Option<(String, String)>,
+    /// Key to sign session cookies.
+    session_key: Vec<u8>,
 }
 
 impl AdminAuthenticator {
     /// Creates a new admin authenticator.
-    pub fn new<R, U, P>(realm: R, user: U, password: P) -> Self
+    ///
+    /// `session_key` should have at least `SESSION_KEY_MIN_LEN` bytes.
+    pub fn new<A>(env_account: A, session_key: Vec<u8>) -> Self
     where
-        R: Into<String>,
-        U: Into<String>,
-        P: Into<Option<String>>,
+        A: Into<Option<(String, String)>>,
     {
         Self {
-            realm: realm.into(),
-            user: user.into(),
-            password: password.into(),
+            env_account: env_account
+                .into()
+                .map(|(user, password)| (user, secret_token_digest(&password))),
+            session_key,
         }
     }
 
     /// Creates a new admin authenticator from the environment variables.
-    pub fn from_env<R>(
-        realm: R,
+    ///
+    /// The account by the environment variables is disabled if the user name
+    /// is not set.
+    /// If the session key is not set, a random key is used and sessions are
+    /// invalidated when the server restarts.
+    pub fn from_env(
         user_env: &str,
         password_env: &str,
-    ) -> Result<Self, Box<error::Error + Send + Sync>>
-    where
-        R: Into<String>,
-    {
-        let user =
-            env::var(user_env).map_err(|e| format!("`{}` envvar must be set: {}", user_env, e))?;
+        session_key_env: &str,
+    ) -> Result<Self, Box<error::Error + Send + Sync>> {
+        let session_key = match env::var(session_key_env) {
+            Ok(v) => {
+                if v.len() < SESSION_KEY_MIN_LEN {
+                    let msg = format!(
+                        "`{}` envvar must have at least {} bytes",
+                        session_key_env, SESSION_KEY_MIN_LEN
+                    );
+                    return Err(msg.into());
+                }
+                v.into_bytes()
+            }
+            Err(env::VarError::NotPresent) => {
+                info!("`{}` envvar is not set, a random key is used", session_key_env);
+                let mut key = vec![0; SESSION_KEY_MIN_LEN];
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| "Failed to generate a session key")?;
+                key
+            }
+            Err(e) => {
+                return Err(format!("`{}` envvar is invalid: {}", session_key_env, e).into())
+            }
+        };
+        let user = match env::var(user_env) {
+            Ok(v) => v,
+            Err(env::VarError::NotPresent) => {
+                info!("`{}` envvar is not set, only `admin_users` are used", user_env);
+                return Ok(Self::new(None, session_key));
+            }
+            Err(e) => return Err(format!("`{}` envvar is invalid: {}", user_env, e).into()),
+        };
         let password = env::var(password_env)
             .map_err(|e| format!("`{}` envvar must be set: {}", password_env, e))?;
-        Ok(Self::new(realm.into(), user, Some(password)))
+        Ok(Self::new(Some((user, password)), session_key))
+    }
+
+    /// Returns the user name of the account given by the environment
+    /// variables.
+    pub fn env_user(&self) -> Option<&str> {
+        self.env_account.as_ref().map(|(user, _)| user.as_str())
     }
 
-    /// Returns the realm.
-    pub fn realm(&self) -> &str {
-        &self.realm
+    /// Checks whether the user is the account given by the environment
+    /// variables.
+    ///
+    /// Passwords are compared in constant time.
+    pub fn is_env_account(&self, user: &str, password: &str) -> bool {
+        match self.env_account {
+            Some((ref env_user, ref env_password_digest)) => {
+                env_user == user && verify_secret_token(password, env_password_digest)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the key to sign session cookies.
+    pub fn session_key(&self) -> &[u8] {
+        &self.session_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns cheap Argon2 parameters to keep tests fast.
+    fn cheap_config() -> argon2::Config<'static> {
+        argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            mem_cost: 64,
+            time_cost: 1,
+            ..argon2::Config::default()
+        }
+    }
+
+    #[test]
+    fn password_hash_round_trip() {
+        let hash = hash_password_with("correct horse", &cheap_config()).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("correct horse ", &hash));
+        assert!(!verify_password("", &hash));
+    }
+
+    #[test]
+    fn password_hashes_are_salted() {
+        let hash1 = hash_password_with("password", &cheap_config()).unwrap();
+        let hash2 = hash_password_with("password", &cheap_config()).unwrap();
+        assert_ne!(hash1, hash2);
+        assert!(verify_password("password", &hash1));
+        assert!(verify_password("password", &hash2));
+    }
+
+    #[test]
+    fn malformed_password_hashes_never_match() {
+        let hash = hash_password_with("password", &cheap_config()).unwrap();
+        let truncated = &hash[..hash.rfind('$').unwrap()];
+        let malformed = [
+            "",
+            "password",
+            truncated,
+            "$argon2id$v=19$m=64,t=1,p=1$!!!$!!!",
+            "$pbkdf2-sha256$100000$c2FsdA==$aGFzaA==",
+        ];
+        for malformed in &malformed {
+            assert!(!verify_password("password", malformed), "{:?}", malformed);
+        }
     }
 
-    /// Checks whether the authentication should be success.
-    pub fn is_authenticated(&self, user: &str, password: Option<&str>) -> bool {
-        self.user == user && self.password.as_ref().map(|s| s.as_ref()) == password
+    #[test]
+    fn unknown_users_never_match() {
+        assert!(!verify_user_password("", None));
+        assert!(!verify_user_password("password", None));
     }
 }
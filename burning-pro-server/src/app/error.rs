@@ -0,0 +1,268 @@
+//! Error types for JSON API.
+
+use std::time::Duration;
+
+use actix_web::error::ResponseError;
+use actix_web::http::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{Middleware, Response};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use serde_json::{self, Value};
+
+/// Error envelope of JSON API responses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ErrorEnvelope {
+    /// Error.
+    error: ErrorBody,
+}
+
+/// Error body of JSON API responses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ErrorBody {
+    /// Machine-readable error code, such as `not_found`.
+    code: String,
+    /// Human-readable error message.
+    message: String,
+    /// Additional information of the error (if any).
+    details: Option<Value>,
+}
+
+/// Error of a field of the request body.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct FieldError {
+    /// Field name.
+    pub field: &'static str,
+    /// Machine-readable error code, such as `too_long`.
+    pub code: &'static str,
+    /// Human-readable error message.
+    pub message: String,
+}
+
+impl FieldError {
+    /// Creates a new `FieldError`.
+    pub fn new<S: Into<String>>(field: &'static str, code: &'static str, message: S) -> Self {
+        Self {
+            field,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Details of rate limit errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+struct RetryDetails {
+    /// Seconds to wait before retrying.
+    retry_after: u64,
+}
+
+/// Details of validation errors.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+struct ValidationDetails<'a> {
+    /// Errors of fields.
+    fields: &'a [FieldError],
+}
+
+/// Error of JSON API, which is responded as JSON object.
+///
+/// The response body is
+/// `{"error": {"code": "...", "message": "...", "details": ...}}`.
+#[derive(Debug, Clone, PartialEq, Fail)]
+#[fail(display = "{}", message)]
+pub struct JsonError {
+    /// HTTP status code.
+    status: StatusCode,
+    /// Machine-readable error code.
+    code: &'static str,
+    /// Error message.
+    message: String,
+    /// Additional information of the error.
+    details: Option<Value>,
+    /// Seconds to wait before retrying (for rate limit errors).
+    retry_after: Option<u64>,
+}
+
+impl JsonError {
+    /// Creates a new `JsonError`.
+    ///
+    /// The error code is derived from the status code.
+    pub fn new<S: Into<String>>(status: StatusCode, message: S) -> Self {
+        Self {
+            status,
+            code: default_code(status),
+            message: message.into(),
+            details: None,
+            retry_after: None,
+        }
+    }
+
+    /// Creates a new `400 Bad Request` error.
+    pub fn bad_request<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// Creates a new `401 Unauthorized` error.
+    pub fn unauthorized<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    /// Creates a new `403 Forbidden` error.
+    pub fn forbidden<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    /// Creates a new `404 Not Found` error.
+    pub fn not_found<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// Creates a new `409 Conflict` error.
+    pub fn conflict<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    /// Creates a new `422 Unprocessable Entity` error for invalid fields.
+    pub fn validation(errors: &[FieldError]) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, "Validation failed")
+            .with_code("validation_failed")
+            .with_details(&ValidationDetails { fields: errors })
+    }
+
+    /// Creates a new `429 Too Many Requests` error.
+    ///
+    /// `wait` is sent as `Retry-After` header (rounded up to seconds) and
+    /// `retry_after` field of the details.
+    pub fn too_many_requests<S: Into<String>>(message: S, wait: Duration) -> Self {
+        let retry_after = wait.as_secs() + if wait.subsec_nanos() > 0 { 1 } else { 0 };
+        Self {
+            retry_after: Some(retry_after),
+            ..Self::new(StatusCode::TOO_MANY_REQUESTS, message)
+                .with_details(&RetryDetails { retry_after })
+        }
+    }
+
+    /// Creates a new `500 Internal Server Error` error.
+    pub fn internal<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// Sets the error code.
+    pub fn with_code(self, code: &'static str) -> Self {
+        Self { code, ..self }
+    }
+
+    /// Sets the additional information of the error.
+    pub fn with_details<T: Serialize>(self, details: &T) -> Self {
+        let details = match serde_json::to_value(details) {
+            Ok(details) => Some(details),
+            Err(e) => {
+                error!("Failed to serialize error details: {}", e);
+                None
+            }
+        };
+        Self { details, ..self }
+    }
+
+    /// Returns the HTTP status code.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the error code.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+}
+
+impl ResponseError for JsonError {
+    fn error_response(&self) -> HttpResponse {
+        let mut resp = HttpResponse::build(self.status);
+        if let Some(retry_after) = self.retry_after {
+            resp.header(RETRY_AFTER, retry_after.to_string());
+        }
+        resp.json(ErrorEnvelope {
+            error: ErrorBody {
+                code: self.code.to_owned(),
+                message: self.message.clone(),
+                details: self.details.clone(),
+            },
+        })
+    }
+}
+
+/// Returns the default error code for the status code.
+fn default_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+        status if status.is_server_error() => "internal_error",
+        _ => "error",
+    }
+}
+
+/// Middleware to respond errors as JSON objects.
+///
+/// Errors raised outside of handlers (such as extractor errors and routing
+/// errors) and errors without JSON body are converted into [`JsonError`]
+/// responses, so that clients always receive the same error format.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct JsonErrors;
+
+impl<S> Middleware<S> for JsonErrors {
+    fn response(&self, _req: &HttpRequest<S>, resp: HttpResponse) -> actix_web::Result<Response> {
+        let status = resp.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(Response::Done(resp));
+        }
+        let is_json = match resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+            Some(content_type) => content_type.starts_with("application/json"),
+            None => false,
+        };
+        if is_json {
+            return Ok(Response::Done(resp));
+        }
+        // Do not expose details of internal errors.
+        let message = match resp.error() {
+            Some(e) if !status.is_server_error() => e.to_string(),
+            _ => status.canonical_reason().unwrap_or("Error").to_owned(),
+        };
+        let mut new_resp = JsonError::new(status, message).error_response();
+        for (name, value) in resp.headers() {
+            let is_body_header = name == CONTENT_TYPE || name == CONTENT_LENGTH;
+            if !is_body_header && !new_resp.headers().contains_key(name) {
+                new_resp.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        Ok(Response::Done(new_resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `Retry-After` header of the rate limit error with the wait.
+    fn retry_after(wait: Duration) -> String {
+        let resp = JsonError::too_many_requests("Too many requests", wait).error_response();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        resp.headers()[RETRY_AFTER].to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn retry_after_is_rounded_up_to_seconds() {
+        assert_eq!(retry_after(Duration::from_secs(0)), "0");
+        assert_eq!(retry_after(Duration::new(0, 1)), "1");
+        assert_eq!(retry_after(Duration::from_secs(6)), "6");
+        assert_eq!(retry_after(Duration::new(5, 999_999_999)), "6");
+        assert_eq!(retry_after(Duration::from_millis(6500)), "7");
+    }
+}
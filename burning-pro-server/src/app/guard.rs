@@ -0,0 +1,53 @@
+//! Guards of unauthenticated endpoints.
+
+use actix_web::http::StatusCode;
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{HttpRequest, ResponseError};
+
+use app::auth::constant_time_eq;
+use app::{client_ip, AppState, JsonError};
+
+/// Name of the HTTP header for the shared app key.
+pub const APP_KEY_HEADER: &str = "X-App-Key";
+
+/// Middleware to protect unauthenticated endpoints from spam.
+///
+/// If the app key is configured, requests without the same key in
+/// `X-App-Key` header are rejected with `401 Unauthorized`.
+/// Other requests are rate-limited for each client IP address by the request
+/// limiter of the app state, and rejected with `429 Too Many Requests` if
+/// the limit is exceeded.
+/// Clients are identified by `client_ip()`, so forwarding headers set by
+/// clients are ignored unless the peer is a trusted proxy.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct RequestGuard;
+
+impl Middleware<AppState> for RequestGuard {
+    fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
+        let client = client_ip(req).unwrap_or_default();
+        if let Some(app_key) = req.state().app_key() {
+            let key = req
+                .headers()
+                .get(APP_KEY_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            if !constant_time_eq(key.as_bytes(), app_key.as_bytes()) {
+                debug!("Invalid app key from {:?}", client);
+                let e = JsonError::new(
+                    StatusCode::UNAUTHORIZED,
+                    format!("Valid `{}` header is required", APP_KEY_HEADER),
+                ).with_code("invalid_app_key");
+                return Ok(Started::Response(e.error_response()));
+            }
+        }
+
+        // Requests with invalid keys do not consume tokens of the client.
+        if let Err(wait) = req.state().request_limiter().check(&client) {
+            debug!("Too many requests from {:?}", client);
+            let e = JsonError::too_many_requests("Too many requests", wait);
+            return Ok(Started::Response(e.error_response()));
+        }
+
+        Ok(Started::Done)
+    }
+}
@@ -0,0 +1,59 @@
+//! HTTP caching (conditional requests).
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::header::{self, EntityTag, HttpDate};
+use actix_web::{HttpMessage, HttpRequest};
+use chrono::NaiveDateTime;
+
+/// Validators of a response, used for conditional requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validators {
+    /// Weak entity tag.
+    etag: EntityTag,
+    /// Datetime when the resource is last modified.
+    last_modified: Option<HttpDate>,
+}
+
+impl Validators {
+    /// Creates a new `Validators` from the fingerprint of the resource and
+    /// the UTC datetime when the resource is last modified.
+    pub fn new(fingerprint: u64, last_modified: Option<NaiveDateTime>) -> Self {
+        Self {
+            etag: EntityTag::weak(format!("{:016x}", fingerprint)),
+            // Note that `Last-Modified` has only second precision, so clients
+            // should prefer `ETag`.
+            last_modified: last_modified.map(|dt| {
+                HttpDate::from(UNIX_EPOCH + Duration::from_secs(dt.timestamp().max(0) as u64))
+            }),
+        }
+    }
+
+    /// Returns whether the client has the fresh response and `304 Not
+    /// Modified` can be returned.
+    ///
+    /// `If-None-Match` takes precedence over `If-Modified-Since`, as
+    /// specified in RFC 7232.
+    pub fn is_fresh<S>(&self, req: &HttpRequest<S>) -> bool {
+        match req.get_header::<header::IfNoneMatch>() {
+            Some(header::IfNoneMatch::Any) => return true,
+            Some(header::IfNoneMatch::Items(ref items)) => {
+                return items.iter().any(|item| item.weak_eq(&self.etag));
+            }
+            None => {}
+        }
+        match (self.last_modified, req.get_header::<header::IfModifiedSince>()) {
+            (Some(last_modified), Some(header::IfModifiedSince(since))) => last_modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Sets `ETag` and `Last-Modified` headers to the response.
+    pub fn set_headers(&self, resp: &mut HttpResponseBuilder) {
+        resp.set(header::ETag(self.etag.clone()));
+        if let Some(last_modified) = self.last_modified {
+            resp.set(header::LastModified(last_modified));
+        }
+    }
+}
@@ -1,7 +1,25 @@
 //! Server app.
 
 mod auth;
+mod error;
+mod guard;
+mod http_cache;
+mod rate_limit;
+mod session;
 pub mod state;
 
-pub use self::auth::AdminAuthenticator;
+pub use self::auth::{
+    admin_user, device_token, generate_secret_token, hash_password, require_device_token,
+    secret_token_digest, verify_password, verify_secret_token, verify_user_password,
+    AdminAuthenticator, AdminUser, DEVICE_TOKEN_HEADER,
+};
+pub use self::error::{FieldError, JsonError, JsonErrors};
+pub use self::http_cache::Validators;
+pub use self::guard::{RequestGuard, APP_KEY_HEADER};
+pub use self::rate_limit::{client_ip, IntervalLimiter, TokenBucketLimiter};
+pub use self::session::{
+    admin_session, admin_session_storage, csrf_token, end_admin_session, login_csrf_cookie,
+    start_admin_session, verify_csrf_token, verify_login_csrf_token, AdminSession,
+    SESSION_KEY_MIN_LEN,
+};
 pub use self::state::{AppState, AppStateBuilder};
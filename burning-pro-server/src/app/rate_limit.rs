@@ -0,0 +1,271 @@
+//! Rate limiters.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::HttpRequest;
+
+use app::AppState;
+
+/// Number of keys to start removing expired entries.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Name of the HTTP header for the addresses forwarded by proxies.
+const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+
+/// Returns the IP address of the client sending the request.
+///
+/// The address of the peer is used, because headers can be set freely by
+/// clients.
+/// Only if the peer is one of the trusted proxies of the app state,
+/// `X-Forwarded-For` header is respected, and the nearest address not of the
+/// trusted proxies is used.
+pub fn client_ip(req: &HttpRequest<AppState>) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    let trusted_proxies = req.state().trusted_proxies();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer.to_string());
+    }
+    // Each proxy appends the address of its peer, so addresses are read from
+    // the last one.
+    let forwarded = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>();
+    let mut client = peer;
+    for addr in forwarded.into_iter().rev() {
+        match addr.trim().parse::<IpAddr>() {
+            Ok(addr) => {
+                client = addr;
+                if !trusted_proxies.contains(&addr) {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    Some(client.to_string())
+}
+
+/// Rate limiter which allows an event at most once per interval for each key.
+///
+/// Clones share the same state, so a limiter can be shared among workers.
+#[derive(Debug, Clone)]
+pub struct IntervalLimiter {
+    /// Minimum interval between events with the same key.
+    interval: Duration,
+    /// Datetime of the last accepted event for each key.
+    last_accepted: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl IntervalLimiter {
+    /// Creates a new `IntervalLimiter`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_accepted: Default::default(),
+        }
+    }
+
+    /// Records an event with the given key if allowed.
+    ///
+    /// Returns `Err(wait)` if the event is rejected, where `wait` is the
+    /// duration until the next event with the key will be accepted.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Records an event with the given key at the given datetime if allowed.
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut last_accepted = self
+            .last_accepted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(&last) = last_accepted.get(key) {
+            let elapsed = now.duration_since(last);
+            if elapsed < self.interval {
+                return Err(self.interval - elapsed);
+            }
+        }
+        if last_accepted.len() >= PRUNE_THRESHOLD {
+            let interval = self.interval;
+            last_accepted.retain(|_, last| now.duration_since(*last) < interval);
+        }
+        last_accepted.insert(key.to_owned(), now);
+        Ok(())
+    }
+
+    /// Cancels the event with the given key accepted by the last `check()`.
+    ///
+    /// This is used when the event is rejected by another limiter after
+    /// accepted by this one.
+    /// The previous event with the key was at least `interval` before, so it
+    /// need not be restored.
+    pub fn refund(&self, key: &str) {
+        self.last_accepted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+    }
+}
+
+/// Token bucket of a key.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Number of tokens at `updated`.
+    tokens: f64,
+    /// Datetime when `tokens` is updated.
+    updated: Instant,
+}
+
+/// Rate limiter with a token bucket for each key.
+///
+/// Each bucket holds at most `capacity` tokens and gets a token per
+/// `refill_interval`.
+/// An event consumes a token, and is rejected if the bucket is empty.
+/// This allows bursts up to `capacity` events while limiting the long-term
+/// rate.
+///
+/// Clones share the same state, so a limiter can be shared among workers.
+#[derive(Debug, Clone)]
+pub struct TokenBucketLimiter {
+    /// Maximum number of tokens in a bucket.
+    capacity: f64,
+    /// Interval to add a token to buckets.
+    refill_interval: Duration,
+    /// Buckets for each key.
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl TokenBucketLimiter {
+    /// Creates a new `TokenBucketLimiter`.
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            refill_interval,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Returns the number of tokens in the bucket at the given datetime.
+    fn tokens_at(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = secs_f64(now.duration_since(bucket.updated));
+        let refilled = bucket.tokens + elapsed / secs_f64(self.refill_interval);
+        refilled.min(self.capacity)
+    }
+
+    /// Records an event with the given key if allowed.
+    ///
+    /// Returns `Err(wait)` if the event is rejected, where `wait` is the
+    /// duration until the next event with the key will be accepted.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Records an event with the given key at the given datetime if allowed.
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= PRUNE_THRESHOLD {
+            // Full buckets are same as missing ones.
+            let capacity = self.capacity;
+            buckets.retain(|_, bucket| self.tokens_at(bucket, now) < capacity);
+        }
+        let capacity = self.capacity;
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let tokens = self.tokens_at(bucket, now);
+        bucket.updated = now;
+        if tokens < 1.0 {
+            bucket.tokens = tokens;
+            let wait = (1.0 - tokens) * secs_f64(self.refill_interval);
+            return Err(Duration::new(
+                wait.trunc() as u64,
+                (wait.fract() * 1e9) as u32,
+            ));
+        }
+        bucket.tokens = tokens - 1.0;
+        Ok(())
+    }
+}
+
+/// Returns the duration in seconds.
+fn secs_f64(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) * 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts the wait is the expected one, ignoring rounding errors.
+    fn assert_wait(result: Result<(), Duration>, expected: Duration) {
+        let wait = secs_f64(result.unwrap_err());
+        let expected = secs_f64(expected);
+        assert!((wait - expected).abs() < 1e-6, "{} != {}", wait, expected);
+    }
+
+    #[test]
+    fn interval_limiter_accepts_an_event_per_interval() {
+        let limiter = IntervalLimiter::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        assert_eq!(limiter.check_at("b", start), Ok(()));
+        assert_eq!(
+            limiter.check_at("a", start + Duration::from_secs(4)),
+            Err(Duration::from_secs(6))
+        );
+        assert_eq!(limiter.check_at("a", start + Duration::from_secs(10)), Ok(()));
+    }
+
+    #[test]
+    fn interval_limiter_refunds_events() {
+        let limiter = IntervalLimiter::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        limiter.refund("a");
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        assert_eq!(limiter.check_at("a", start), Err(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn token_bucket_limiter_allows_bursts() {
+        let limiter = TokenBucketLimiter::new(3, Duration::from_secs(10));
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at("a", start), Ok(()));
+        }
+        assert_wait(limiter.check_at("a", start), Duration::from_secs(10));
+        // Buckets are independent for each key.
+        assert_eq!(limiter.check_at("b", start), Ok(()));
+    }
+
+    #[test]
+    fn token_bucket_limiter_refills_tokens() {
+        let limiter = TokenBucketLimiter::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+
+        // Rejected events do not consume tokens.
+        let t = start + Duration::from_secs(4);
+        assert_wait(limiter.check_at("a", t), Duration::from_secs(6));
+        let t = start + Duration::from_millis(7500);
+        assert_wait(limiter.check_at("a", t), Duration::from_millis(2500));
+        let t = start + Duration::from_secs(10);
+        assert_eq!(limiter.check_at("a", t), Ok(()));
+        assert_wait(limiter.check_at("a", t), Duration::from_secs(10));
+
+        // Tokens are refilled up to the capacity.
+        let t = start + Duration::from_secs(100);
+        assert_eq!(limiter.check_at("a", t), Ok(()));
+        assert_eq!(limiter.check_at("a", t), Ok(()));
+        assert_wait(limiter.check_at("a", t), Duration::from_secs(10));
+    }
+}
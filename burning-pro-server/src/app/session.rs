@@ -0,0 +1,154 @@
+//! Sessions of admin users.
+
+use actix_web::middleware::session::{CookieSessionBackend, RequestSession, SessionStorage};
+use actix_web::{Error, HttpRequest};
+use chrono::{Duration, NaiveDateTime, Utc};
+use cookie::{Cookie, SameSite};
+
+use app::auth::constant_time_eq;
+use app::{generate_secret_token, AppState};
+
+/// Name of the session cookie.
+const SESSION_COOKIE_NAME: &str = "burning-pro-admin";
+
+/// Path of the session cookie.
+const SESSION_COOKIE_PATH: &str = "/register";
+
+/// Key of the admin session in the session storage.
+const SESSION_KEY: &str = "admin";
+
+/// Lifetime of admin sessions (in seconds).
+const SESSION_LIFETIME_SECS: i64 = 12 * 60 * 60;
+
+/// Name of the cookie of the CSRF token for the login form.
+const LOGIN_CSRF_COOKIE_NAME: &str = "burning-pro-admin-login";
+
+/// Path of the cookie of the CSRF token for the login form.
+const LOGIN_CSRF_COOKIE_PATH: &str = "/register/login/";
+
+/// Lifetime of CSRF tokens for the login form (in seconds).
+const LOGIN_CSRF_LIFETIME_SECS: i64 = 60 * 60;
+
+/// Minimum length (in bytes) of the key to sign session cookies.
+pub const SESSION_KEY_MIN_LEN: usize = 32;
+
+/// Session of an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AdminSession {
+    /// User name.
+    pub name: String,
+    /// Last modified datetime of the user in the `admin_users` table.
+    ///
+    /// Sessions are invalidated when users are modified (for example, when
+    /// their passwords are reset).
+    /// `None` for the account given by the environment variables.
+    pub user_modified_at: Option<NaiveDateTime>,
+    /// Unix time when the session expires.
+    pub expires_at: i64,
+    /// Token to be sent with forms to prevent CSRF.
+    pub csrf_token: String,
+}
+
+impl AdminSession {
+    /// Creates a new session with a new CSRF token.
+    pub fn new(name: String, user_modified_at: Option<NaiveDateTime>) -> Result<Self, Error> {
+        Ok(Self {
+            name,
+            user_modified_at,
+            expires_at: Utc::now().timestamp() + SESSION_LIFETIME_SECS,
+            csrf_token: generate_secret_token()?,
+        })
+    }
+}
+
+/// Returns the middleware to store admin sessions into signed cookies.
+///
+/// `key` should have at least `SESSION_KEY_MIN_LEN` bytes.
+pub fn admin_session_storage(key: &[u8]) -> SessionStorage<CookieSessionBackend, AppState> {
+    SessionStorage::new(
+        CookieSessionBackend::signed(key)
+            .name(SESSION_COOKIE_NAME)
+            .path(SESSION_COOKIE_PATH)
+            // The server may be served over plain HTTP.
+            .secure(false)
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .max_age(Duration::seconds(SESSION_LIFETIME_SECS)),
+    )
+}
+
+/// Returns the admin session of the request.
+///
+/// Returns `None` if the request has no sessions or the session is expired.
+pub fn admin_session<S>(req: &HttpRequest<S>) -> Option<AdminSession> {
+    let session = match req.session().get::<AdminSession>(SESSION_KEY) {
+        Ok(v) => v?,
+        Err(e) => {
+            debug!("Invalid admin session: {}", e);
+            return None;
+        }
+    };
+    if session.expires_at <= Utc::now().timestamp() {
+        debug!("Admin session is expired: {:?}", session.name);
+        return None;
+    }
+    Some(session)
+}
+
+/// Starts the admin session, replacing the current one.
+pub fn start_admin_session<S>(req: &HttpRequest<S>, session: &AdminSession) -> Result<(), Error> {
+    let storage = req.session();
+    storage.clear();
+    storage.set(SESSION_KEY, session)
+}
+
+/// Ends the admin session.
+pub fn end_admin_session<S>(req: &HttpRequest<S>) {
+    req.session().clear();
+}
+
+/// Returns the CSRF token of the admin session.
+///
+/// Returns an empty string if the request has no valid sessions.
+pub fn csrf_token<S>(req: &HttpRequest<S>) -> String {
+    admin_session(req)
+        .map(|session| session.csrf_token)
+        .unwrap_or_default()
+}
+
+/// Checks whether the token matches the CSRF token of the admin session.
+///
+/// Tokens are compared in constant time.
+pub fn verify_csrf_token<S>(req: &HttpRequest<S>, token: &str) -> bool {
+    match admin_session(req) {
+        Some(session) => constant_time_eq(session.csrf_token.as_bytes(), token.as_bytes()),
+        None => false,
+    }
+}
+
+/// Returns a cookie with a new CSRF token for the login form.
+///
+/// Login requests have no admin sessions yet, so the token is bound to the
+/// browser by this cookie, and should also be sent in the form.
+pub fn login_csrf_cookie() -> Result<Cookie<'static>, Error> {
+    Ok(Cookie::build(LOGIN_CSRF_COOKIE_NAME, generate_secret_token()?)
+        .path(LOGIN_CSRF_COOKIE_PATH)
+        // The server may be served over plain HTTP.
+        .secure(false)
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(Duration::seconds(LOGIN_CSRF_LIFETIME_SECS))
+        .finish())
+}
+
+/// Checks whether the token matches the CSRF token in the login cookie.
+///
+/// Tokens are compared in constant time.
+pub fn verify_login_csrf_token<S>(req: &HttpRequest<S>, token: &str) -> bool {
+    match req.cookie(LOGIN_CSRF_COOKIE_NAME) {
+        Some(cookie) => {
+            !token.is_empty() && constant_time_eq(cookie.value().as_bytes(), token.as_bytes())
+        }
+        None => false,
+    }
+}
@@ -1,16 +1,51 @@
 //! Server app state.
 
 use std::error;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use actix::prelude::*;
 use diesel::prelude::*;
-use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::{self as diesel_r2d2, ConnectionManager};
 use r2d2;
 use tera::Tera;
 
-use app::AdminAuthenticator;
+use app::{AdminAuthenticator, IntervalLimiter, TokenBucketLimiter};
 use db::DbExecutor;
+use good_phrase::ranking::RankingCache;
+
+/// Minimum interval of phrase event reports from a client for the same phrase.
+const EVENT_REPORT_INTERVAL_SECS: u64 = 60;
+/// Maximum number of phrase event reports from an IP address for the same
+/// phrase in a burst.
+const EVENT_REPORT_IP_BURST: u32 = 10;
+/// Interval to allow another phrase event report from an IP address for the
+/// same phrase after a burst.
+const EVENT_REPORT_IP_REFILL_SECS: u64 = 6;
+
+/// Maximum number of phrase requests from a client in a burst.
+const PHRASE_REQUEST_BURST: u32 = 5;
+/// Interval to allow another phrase request from a client after a burst.
+const PHRASE_REQUEST_REFILL_SECS: u64 = 10 * 60;
+
+/// Time (in milliseconds) to wait for other connections to release locks.
+const SQLITE_BUSY_TIMEOUT_MS: u32 = 5000;
+
+/// Customizer of SQLite connections in the pool.
+///
+/// Without busy timeout, a query fails immediately with "database is locked"
+/// while another connection (such as background jobs) is writing.
+#[derive(Debug, Clone, Copy)]
+struct SqliteCustomizer;
+
+impl r2d2::CustomizeConnection<SqliteConnection, diesel_r2d2::Error> for SqliteCustomizer {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel_r2d2::Error> {
+        conn.execute(&format!("PRAGMA busy_timeout = {}", SQLITE_BUSY_TIMEOUT_MS))
+            .map(|_| ())
+            .map_err(diesel_r2d2::Error::QueryError)
+    }
+}
 
 /// Application-wide states.
 #[derive(Clone)]
@@ -21,6 +56,18 @@ pub struct AppState {
     template: Arc<Tera>,
     /// Admin authenticator.
     admin_auth: AdminAuthenticator,
+    /// Rate limiter for phrase event reports.
+    event_limiter: IntervalLimiter,
+    /// Rate limiter for phrase event reports from each IP address.
+    event_ip_limiter: TokenBucketLimiter,
+    /// Address of ranking cache actor.
+    ranking_cache: Addr<RankingCache>,
+    /// Rate limiter for phrase requests.
+    request_limiter: TokenBucketLimiter,
+    /// Shared app key required for phrase requests (if any).
+    app_key: Option<String>,
+    /// Addresses of reverse proxies whose forwarding headers are trusted.
+    trusted_proxies: Arc<Vec<IpAddr>>,
 }
 
 impl AppState {
@@ -38,6 +85,37 @@ impl AppState {
     pub fn admin_auth(&self) -> &AdminAuthenticator {
         &self.admin_auth
     }
+
+    /// Returns a rate limiter for phrase event reports.
+    pub fn event_limiter(&self) -> &IntervalLimiter {
+        &self.event_limiter
+    }
+
+    /// Returns a rate limiter for phrase event reports from each IP address.
+    pub fn event_ip_limiter(&self) -> &TokenBucketLimiter {
+        &self.event_ip_limiter
+    }
+
+    /// Returns an address for ranking cache actor.
+    pub fn ranking_cache(&self) -> &Addr<RankingCache> {
+        &self.ranking_cache
+    }
+
+    /// Returns a rate limiter for phrase requests.
+    pub fn request_limiter(&self) -> &TokenBucketLimiter {
+        &self.request_limiter
+    }
+
+    /// Returns the shared app key required for phrase requests (if any).
+    pub fn app_key(&self) -> Option<&str> {
+        self.app_key.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns addresses of reverse proxies whose forwarding headers are
+    /// trusted.
+    pub fn trusted_proxies(&self) -> &[IpAddr] {
+        &self.trusted_proxies
+    }
 }
 
 /// `AppState` builder.
@@ -54,6 +132,10 @@ pub struct AppStateBuilder {
     database_url: Option<String>,
     /// Admin authenticator.
     admin_auth: Option<AdminAuthenticator>,
+    /// Shared app key required for phrase requests.
+    app_key: Option<String>,
+    /// Addresses of trusted reverse proxies.
+    trusted_proxies: Vec<IpAddr>,
 }
 
 impl AppStateBuilder {
@@ -81,6 +163,27 @@ impl AppStateBuilder {
         }
     }
 
+    /// Sets the shared app key required for phrase requests.
+    ///
+    /// If not set, phrase requests are accepted without app keys.
+    pub fn app_key<S: Into<String>>(self, app_key: S) -> Self {
+        Self {
+            app_key: Some(app_key.into()),
+            ..self
+        }
+    }
+
+    /// Sets addresses of reverse proxies whose forwarding headers are trusted.
+    ///
+    /// If not set, forwarding headers are ignored, and clients are identified
+    /// by their peer addresses.
+    pub fn trusted_proxies(self, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            trusted_proxies,
+            ..self
+        }
+    }
+
     /// Builds the `AppState`.
     ///
     /// This method will ensure that all of required field values are set and
@@ -94,7 +197,9 @@ impl AppStateBuilder {
                 .database_url
                 .ok_or("`database_url` field is required but not set")?;
             let manager = ConnectionManager::<SqliteConnection>::new(database_url);
-            let pool = r2d2::Pool::builder().build(manager)?;
+            let pool = r2d2::Pool::builder()
+                .connection_customizer(Box::new(SqliteCustomizer))
+                .build(manager)?;
             SyncArbiter::start(3, move || DbExecutor::new(pool.clone()))
         };
         let template = {
@@ -104,10 +209,27 @@ impl AppStateBuilder {
         let admin_auth = self
             .admin_auth
             .ok_or("`admin_auth` field is required but not set")?;
+        let event_limiter =
+            IntervalLimiter::new(Duration::from_secs(EVENT_REPORT_INTERVAL_SECS));
+        let event_ip_limiter = TokenBucketLimiter::new(
+            EVENT_REPORT_IP_BURST,
+            Duration::from_secs(EVENT_REPORT_IP_REFILL_SECS),
+        );
+        let ranking_cache = RankingCache::new(db.clone()).start();
+        let request_limiter = TokenBucketLimiter::new(
+            PHRASE_REQUEST_BURST,
+            Duration::from_secs(PHRASE_REQUEST_REFILL_SECS),
+        );
         Ok(AppState {
             db,
             template,
             admin_auth,
+            event_limiter,
+            event_ip_limiter,
+            ranking_cache,
+            request_limiter,
+            app_key: self.app_key,
+            trusted_proxies: Arc::new(self.trusted_proxies),
         })
     }
 }
@@ -2,21 +2,38 @@
 
 extern crate burning_pro_server;
 
-use burning_pro_server::app::{AdminAuthenticator, AppState, AppStateBuilder};
-use burning_pro_server::{admin, good_phrase};
+use burning_pro_server::app::{
+    admin_session, admin_session_storage, end_admin_session, generate_secret_token,
+    hash_password, secret_token_digest, AdminAuthenticator, AdminUser, AppState, AppStateBuilder,
+    JsonError, JsonErrors, RequestGuard,
+};
+use burning_pro_server::db::{
+    AdminRole, CreateAdminApiToken, CreateAdminUser, GetAdminUser, ListAdminApiTokens,
+    ResetAdminUser, RevokeAdminApiToken, UseAdminApiToken,
+};
+use burning_pro_server::{admin, db, good_phrase, mylist, person, sync, tag};
 
 extern crate actix;
 extern crate actix_web;
-extern crate actix_web_httpauth;
+extern crate chrono;
 extern crate dotenv;
+extern crate futures;
 #[macro_use]
 extern crate log;
 extern crate pretty_env_logger;
 
 use std::env;
+use std::io;
+use std::net::IpAddr;
+use std::process;
 
+use actix::SystemRunner;
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ResponseError};
+use actix_web::http::{header, Method};
 use actix_web::middleware::{Logger, Middleware, Started};
-use actix_web::{server, App, HttpRequest};
+use actix_web::{server, App, HttpRequest, HttpResponse, Scope};
+use chrono::NaiveDateTime;
+use futures::Future;
 
 /// Setup global logger.
 fn setup_logger() {
@@ -44,9 +61,14 @@ fn fire(req: HttpRequest<AppState>) -> &'static str {
 }
 
 macro_rules! regist_form_handler {
-    ($root:path, $new:path, $update:path, $post:path) => {
+    ($root:path, $new:path, $update:path, $post:path, $retract:path) => {
+        regist_form_handler!($root, $new, $update, $post, $retract, |scope| scope)
+    };
+    ($root:path, $new:path, $update:path, $post:path, $retract:path, $extra:expr) => {
         |scope| {
-            scope
+            // Entries are editable by editors.
+            let scope = scope
+                .middleware(AdminAuth::new(AdminRole::Editor))
                 .resource("/", |r| r.with($root))
                 .resource("/new/", |r| {
                     r.get().with($new);
@@ -54,33 +76,400 @@ macro_rules! regist_form_handler {
                 }).resource("/{id}/", |r| {
                     r.get().with($update);
                     r.post().with($post);
-                })
+                }).resource("/{id}/retract/", |r| r.post().with($retract));
+            // Additional resources.
+            ($extra)(scope)
         }
     };
 }
 
+/// Registers resources of the public API to the app or the scope.
+macro_rules! public_api {
+    ($target:expr) => {
+        $target
+            .resource("/good_phrases/", |r| r.with(good_phrase::index))
+            .resource("/good_phrases/search/", |r| r.with(good_phrase::search))
+            .resource("/good_phrases/search/phrase/", |r| {
+                r.with(good_phrase::search_phrase)
+            }).resource("/good_phrases/random/", |r| r.with(good_phrase::random))
+            .resource("/good_phrases/daily/", |r| r.with(good_phrase::daily))
+            .resource("/good_phrases/ranking/", |r| r.with(good_phrase::ranking))
+            .resource("/good_phrases/{id}/", |r| r.get().with(good_phrase::show))
+            .resource("/good_phrases/{id}/favorite/", |r| {
+                r.put().with(good_phrase::favorite);
+                r.delete().with(good_phrase::unfavorite);
+            }).resource("/good_phrases/{id}/view/", |r| {
+                r.post().with(good_phrase::report_view)
+            }).resource("/good_phrases/{id}/use/", |r| {
+                r.post().with(good_phrase::report_use)
+            }).resource("/persons/", |r| r.get().with(person::index))
+            .resource("/persons/{id}/", |r| r.get().with(person::show))
+            .resource("/tags/", |r| r.get().with(tag::index))
+            .resource("/mylists/", |r| {
+                r.get().with(mylist::index);
+                r.post().with(mylist::create);
+            }).resource("/mylists/order/", |r| r.put().with(mylist::reorder))
+            .resource("/mylists/{id}/", |r| {
+                r.get().with(mylist::show);
+                r.put().with(mylist::rename);
+                r.delete().with(mylist::delete);
+            }).resource("/mylists/{id}/phrases/{good_phrase_id}/", |r| {
+                r.put().with(mylist::add_phrase);
+                r.delete().with(mylist::remove_phrase);
+            }).resource("/sync/", |r| r.get().with(sync::index))
+            .resource("/request/phrase_app/", |r| {
+                r.middleware(RequestGuard);
+                r.with(admin::phrase_request::post)
+            }).resource("/request/phrase_app/{id}/", |r| {
+                r.get().with(admin::phrase_request::status)
+            })
+    };
+}
+
 /// Admin auth middleware.
-#[derive(Default, Debug, Clone)]
-struct AdminAuth;
+///
+/// Authenticates the admin user by the session (if not yet authenticated by
+/// the middleware of the outer scope), and checks the role of the user.
+/// `GET` and `HEAD` requests are allowed for all roles, and others require
+/// `write_role`.
+#[derive(Debug, Clone, Copy)]
+struct AdminAuth {
+    /// Role required for requests other than `GET` and `HEAD`.
+    write_role: AdminRole,
+}
+
+impl AdminAuth {
+    /// Creates a new `AdminAuth`.
+    fn new(write_role: AdminRole) -> Self {
+        Self { write_role }
+    }
+
+    /// Checks whether the user has the role required for the request.
+    fn check_role(&self, req: &HttpRequest<AppState>, user: &AdminUser) -> actix_web::Result<()> {
+        let required = if is_safe_method(req) {
+            AdminRole::Viewer
+        } else {
+            self.write_role
+        };
+        if user.role.allows(required) {
+            Ok(())
+        } else {
+            debug!("Admin user {:?} is not allowed: {:?}", user, req);
+            Err(ErrorForbidden("Forbidden"))
+        }
+    }
+
+    /// Authenticates the user, and checks the role.
+    fn authenticate(&self, req: &HttpRequest<AppState>, user: AdminUser) -> actix_web::Result<()> {
+        self.check_role(req, &user)?;
+        req.extensions_mut().insert(user);
+        Ok(())
+    }
+}
+
+/// Checks whether the request method is `GET` or `HEAD`.
+fn is_safe_method(req: &HttpRequest<AppState>) -> bool {
+    *req.method() == Method::GET || *req.method() == Method::HEAD
+}
+
+/// Returns the response to the request without valid sessions.
+///
+/// `GET` and `HEAD` requests are redirected to the login page.
+fn login_required(req: &HttpRequest<AppState>) -> HttpResponse {
+    if is_safe_method(req) {
+        HttpResponse::SeeOther()
+            .header(header::LOCATION, admin::login::login_url(req.path()))
+            .finish()
+    } else {
+        HttpResponse::Unauthorized().body("Login required")
+    }
+}
 
 impl Middleware<AppState> for AdminAuth {
     fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
-        use actix_web::FromRequest;
-        use actix_web_httpauth::extractors::{
-            basic::{BasicAuth, Config},
-            AuthenticationError,
+        let user = req.extensions().get::<AdminUser>().cloned();
+        if let Some(user) = user {
+            self.check_role(req, &user)?;
+            return Ok(Started::Done);
+        }
+
+        let session = match admin_session(req) {
+            Some(v) => v,
+            None => return Ok(Started::Response(login_required(req))),
         };
+        if session.user_modified_at.is_none() {
+            // The account given by the environment variables.
+            if req.state().admin_auth().env_user() != Some(session.name.as_str()) {
+                debug!("Admin session is invalidated: {:?}", session.name);
+                end_admin_session(req);
+                return Ok(Started::Response(login_required(req)));
+            }
+            let user = AdminUser {
+                name: session.name,
+                role: AdminRole::Moderator,
+            };
+            self.authenticate(req, user)?;
+            return Ok(Started::Done);
+        }
+
+        let get_user = req.state().db().send(GetAdminUser {
+            name: session.name.clone(),
+        });
+        let req = req.clone();
+        let this = *self;
+        let fut = get_user.from_err().and_then(move |res| {
+            let row = res.map_err(|e| {
+                error!("`AdminAuth::start()`: {}", e);
+                ErrorInternalServerError("DB error")
+            })?;
+            let role = match row {
+                Some(ref row) if Some(row.modified_at) == session.user_modified_at => {
+                    AdminRole::from_name(&row.role)
+                }
+                _ => None,
+            };
+            let role = match role {
+                Some(role) => role,
+                None => {
+                    debug!("Admin session is invalidated: {:?}", session.name);
+                    end_admin_session(&req);
+                    return Ok(Some(login_required(&req)));
+                }
+            };
+            let user = AdminUser {
+                name: session.name,
+                role,
+            };
+            this.authenticate(&req, user)?;
+            Ok(None)
+        });
+        Ok(Started::Future(Box::new(fut)))
+    }
+}
 
-        let authenticator = req.state().admin_auth();
-        let mut config = Config::default();
-        config.realm(authenticator.realm());
-        let auth_info = BasicAuth::from_request(&req, &config)?;
+/// Admin API auth middleware.
+///
+/// Authenticates the admin user by the bearer token in the `Authorization`
+/// header, and checks the role of the user.
+#[derive(Debug, Clone, Copy)]
+struct AdminApiAuth {
+    /// Role required for the API.
+    role: AdminRole,
+}
 
-        if authenticator.is_authenticated(auth_info.username(), auth_info.password()) {
-            Ok(Started::Done)
-        } else {
-            Err(AuthenticationError::from(config).into())
+impl AdminApiAuth {
+    /// Creates a new `AdminApiAuth`.
+    fn new(role: AdminRole) -> Self {
+        Self { role }
+    }
+}
+
+/// Returns the response to the request without valid bearer tokens.
+fn token_required(message: &str) -> HttpResponse {
+    let mut resp = JsonError::unauthorized(message).error_response();
+    resp.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Bearer"),
+    );
+    resp
+}
+
+impl Middleware<AppState> for AdminApiAuth {
+    fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| {
+                let mut parts = v.splitn(2, ' ');
+                match (parts.next(), parts.next()) {
+                    (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("Bearer") => {
+                        Some(token.trim())
+                    }
+                    _ => None,
+                }
+            });
+        let token = match token {
+            Some(v) if !v.is_empty() => v,
+            _ => return Ok(Started::Response(token_required("Bearer token required"))),
+        };
+
+        let use_token = req.state().db().send(UseAdminApiToken {
+            token_digest: secret_token_digest(token),
+        });
+        let req = req.clone();
+        let required = self.role;
+        let fut = use_token.from_err().and_then(move |res| {
+            let row = res.map_err(|e| {
+                error!("`AdminApiAuth::start()`: {}", e);
+                JsonError::internal("DB error")
+            })?;
+            let user = match row {
+                Some(row) => AdminRole::from_name(&row.role).map(|role| AdminUser {
+                    name: row.name,
+                    role,
+                }),
+                None => None,
+            };
+            let user = match user {
+                Some(v) => v,
+                None => {
+                    debug!("Invalid admin API token");
+                    return Ok(Some(token_required("Invalid bearer token")));
+                }
+            };
+            if !user.role.allows(required) {
+                debug!("Admin user {:?} is not allowed: {:?}", user, req);
+                return Err(JsonError::forbidden("Forbidden").into());
+            }
+            req.extensions_mut().insert(user);
+            Ok(None)
+        });
+        Ok(Started::Future(Box::new(fut)))
+    }
+}
+
+/// Usage of subcommands.
+const USAGE: &str = "\
+Usage:
+    burning-pro-server
+    burning-pro-server admin-user create <name> <role>
+    burning-pro-server admin-user reset <name> [<role>]
+    burning-pro-server admin-token create <name> <label>
+    burning-pro-server admin-token list [<name>]
+    burning-pro-server admin-token revoke <id>
+
+Roles of admin users are `viewer`, `editor`, or `moderator`.
+Passwords of admin users are read from the stdin.
+Admin API tokens are printed only when they are created.";
+
+/// Reads a password from the stdin.
+fn read_password() -> Result<String, String> {
+    eprint!("Password: ");
+    let mut line = String::new();
+    io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read the password: {}", e))?;
+    let password = line.lines().next().unwrap_or_default().to_owned();
+    if password.is_empty() {
+        return Err("Password is empty".into());
+    }
+    hash_password(&password).map_err(|_| "Failed to hash the password".into())
+}
+
+/// Runs the subcommand to manage admin users.
+fn run_admin_user_command(
+    sys: &mut SystemRunner,
+    app_state: &AppState,
+    args: &[&str],
+) -> Result<(), String> {
+    let parse_role =
+        |name: &str| AdminRole::from_name(name).ok_or_else(|| format!("Unknown role: {:?}", name));
+    let (name, done) = match *args {
+        ["create", name, role] => {
+            let msg = CreateAdminUser {
+                name: name.to_owned(),
+                role: parse_role(role)?,
+                password_hash: read_password()?,
+            };
+            let done = sys.block_on(app_state.db().send(msg));
+            (name, done.map(|res| res.map(|created| (created, "already exists"))))
+        }
+        ["reset", name] | ["reset", name, _] => {
+            let msg = ResetAdminUser {
+                name: name.to_owned(),
+                role: match args.get(2) {
+                    Some(role) => Some(parse_role(role)?),
+                    None => None,
+                },
+                password_hash: read_password()?,
+            };
+            let done = sys.block_on(app_state.db().send(msg));
+            (name, done.map(|res| res.map(|reset| (reset, "is not found"))))
+        }
+        _ => return Err(USAGE.into()),
+    };
+    match done {
+        Ok(Ok((true, _))) => {
+            info!("Admin user {:?} is saved", name);
+            Ok(())
+        }
+        Ok(Ok((false, reason))) => Err(format!("Admin user {:?} {}", name, reason)),
+        Ok(Err(e)) => Err(format!("DB error: {}", e)),
+        Err(e) => Err(format!("DB error: {}", e)),
+    }
+}
+
+/// Runs the subcommand to manage admin API tokens.
+fn run_admin_token_command(
+    sys: &mut SystemRunner,
+    app_state: &AppState,
+    args: &[&str],
+) -> Result<(), String> {
+    match *args {
+        ["create", name, label] => {
+            let token = generate_secret_token().map_err(|_| "Failed to generate a token")?;
+            let msg = CreateAdminApiToken {
+                user_name: name.to_owned(),
+                token_digest: secret_token_digest(&token),
+                label: label.to_owned(),
+            };
+            match sys.block_on(app_state.db().send(msg)) {
+                Ok(Ok(Some(id))) => {
+                    info!("Admin API token {} is created for {:?}", id, name);
+                    println!("{}", token);
+                    Ok(())
+                }
+                Ok(Ok(None)) => Err(format!("Admin user {:?} is not found", name)),
+                Ok(Err(e)) => Err(format!("DB error: {}", e)),
+                Err(e) => Err(format!("DB error: {}", e)),
+            }
+        }
+        ["list"] | ["list", _] => {
+            let msg = ListAdminApiTokens {
+                user_name: args.get(1).map(|&name| name.to_owned()),
+            };
+            let tokens = match sys.block_on(app_state.db().send(msg)) {
+                Ok(Ok(v)) => v,
+                Ok(Err(e)) => return Err(format!("DB error: {}", e)),
+                Err(e) => return Err(format!("DB error: {}", e)),
+            };
+            for token in tokens {
+                let or_dash = |dt: Option<NaiveDateTime>| match dt {
+                    Some(dt) => dt.to_string(),
+                    None => "-".to_owned(),
+                };
+                println!(
+                    "{}\t{}\t{}\tcreated: {}\tlast used: {}\trevoked: {}",
+                    token.admin_api_token_id,
+                    token.user_name,
+                    token.label,
+                    token.created_at,
+                    or_dash(token.last_used_at),
+                    or_dash(token.revoked_at),
+                );
+            }
+            Ok(())
+        }
+        ["revoke", id] => {
+            let admin_api_token_id = id
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid token ID: {:?}", id))?;
+            let msg = RevokeAdminApiToken { admin_api_token_id };
+            match sys.block_on(app_state.db().send(msg)) {
+                Ok(Ok(true)) => {
+                    info!("Admin API token {} is revoked", admin_api_token_id);
+                    Ok(())
+                }
+                Ok(Ok(false)) => Err(format!(
+                    "Admin API token {} is not found or already revoked",
+                    admin_api_token_id
+                )),
+                Ok(Err(e)) => Err(format!("DB error: {}", e)),
+                Err(e) => Err(format!("DB error: {}", e)),
+            }
         }
+        _ => Err(USAGE.into()),
     }
 }
 
@@ -125,38 +514,125 @@ fn main() {
         }
     };
 
-    let sys = actix::System::new("burning-pro-server");
+    let mut sys = actix::System::new("burning-pro-server");
 
     let database_url = env::var("DATABASE_URL").expect("`DATABASE_URL` envvar must be set");
     info!("Database URL: {}", database_url);
-    let admin_auth = AdminAuthenticator::from_env(
-        "Burning Pro admin web UI",
-        "ADMIN_WEB_USER",
-        "ADMIN_WEB_PASSWORD",
-    ).expect("Failed to get admin web auth config");
-    let app_state = AppStateBuilder::new()
+    let admin_auth =
+        AdminAuthenticator::from_env("ADMIN_WEB_USER", "ADMIN_WEB_PASSWORD", "ADMIN_SESSION_KEY")
+            .expect("Failed to get admin web auth config");
+    let mut app_state_builder = AppStateBuilder::new()
         .database_url(database_url)
-        .admin_auth(admin_auth)
+        .admin_auth(admin_auth);
+    match env::var("APP_KEY") {
+        Ok(app_key) => app_state_builder = app_state_builder.app_key(app_key),
+        Err(env::VarError::NotPresent) => info!("No app key is set for phrase requests"),
+        Err(e) => {
+            error!("Envvar `$APP_KEY` has invalid value: {}", e);
+            panic!("Envvar `$APP_KEY` has invalid value: {}", e);
+        }
+    }
+    match env::var("TRUSTED_PROXIES") {
+        Ok(v) => {
+            let trusted_proxies = v
+                .split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(str::parse::<IpAddr>)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|e| {
+                    error!("Envvar `$TRUSTED_PROXIES` has invalid value: {}", e);
+                    panic!("Envvar `$TRUSTED_PROXIES` has invalid value: {}", e);
+                });
+            info!("Trusted proxies: {:?}", trusted_proxies);
+            app_state_builder = app_state_builder.trusted_proxies(trusted_proxies);
+        }
+        Err(env::VarError::NotPresent) => info!("No trusted proxies are set"),
+        Err(e) => {
+            error!("Envvar `$TRUSTED_PROXIES` has invalid value: {}", e);
+            panic!("Envvar `$TRUSTED_PROXIES` has invalid value: {}", e);
+        }
+    }
+    let app_state = app_state_builder
         .build()
         .expect("Failed to build application state");
 
+    // Subcommands.
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    if !args.is_empty() {
+        let res = match args[0] {
+            "admin-user" => run_admin_user_command(&mut sys, &app_state, &args[1..]),
+            "admin-token" => run_admin_token_command(&mut sys, &app_state, &args[1..]),
+            _ => Err(USAGE.into()),
+        };
+        if let Err(e) = res {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Phrases inserted before the n-gram index is created are not indexed yet.
+    actix::Arbiter::spawn(app_state.db().send(db::IndexPhraseNgrams).then(|res| {
+        match res {
+            Ok(Ok(num)) => info!("Added {} phrases to the n-gram index", num),
+            Ok(Err(e)) => error!("Failed to update the n-gram index: {}", e),
+            Err(e) => error!("Failed to update the n-gram index: {}", e),
+        }
+        Ok(())
+    }));
+
     info!("starting server ({})...", listen);
     server::new(move || {
-        App::with_state(app_state.clone())
+        let session_storage = admin_session_storage(app_state.admin_auth().session_key());
+        let app = App::with_state(app_state.clone())
             .middleware(Logger::default())
+            .middleware(session_storage)
             .resource("/", |r| r.with(fire))
-            .resource("/good_phrases/", |r| r.with(good_phrase::index))
-            .scope("/register", |scope| {
+            .scope("/api/v1", |scope| {
+                public_api!(scope.middleware(JsonErrors)).nested("/admin", |scope| {
+                    scope
+                        .middleware(AdminApiAuth::new(AdminRole::Editor))
+                        .resource("/phrases/", |r| r.post().with(admin::api::create_phrase))
+                        .resource("/phrases/{id}/", |r| {
+                            r.put().with(admin::api::update_phrase);
+                            r.delete().with(admin::api::delete_phrase);
+                        }).resource("/persons/", |r| r.post().with(admin::api::create_person))
+                        .resource("/persons/{id}/", |r| {
+                            r.put().with(admin::api::update_person);
+                            r.delete().with(admin::api::delete_person);
+                        }).resource("/tags/", |r| r.post().with(admin::api::create_tag))
+                        .resource("/tags/{id}/", |r| {
+                            r.put().with(admin::api::update_tag);
+                            r.delete().with(admin::api::delete_tag);
+                        })
+                })
+            })
+            // The login page should be registered before `/register` scope,
+            // which requires login.
+            .resource("/register/login/", |r| {
+                r.get().with(admin::login::index);
+                r.post().with(admin::login::login);
+            }).scope("/register", |scope| {
                 scope
-                    .middleware(AdminAuth)
+                    // Only authenticates users here, and nested scopes require
+                    // roles to edit.
+                    .middleware(AdminAuth::new(AdminRole::Viewer))
                     .resource("/", |r| r.with(admin::index))
+                    .resource("/logout/", |r| r.post().with(admin::login::logout))
                     .nested(
                         "/phrase",
                         regist_form_handler!(
                             admin::phrase::index,
                             admin::phrase::new,
                             admin::phrase::update,
-                            admin::phrase::post
+                            admin::phrase::post,
+                            admin::phrase::retract,
+                            |scope: Scope<_>| scope
+                                .resource("/{id}/revision/{revision_id}/restore/", |r| {
+                                    r.post().with(admin::phrase::restore)
+                                })
                         ),
                     ).nested(
                         "/tag",
@@ -164,7 +640,8 @@ fn main() {
                             admin::tag::index,
                             admin::tag::new,
                             admin::tag::update,
-                            admin::tag::post
+                            admin::tag::post,
+                            admin::tag::retract
                         ),
                     ).nested(
                         "/person",
@@ -172,14 +649,24 @@ fn main() {
                             admin::person::index,
                             admin::person::new,
                             admin::person::update,
-                            admin::person::post
+                            admin::person::post,
+                            admin::person::retract
                         ),
                     ).nested("/phrase_request", |scope| {
-                        scope.resource("/", |r| r.get().with(admin::phrase_request::index))
-                    })
-            }).scope("/request", |scope| {
-                scope.resource("/phrase_app/", |r| r.with(admin::phrase_request::post))
-            })
+                        scope
+                            .middleware(AdminAuth::new(AdminRole::Moderator))
+                            .resource("/", |r| r.get().with(admin::phrase_request::index))
+                            .resource("/{id}/", |r| {
+                                r.get().with(admin::phrase_request::moderate);
+                                r.post().with(admin::phrase_request::approve);
+                            }).resource("/{id}/reject/", |r| {
+                                r.post().with(admin::phrase_request::reject);
+                            })
+                    }).resource("/audit_log/", |r| r.get().with(admin::audit_log::index))
+            });
+        // Deprecated aliases of the public API without the version prefix.
+        // This scope should be registered last, since it matches all paths.
+        app.scope("", |scope| public_api!(scope.middleware(JsonErrors)))
     }).bind(&listen)
     .unwrap_or_else(|e| {
         panic!("Failed to bind {}: {}", listen, e);
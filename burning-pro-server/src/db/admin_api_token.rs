@@ -0,0 +1,217 @@
+//! Bearer tokens of admin users for the admin API.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use chrono::{Local, NaiveDateTime};
+use diesel;
+use diesel::prelude::*;
+
+use db::upsert_entry::last_insert_rowid;
+use db::{DbExecutor, Error};
+use models;
+
+/// A token shown to admins (without the digest).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct AdminApiTokenInfo {
+    /// Token ID.
+    pub admin_api_token_id: i32,
+    /// UTC datetime the token is created at.
+    pub created_at: NaiveDateTime,
+    /// Name of the owner.
+    pub user_name: String,
+    /// Label of the token.
+    pub label: String,
+    /// UTC datetime the token is last used at (if used).
+    pub last_used_at: Option<NaiveDateTime>,
+    /// UTC datetime the token is revoked at (if revoked).
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// A message type to create a token of an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CreateAdminApiToken {
+    /// Name of the owner.
+    pub user_name: String,
+    /// Digest of the token.
+    pub token_digest: String,
+    /// Label of the token.
+    pub label: String,
+}
+
+impl Message for CreateAdminApiToken {
+    /// Token ID, or `None` if the user is not found.
+    type Result = Result<Option<i32>, Error>;
+}
+
+impl Handler<CreateAdminApiToken> for DbExecutor {
+    type Result = <CreateAdminApiToken as Message>::Result;
+
+    fn handle(&mut self, msg: CreateAdminApiToken, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_api_tokens::table;
+        use schema::admin_users;
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+
+        // Use transaction to get correct `last_insert_rowid` result.
+        conn.transaction::<_, Error, _>(|| {
+            let admin_user_id = admin_users::table
+                .filter(admin_users::columns::name.eq(&msg.user_name))
+                .select(admin_users::columns::admin_user_id)
+                .first::<i32>(conn)
+                .optional()?;
+            let admin_user_id = match admin_user_id {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+            let new_row = models::NewAdminApiToken {
+                admin_api_token_id: None,
+                created_at: &now_utc,
+                modified_at: &now_utc,
+                admin_user_id,
+                token_digest: &msg.token_digest,
+                label: &msg.label,
+            };
+            diesel::insert_into(table).values(new_row).execute(conn)?;
+            let id = diesel::select(last_insert_rowid).get_result::<i32>(conn)?;
+            Ok(Some(id))
+        })
+    }
+}
+
+/// A message type to list tokens, ordered from newest to oldest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListAdminApiTokens {
+    /// Name of the owner (all users if `None`).
+    pub user_name: Option<String>,
+}
+
+impl Message for ListAdminApiTokens {
+    type Result = Result<Vec<AdminApiTokenInfo>, Error>;
+}
+
+impl Handler<ListAdminApiTokens> for DbExecutor {
+    type Result = <ListAdminApiTokens as Message>::Result;
+
+    fn handle(&mut self, msg: ListAdminApiTokens, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_api_tokens::{columns, table};
+        use schema::admin_users;
+
+        let conn = &self.pool().get()?;
+
+        let mut query = table
+            .inner_join(admin_users::table)
+            .select((
+                columns::admin_api_token_id,
+                columns::created_at,
+                admin_users::columns::name,
+                columns::label,
+                columns::last_used_at,
+                columns::revoked_at,
+            )).order(columns::admin_api_token_id.desc())
+            .into_boxed();
+        if let Some(user_name) = msg.user_name {
+            query = query.filter(admin_users::columns::name.eq(user_name));
+        }
+        let rows = query.load::<(
+            i32,
+            NaiveDateTime,
+            String,
+            String,
+            Option<NaiveDateTime>,
+            Option<NaiveDateTime>,
+        )>(conn)?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(admin_api_token_id, created_at, user_name, label, last_used_at, revoked_at)| {
+                    AdminApiTokenInfo {
+                        admin_api_token_id,
+                        created_at,
+                        user_name,
+                        label,
+                        last_used_at,
+                        revoked_at,
+                    }
+                },
+            ).collect())
+    }
+}
+
+/// A message type to revoke a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RevokeAdminApiToken {
+    /// Token ID.
+    pub admin_api_token_id: i32,
+}
+
+impl Message for RevokeAdminApiToken {
+    /// `Ok(false)` if the token is not found or already revoked.
+    type Result = Result<bool, Error>;
+}
+
+impl Handler<RevokeAdminApiToken> for DbExecutor {
+    type Result = <RevokeAdminApiToken as Message>::Result;
+
+    fn handle(&mut self, msg: RevokeAdminApiToken, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_api_tokens::{columns, table};
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+
+        let count = diesel::update(
+            table
+                .filter(columns::admin_api_token_id.eq(msg.admin_api_token_id))
+                .filter(columns::revoked_at.is_null()),
+        ).set((
+            columns::modified_at.eq(now_utc),
+            columns::revoked_at.eq(now_utc),
+        )).execute(conn)?;
+        Ok(count > 0)
+    }
+}
+
+/// A message type to get the owner of a token, and to record its use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UseAdminApiToken {
+    /// Digest of the token.
+    pub token_digest: String,
+}
+
+impl Message for UseAdminApiToken {
+    /// Owner of the token, or `None` if the token is not found or revoked.
+    type Result = Result<Option<models::AdminUser>, Error>;
+}
+
+impl Handler<UseAdminApiToken> for DbExecutor {
+    type Result = <UseAdminApiToken as Message>::Result;
+
+    fn handle(&mut self, msg: UseAdminApiToken, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_api_tokens::{columns, table};
+        use schema::admin_users;
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+
+        conn.transaction::<_, Error, _>(|| {
+            let row = table
+                .inner_join(admin_users::table)
+                .filter(columns::token_digest.eq(&msg.token_digest))
+                .filter(columns::revoked_at.is_null())
+                .select((columns::admin_api_token_id, admin_users::all_columns))
+                .first::<(i32, models::AdminUser)>(conn)
+                .optional()?;
+            let (admin_api_token_id, user) = match row {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+            diesel::update(table.find(admin_api_token_id))
+                .set(columns::last_used_at.eq(now_utc))
+                .execute(conn)?;
+            Ok(Some(user))
+        })
+    }
+}
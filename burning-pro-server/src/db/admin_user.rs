@@ -0,0 +1,248 @@
+//! Users of the admin web UI.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use std::fmt;
+
+use actix::prelude::*;
+use chrono::Local;
+use diesel;
+use diesel::prelude::*;
+
+use app::verify_user_password;
+use db::{DbExecutor, Error};
+use models;
+
+/// Role of admin users.
+///
+/// Roles are ordered by their permissions, i.e. a role has all permissions of
+/// smaller roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AdminRole {
+    /// Can only view the admin web UI.
+    Viewer,
+    /// Can also edit phrases, tags, and persons.
+    Editor,
+    /// Can also moderate phrase requests.
+    Moderator,
+}
+
+impl AdminRole {
+    /// All roles.
+    pub const ALL: &'static [AdminRole] =
+        &[AdminRole::Viewer, AdminRole::Editor, AdminRole::Moderator];
+
+    /// Returns the name stored in the DB.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Editor => "editor",
+            AdminRole::Moderator => "moderator",
+        }
+    }
+
+    /// Returns the role with the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        AdminRole::ALL.iter().cloned().find(|role| role.as_str() == name)
+    }
+
+    /// Checks whether the role has permissions of the given role.
+    pub fn allows(self, required: AdminRole) -> bool {
+        self >= required
+    }
+}
+
+/// A message type to get an admin user by the name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetAdminUser {
+    /// User name.
+    pub name: String,
+}
+
+impl Message for GetAdminUser {
+    type Result = Result<Option<models::AdminUser>, Error>;
+}
+
+impl Handler<GetAdminUser> for DbExecutor {
+    type Result = <GetAdminUser as Message>::Result;
+
+    fn handle(&mut self, msg: GetAdminUser, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_users::{columns, table};
+
+        let conn = &self.pool().get()?;
+
+        table
+            .filter(columns::name.eq(msg.name))
+            .first::<models::AdminUser>(conn)
+            .optional()
+            .map_err(Into::into)
+    }
+}
+
+/// A message type to verify the password of an admin user.
+///
+/// Password hashes are expensive to compute, so this is done on the DB
+/// executor threads instead of the HTTP workers.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct VerifyAdminLogin {
+    /// User name.
+    pub name: String,
+    /// Password.
+    pub password: String,
+}
+
+impl fmt::Debug for VerifyAdminLogin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The password should not be logged.
+        f.debug_struct("VerifyAdminLogin")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl Message for VerifyAdminLogin {
+    /// `Ok(None)` if the user is not found or the password does not match.
+    type Result = Result<Option<models::AdminUser>, Error>;
+}
+
+impl Handler<VerifyAdminLogin> for DbExecutor {
+    type Result = <VerifyAdminLogin as Message>::Result;
+
+    fn handle(&mut self, msg: VerifyAdminLogin, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_users::{columns, table};
+
+        let conn = &self.pool().get()?;
+
+        let row = table
+            .filter(columns::name.eq(&msg.name))
+            .first::<models::AdminUser>(conn)
+            .optional()?;
+        let verified = verify_user_password(
+            &msg.password,
+            row.as_ref().map(|row| row.password_hash.as_str()),
+        );
+        Ok(if verified { row } else { None })
+    }
+}
+
+/// A message type to create an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CreateAdminUser {
+    /// User name.
+    pub name: String,
+    /// Password hash.
+    pub password_hash: String,
+    /// Role.
+    pub role: AdminRole,
+}
+
+impl Message for CreateAdminUser {
+    /// `Ok(false)` if the user with the same name already exists.
+    type Result = Result<bool, Error>;
+}
+
+impl Handler<CreateAdminUser> for DbExecutor {
+    type Result = <CreateAdminUser as Message>::Result;
+
+    fn handle(&mut self, msg: CreateAdminUser, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_users::{columns, table};
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+
+        conn.transaction::<_, Error, _>(|| {
+            let count = table
+                .filter(columns::name.eq(&msg.name))
+                .count()
+                .get_result::<i64>(conn)?;
+            if count > 0 {
+                return Ok(false);
+            }
+            let new_row = models::NewAdminUser {
+                admin_user_id: None,
+                created_at: &now_utc,
+                modified_at: &now_utc,
+                name: &msg.name,
+                password_hash: &msg.password_hash,
+                role: msg.role.as_str(),
+            };
+            diesel::insert_into(table).values(new_row).execute(conn)?;
+            Ok(true)
+        })
+    }
+}
+
+/// A message type to reset the password (and the role) of an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResetAdminUser {
+    /// User name.
+    pub name: String,
+    /// New password hash.
+    pub password_hash: String,
+    /// New role (unchanged if `None`).
+    pub role: Option<AdminRole>,
+}
+
+impl Message for ResetAdminUser {
+    /// `Ok(false)` if the user is not found.
+    type Result = Result<bool, Error>;
+}
+
+impl Handler<ResetAdminUser> for DbExecutor {
+    type Result = <ResetAdminUser as Message>::Result;
+
+    fn handle(&mut self, msg: ResetAdminUser, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::admin_users::{columns, table};
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+
+        conn.transaction::<_, Error, _>(|| {
+            let target = table.filter(columns::name.eq(&msg.name));
+            let mut count = diesel::update(target)
+                .set((
+                    columns::password_hash.eq(&msg.password_hash),
+                    columns::modified_at.eq(now_utc),
+                )).execute(conn)?;
+            if let Some(role) = msg.role {
+                count = diesel::update(target)
+                    .set(columns::role.eq(role.as_str()))
+                    .execute(conn)?;
+            }
+            Ok(count > 0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::testing;
+
+    /// Inserts an admin user with the role name.
+    fn insert(conn: &SqliteConnection, name: &str, role: &str) -> QueryResult<usize> {
+        use schema::admin_users::table;
+
+        let now_utc = Local::now().naive_utc();
+        let new_row = models::NewAdminUser {
+            admin_user_id: None,
+            created_at: &now_utc,
+            modified_at: &now_utc,
+            name,
+            password_hash: "hash",
+            role,
+        };
+        diesel::insert_into(table).values(new_row).execute(conn)
+    }
+
+    #[test]
+    fn db_accepts_only_known_roles() {
+        let conn = testing::establish();
+        for role in AdminRole::ALL {
+            insert(&conn, role.as_str(), role.as_str()).unwrap();
+        }
+        assert!(insert(&conn, "admin", "admin").is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Audit log of changes made by admin users.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use chrono::{Local, NaiveDateTime};
+use diesel;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use db::{DbExecutor, Error};
+use models;
+use schema;
+
+/// Kind of changes recorded to the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditAction {
+    /// The entry is created.
+    Create,
+    /// The entry is updated.
+    Update,
+    /// The entry is retracted.
+    Retract,
+    /// The entry is restored from retraction.
+    Restore,
+    /// The phrase request is approved.
+    Approve,
+    /// The phrase request is rejected.
+    Reject,
+}
+
+impl AuditAction {
+    /// All actions.
+    pub const ALL: &'static [AuditAction] = &[
+        AuditAction::Create,
+        AuditAction::Update,
+        AuditAction::Retract,
+        AuditAction::Restore,
+        AuditAction::Approve,
+        AuditAction::Reject,
+    ];
+
+    /// Returns the name stored in the DB.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Retract => "retract",
+            AuditAction::Restore => "restore",
+            AuditAction::Approve => "approve",
+            AuditAction::Reject => "reject",
+        }
+    }
+}
+
+/// Table of entries recorded to the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditTable {
+    /// `good_phrases`.
+    GoodPhrases,
+    /// `persons`.
+    Persons,
+    /// `good_phrase_tags`.
+    GoodPhraseTags,
+    /// `good_phrase_requests`.
+    GoodPhraseRequests,
+}
+
+impl AuditTable {
+    /// All tables.
+    pub const ALL: &'static [AuditTable] = &[
+        AuditTable::GoodPhrases,
+        AuditTable::Persons,
+        AuditTable::GoodPhraseTags,
+        AuditTable::GoodPhraseRequests,
+    ];
+
+    /// Returns the table name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditTable::GoodPhrases => "good_phrases",
+            AuditTable::Persons => "persons",
+            AuditTable::GoodPhraseTags => "good_phrase_tags",
+            AuditTable::GoodPhraseRequests => "good_phrase_requests",
+        }
+    }
+}
+
+/// A message type to make a change as an admin user.
+///
+/// The change is recorded to the audit log in the same transaction.
+/// Admin write messages (such as `upsert_entry::GoodPhrase`) are handled only
+/// when wrapped by this type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Audited<M> {
+    /// Name of the admin user making the change.
+    pub editor: String,
+    /// Message to make the change.
+    pub msg: M,
+}
+
+impl<M> Audited<M> {
+    /// Creates a new `Audited`.
+    pub fn new<S: Into<String>>(editor: S, msg: M) -> Self {
+        Self {
+            editor: editor.into(),
+            msg,
+        }
+    }
+}
+
+impl<M: Message> Message for Audited<M> {
+    type Result = M::Result;
+}
+
+/// Serializes the row into a JSON object, without timestamps of the row.
+fn to_object<T: Serialize>(row: &T) -> Map<String, Value> {
+    // Models are always serialized as JSON objects.
+    let mut object = match serde_json::to_value(row) {
+        Ok(Value::Object(object)) => object,
+        _ => Map::new(),
+    };
+    object.remove("created_at");
+    object.remove("modified_at");
+    object
+}
+
+/// Returns the snapshot of the entry to be recorded to the audit log.
+///
+/// Phrases have IDs of their tags, and persons have their URLs.
+/// Returns `None` if the entry does not exist.
+pub(super) fn snapshot(
+    conn: &SqliteConnection,
+    table: AuditTable,
+    row_id: i32,
+) -> Result<Option<Map<String, Value>>, Error> {
+    let object = match table {
+        AuditTable::GoodPhrases => {
+            let row = match schema::good_phrases::table
+                .find(row_id)
+                .first::<models::GoodPhrase>(conn)
+                .optional()?
+            {
+                Some(row) => row,
+                None => return Ok(None),
+            };
+            let tag_ids = schema::good_phrases_and_tags::table
+                .filter(schema::good_phrases_and_tags::columns::good_phrase_id.eq(row_id))
+                .select(schema::good_phrases_and_tags::columns::good_phrase_tag_id)
+                .order(schema::good_phrases_and_tags::columns::good_phrase_tag_id.asc())
+                .load::<i32>(conn)?;
+            let mut object = to_object(&row);
+            object.insert("tag_ids".into(), tag_ids.into());
+            object
+        }
+        AuditTable::Persons => {
+            let row = match schema::persons::table
+                .find(row_id)
+                .first::<models::Person>(conn)
+                .optional()?
+            {
+                Some(row) => row,
+                None => return Ok(None),
+            };
+            let urls = schema::person_urls::table
+                .filter(schema::person_urls::columns::person_id.eq(row_id))
+                .select(schema::person_urls::columns::url)
+                .order(schema::person_urls::columns::url.asc())
+                .load::<String>(conn)?;
+            let mut object = to_object(&row);
+            object.insert("url".into(), urls.into());
+            object
+        }
+        AuditTable::GoodPhraseTags => match schema::good_phrase_tags::table
+            .find(row_id)
+            .first::<models::GoodPhraseTag>(conn)
+            .optional()?
+        {
+            Some(row) => to_object(&row),
+            None => return Ok(None),
+        },
+        AuditTable::GoodPhraseRequests => match schema::good_phrase_requests::table
+            .find(row_id)
+            .first::<models::GoodPhraseRequest>(conn)
+            .optional()?
+        {
+            Some(row) => to_object(&row),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(object))
+}
+
+/// Records the change of the entry to the audit log.
+///
+/// Only fields changed between the snapshots are recorded, and nothing is
+/// recorded if no fields are changed.
+/// `before` should be `None` for created entries.
+pub(super) fn record(
+    conn: &SqliteConnection,
+    editor: &str,
+    action: AuditAction,
+    table: AuditTable,
+    row_id: i32,
+    before: Option<Map<String, Value>>,
+    after: Option<Map<String, Value>>,
+) -> Result<(), Error> {
+    let (old_values, new_values) = match (before, after) {
+        (Some(before), Some(after)) => {
+            let mut old_values = Map::new();
+            let mut new_values = Map::new();
+            for (key, old_value) in &before {
+                let new_value = after.get(key).cloned().unwrap_or(Value::Null);
+                if *old_value != new_value {
+                    old_values.insert(key.clone(), old_value.clone());
+                    new_values.insert(key.clone(), new_value);
+                }
+            }
+            for (key, new_value) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+                old_values.insert(key.clone(), Value::Null);
+                new_values.insert(key.clone(), new_value.clone());
+            }
+            if new_values.is_empty() {
+                return Ok(());
+            }
+            (Some(old_values), Some(new_values))
+        }
+        (before, after) => (before, after),
+    };
+    let old_values = old_values.map(|v| Value::Object(v).to_string());
+    let new_values = new_values.map(|v| Value::Object(v).to_string());
+
+    let now_utc = Local::now().naive_utc();
+    let new_row = models::NewAuditLog {
+        audit_log_id: None,
+        created_at: &now_utc,
+        modified_at: &now_utc,
+        user_name: editor,
+        action: action.as_str(),
+        table_name: table.as_str(),
+        row_id,
+        old_values: old_values.as_ref().map(AsRef::as_ref),
+        new_values: new_values.as_ref().map(AsRef::as_ref),
+    };
+    diesel::insert_into(schema::audit_log::table)
+        .values(new_row)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Conditions to filter the audit log.
+///
+/// Default value matches all records.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditLogFilter {
+    /// Name of the admin user.
+    pub user_name: Option<String>,
+    /// Kind of the change.
+    pub action: Option<AuditAction>,
+    /// Table of the changed entry.
+    pub table: Option<AuditTable>,
+    /// Row ID of the changed entry.
+    pub row_id: Option<i32>,
+    /// Lower bound (inclusive) of the UTC datetime of the change.
+    pub since: Option<NaiveDateTime>,
+    /// Upper bound (exclusive) of the UTC datetime of the change.
+    pub until: Option<NaiveDateTime>,
+}
+
+/// A message type to get records of the audit log.
+///
+/// Records are ordered from newest to oldest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetAuditLog {
+    /// Filter conditions.
+    pub filter: AuditLogFilter,
+    /// Returns records older than the record with the given ID if specified.
+    pub before_id: Option<i32>,
+    /// Maximum number of records to return.
+    pub limit: i64,
+}
+
+impl Message for GetAuditLog {
+    type Result = Result<Vec<models::AuditLog>, Error>;
+}
+
+impl Handler<GetAuditLog> for DbExecutor {
+    type Result = <GetAuditLog as Message>::Result;
+
+    fn handle(&mut self, msg: GetAuditLog, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::audit_log::{columns, table};
+
+        let conn = &self.pool().get()?;
+        let GetAuditLog {
+            filter,
+            before_id,
+            limit,
+        } = msg;
+
+        let mut query = table.into_boxed();
+        if let Some(user_name) = filter.user_name {
+            query = query.filter(columns::user_name.eq(user_name));
+        }
+        if let Some(action) = filter.action {
+            query = query.filter(columns::action.eq(action.as_str()));
+        }
+        if let Some(audit_table) = filter.table {
+            query = query.filter(columns::table_name.eq(audit_table.as_str()));
+        }
+        if let Some(row_id) = filter.row_id {
+            query = query.filter(columns::row_id.eq(row_id));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(columns::created_at.ge(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(columns::created_at.lt(until));
+        }
+        if let Some(before_id) = before_id {
+            query = query.filter(columns::audit_log_id.lt(before_id));
+        }
+        query
+            .order(columns::audit_log_id.desc())
+            .limit(limit)
+            .load::<models::AuditLog>(conn)
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,173 @@
+//! `GetFeedVersion` message.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamp};
+
+use db::{DbExecutor, Error};
+
+/// Tables whose rows appear in the good_phrases feed.
+///
+/// Rows of `tombstones` are inserted when rows are deleted from some of these
+/// tables, so that deletions change the last modified datetime.
+const FEED_TABLES: &[&str] = &[
+    "good_phrases",
+    "persons",
+    "person_urls",
+    "good_phrase_tags",
+    "good_phrases_and_tags",
+    "good_phrase_uses",
+    "good_phrase_favorites",
+    "mylists",
+    "mylist_entries",
+    "tombstones",
+];
+
+/// Version of the good_phrases feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeedVersion {
+    /// Fingerprint of the row counts and the last modified datetimes of the
+    /// tables.
+    ///
+    /// This changes when rows are inserted, updated, or deleted.
+    pub fingerprint: u64,
+    /// UTC datetime when the rows are last modified, or `None` if all tables
+    /// are empty.
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+/// Row count and last modified datetime of a table.
+#[derive(Debug, Clone, QueryableByName)]
+struct TableVersion {
+    /// Table name.
+    #[sql_type = "Text"]
+    table_name: String,
+    /// Number of rows.
+    #[sql_type = "BigInt"]
+    count: i64,
+    /// Last modified datetime of rows.
+    #[sql_type = "Nullable<Timestamp>"]
+    last_modified: Option<NaiveDateTime>,
+}
+
+/// A message type to get the version of the good_phrases feed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetFeedVersion;
+
+impl Message for GetFeedVersion {
+    type Result = Result<FeedVersion, Error>;
+}
+
+impl Handler<GetFeedVersion> for DbExecutor {
+    type Result = <GetFeedVersion as Message>::Result;
+
+    fn handle(&mut self, _msg: GetFeedVersion, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        feed_version(conn)
+    }
+}
+
+/// Returns the current version of the good_phrases feed.
+fn feed_version(conn: &SqliteConnection) -> Result<FeedVersion, Error> {
+    // Table names are not user input, so they can be embedded safely.
+    let query = FEED_TABLES
+        .iter()
+        .map(|table| {
+            format!(
+                "SELECT '{0}' AS table_name, COUNT(*) AS count, \
+                 MAX(modified_at) AS last_modified FROM {0}",
+                table
+            )
+        }).collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let versions = diesel::sql_query(query).load::<TableVersion>(conn)?;
+
+    let mut hasher = Fnv1a::new();
+    for version in &versions {
+        hasher.write(version.table_name.as_bytes());
+        hasher.write_u64(version.count as u64);
+        if let Some(last_modified) = version.last_modified {
+            hasher.write_u64(last_modified.timestamp() as u64);
+            hasher.write_u64(u64::from(last_modified.timestamp_subsec_nanos()));
+        }
+    }
+    Ok(FeedVersion {
+        fingerprint: hasher.finish(),
+        last_modified: versions.iter().filter_map(|v| v.last_modified).max(),
+    })
+}
+
+/// FNV-1a 64-bit hasher.
+///
+/// This is used (instead of `std::collections::hash_map::DefaultHasher`) to
+/// get fingerprints stable across builds and server restarts.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    /// Creates a new `Fnv1a`.
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Feeds the bytes.
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Feeds the integer in little endian.
+    fn write_u64(&mut self, v: u64) {
+        let mut bytes = [0; 8];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (v >> (i * 8)) as u8;
+        }
+        self.write(&bytes);
+    }
+
+    /// Returns the hash value.
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+
+    use super::*;
+    use db::testing;
+    use db::upsert_entry::{upsert_good_phrase_tag, GoodPhraseTag};
+
+    #[test]
+    fn tag_edit_changes_feed_version() {
+        let conn = testing::establish();
+        let created_at = Local::now().naive_utc();
+        let tag = GoodPhraseTag {
+            good_phrase_tag_id: None,
+            name: "tag".to_owned(),
+            description: None,
+        };
+        let tag_id = upsert_good_phrase_tag(&conn, tag, &created_at).unwrap();
+        let before = feed_version(&conn).unwrap();
+
+        let modified_at = created_at + Duration::seconds(1);
+        let tag = GoodPhraseTag {
+            good_phrase_tag_id: Some(tag_id),
+            name: "renamed".to_owned(),
+            description: Some("description".to_owned()),
+        };
+        upsert_good_phrase_tag(&conn, tag, &modified_at).unwrap();
+        let after = feed_version(&conn).unwrap();
+
+        assert_ne!(before.fingerprint, after.fingerprint);
+        assert_eq!(after.last_modified, Some(modified_at));
+    }
+}
@@ -1,79 +1,480 @@
 //! `GetGoodPhrases` message.
 
+use std::collections::HashMap;
+
 use actix::prelude::*;
-use chrono::{DateTime, FixedOffset};
+use base64;
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use diesel::dsl::{InnerJoin, IntoBoxed};
 use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
 
-use db::{DbExecutor, Error};
+use db::phrase_meta::PhraseMetas;
+use db::user::find_user_id;
+use db::{DbExecutor, Error, MAX_IDS_PER_QUERY};
 use good_phrase::response;
 use models;
 use schema;
 
+/// Boxed query for good_phrases joined with their persons.
+pub(super) type GoodPhraseAndPersonQuery =
+    IntoBoxed<'static, InnerJoin<schema::good_phrases::table, schema::persons::table>, Sqlite>;
+
+/// Conditions to filter good_phrases.
+///
+/// Default value matches all visible good_phrases.
+/// Retracted phrases and phrases of retracted persons never match.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GoodPhraseFilter {
+    /// Tag name the phrase should have.
+    pub tag: Option<String>,
+    /// Person ID of the author.
+    pub person_id: Option<i32>,
+    /// Lower bound (inclusive) of the datetime the phrase is published at.
+    pub published_after: Option<DateTime<FixedOffset>>,
+    /// Upper bound (exclusive) of the datetime the phrase is published at.
+    pub published_before: Option<DateTime<FixedOffset>>,
+    /// Whether to include phrases whose source web page is deleted.
+    pub include_deleted: bool,
+}
+
+impl Default for GoodPhraseFilter {
+    fn default() -> Self {
+        Self {
+            tag: None,
+            person_id: None,
+            published_after: None,
+            published_before: None,
+            include_deleted: true,
+        }
+    }
+}
+
+impl GoodPhraseFilter {
+    /// Applies the filter conditions to the given query.
+    pub(super) fn apply(&self, query: GoodPhraseAndPersonQuery) -> GoodPhraseAndPersonQuery {
+        use schema::good_phrases::columns;
+
+        let mut query = query
+            .filter(columns::retracted_at.is_null())
+            .filter(schema::persons::columns::retracted_at.is_null());
+        if let Some(ref tag) = self.tag {
+            let tagged_phrase_ids = schema::good_phrases_and_tags::table
+                .inner_join(schema::good_phrase_tags::table)
+                .filter(schema::good_phrase_tags::columns::name.eq(tag.clone()))
+                .filter(schema::good_phrase_tags::columns::retracted_at.is_null())
+                .select(schema::good_phrases_and_tags::columns::good_phrase_id);
+            query = query.filter(columns::good_phrase_id.eq_any(tagged_phrase_ids));
+        }
+        if let Some(person_id) = self.person_id {
+            query = query.filter(columns::person_id.eq(person_id));
+        }
+        if let Some(published_after) = self.published_after {
+            query = query.filter(columns::published_at.ge(published_after.naive_utc()));
+        }
+        if let Some(published_before) = self.published_before {
+            query = query.filter(columns::published_at.lt(published_before.naive_utc()));
+        }
+        if !self.include_deleted {
+            query = query.filter(columns::deleted.eq(false));
+        }
+        query
+    }
+}
+
+/// Position of a good_phrase in the list ordered by `(modified_at, good_phrase_id)`.
+///
+/// This is passed to clients as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GoodPhraseCursor {
+    /// UTC datetime the row is last modified at.
+    modified_at: NaiveDateTime,
+    /// Row ID.
+    good_phrase_id: i32,
+}
+
+impl GoodPhraseCursor {
+    /// Format of `modified_at` in the encoded cursor.
+    const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.f";
+
+    /// Creates a cursor pointing to the given row.
+    fn from_row(good_phrase: &models::GoodPhrase) -> Self {
+        Self {
+            modified_at: good_phrase.modified_at,
+            good_phrase_id: good_phrase.good_phrase_id,
+        }
+    }
+
+    /// Encodes the cursor into an opaque string.
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}/{}",
+            self.modified_at.format(Self::DATETIME_FORMAT),
+            self.good_phrase_id
+        );
+        base64::encode_config(&raw, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decodes the cursor from the string created by `encode()`.
+    ///
+    /// Returns `None` if the given string is not a valid cursor.
+    pub fn decode(s: &str) -> Option<Self> {
+        let raw = base64::decode_config(s, base64::URL_SAFE_NO_PAD).ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let mut parts = raw.splitn(2, '/');
+        let modified_at =
+            NaiveDateTime::parse_from_str(parts.next()?, Self::DATETIME_FORMAT).ok()?;
+        let good_phrase_id = parts.next()?.parse().ok()?;
+        Some(Self {
+            modified_at,
+            good_phrase_id,
+        })
+    }
+}
+
 /// A message type to get good_phrases.
 ///
-/// This may have query parameters to limit or filter results (while it does not
-/// for now).
+/// Phrases are ordered by `(modified_at, good_phrase_id)`, so that a phrase
+/// modified during pagination is returned again at the tail of the list
+/// rather than skipped.
 #[derive(Debug, Clone)]
-pub struct GetGoodPhrases;
+pub struct GetGoodPhrases {
+    /// Filter conditions.
+    pub filter: GoodPhraseFilter,
+    /// Returns phrases after the given position (exclusive) if specified.
+    pub cursor: Option<GoodPhraseCursor>,
+    /// Maximum number of phrases to return.
+    ///
+    /// `None` means no limit.
+    pub limit: Option<i64>,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
 
 impl Message for GetGoodPhrases {
-    type Result = Result<Vec<response::GoodPhrase>, Error>;
+    type Result = Result<response::GoodPhrasePage, Error>;
 }
 
 impl Handler<GetGoodPhrases> for DbExecutor {
     type Result = <GetGoodPhrases as Message>::Result;
 
-    fn handle(&mut self, _msg: GetGoodPhrases, _ctx: &mut Self::Context) -> Self::Result {
-        // Local offset.
-        let tz_offset = FixedOffset::east(9 * 60 * 60);
+    fn handle(&mut self, msg: GetGoodPhrases, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        get_good_phrases(conn, msg)
+    }
+}
+
+/// Gets good_phrases with the conditions of the message.
+fn get_good_phrases<C>(conn: &C, msg: GetGoodPhrases) -> Result<response::GoodPhrasePage, Error>
+where
+    C: Connection<Backend = Sqlite>,
+{
+    use schema::good_phrases::columns;
+
+    let mut query = msg
+        .filter
+        .apply(
+            schema::good_phrases::table
+                .inner_join(schema::persons::table)
+                .into_boxed(),
+        ).order((columns::modified_at.asc(), columns::good_phrase_id.asc()));
+    if let Some(cursor) = msg.cursor {
+        query = query.filter(
+            columns::modified_at.gt(cursor.modified_at).or(columns::modified_at
+                .eq(cursor.modified_at)
+                .and(columns::good_phrase_id.gt(cursor.good_phrase_id))),
+        );
+    }
+    if let Some(limit) = msg.limit {
+        // Fetch one more row to know whether the next page exists.
+        query = query.limit(limit + 1);
+    }
+    let mut good_phrases_and_persons = query.load::<(models::GoodPhrase, models::Person)>(conn)?;
+    let next_cursor = match msg.limit {
+        Some(limit) if good_phrases_and_persons.len() as i64 > limit => {
+            good_phrases_and_persons.truncate(limit as usize);
+            good_phrases_and_persons
+                .last()
+                .map(|(good_phrase, _)| GoodPhraseCursor::from_row(good_phrase).encode())
+        }
+        _ => None,
+    };
+    let user_id = match msg.device_token {
+        Some(ref token) => find_user_id(conn, token)?,
+        None => None,
+    };
+    let good_phrases = load_responses(conn, good_phrases_and_persons, user_id)?;
+    Ok(response::GoodPhrasePage {
+        good_phrases,
+        next_cursor,
+    })
+}
+
+/// A message type to get good_phrases with the given IDs.
+///
+/// Phrases are returned in the order of the given IDs, and nonexistent IDs are
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct GetGoodPhrasesByIds {
+    /// Phrase IDs.
+    pub good_phrase_ids: Vec<i32>,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
 
+impl Message for GetGoodPhrasesByIds {
+    type Result = Result<Vec<response::GoodPhrase>, Error>;
+}
+
+impl Handler<GetGoodPhrasesByIds> for DbExecutor {
+    type Result = <GetGoodPhrasesByIds as Message>::Result;
+
+    fn handle(&mut self, msg: GetGoodPhrasesByIds, _ctx: &mut Self::Context) -> Self::Result {
         let conn = &self.pool().get()?;
-        let good_phrases_and_persons = schema::good_phrases::table
-            .inner_join(schema::persons::table)
-            .load::<(models::GoodPhrase, models::Person)>(conn)?;
-        let mut result = Vec::new();
-        for (good_phrase, person) in good_phrases_and_persons {
-            let urls = models::PersonUrl::belonging_to(&person).load::<models::PersonUrl>(conn)?;
-            let tags = models::GoodPhraseAndTag::belonging_to(&good_phrase)
+        let user_id = match msg.device_token {
+            Some(ref token) => find_user_id(conn, token)?,
+            None => None,
+        };
+        load_responses_by_ids(conn, &msg.good_phrase_ids, user_id)
+    }
+}
+
+/// Builds response objects for the phrases with the given IDs, preserving the
+/// order.
+///
+/// Nonexistent IDs and IDs of phrases hidden by retraction are ignored.
+pub(super) fn load_responses_by_ids(
+    conn: &SqliteConnection,
+    good_phrase_ids: &[i32],
+    user_id: Option<i32>,
+) -> Result<Vec<response::GoodPhrase>, Error> {
+    let ranks = good_phrase_ids
+        .iter()
+        .enumerate()
+        .map(|(rank, &good_phrase_id)| (good_phrase_id, rank))
+        .collect::<HashMap<_, _>>();
+    let unique_ids = ranks.keys().cloned().collect::<Vec<_>>();
+
+    let mut good_phrases_and_persons = Vec::with_capacity(unique_ids.len());
+    for ids in unique_ids.chunks(MAX_IDS_PER_QUERY) {
+        good_phrases_and_persons.extend(
+            schema::good_phrases::table
+                .inner_join(schema::persons::table)
+                .filter(schema::good_phrases::columns::good_phrase_id.eq_any(ids))
+                .filter(schema::good_phrases::columns::retracted_at.is_null())
+                .filter(schema::persons::columns::retracted_at.is_null())
+                .load::<(models::GoodPhrase, models::Person)>(conn)?,
+        );
+    }
+    good_phrases_and_persons.sort_by_key(|(good_phrase, _)| ranks[&good_phrase.good_phrase_id]);
+    load_responses(conn, good_phrases_and_persons, user_id)
+}
+
+/// Ensures the phrase is visible to clients.
+///
+/// Returns `NotFound` error if the phrase does not exist or is hidden by
+/// retraction.
+pub(super) fn ensure_visible(conn: &SqliteConnection, good_phrase_id: i32) -> Result<(), Error> {
+    schema::good_phrases::table
+        .inner_join(schema::persons::table)
+        .filter(schema::good_phrases::columns::good_phrase_id.eq(good_phrase_id))
+        .filter(schema::good_phrases::columns::retracted_at.is_null())
+        .filter(schema::persons::columns::retracted_at.is_null())
+        .select(schema::good_phrases::columns::good_phrase_id)
+        .first::<i32>(conn)?;
+    Ok(())
+}
+
+/// Builds response objects for the given phrases and their authors.
+///
+/// Retracted tags are omitted.
+/// URLs, tags, and metadata are loaded in batch, so the number of queries does
+/// not depend on the number of phrases (except for very large input split into
+/// chunks).
+///
+/// User-local metadata is filled only if `user_id` is given.
+pub(super) fn load_responses<C>(
+    conn: &C,
+    good_phrases_and_persons: Vec<(models::GoodPhrase, models::Person)>,
+    user_id: Option<i32>,
+) -> Result<Vec<response::GoodPhrase>, Error>
+where
+    C: Connection<Backend = Sqlite>,
+{
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+
+    let (good_phrases, persons): (Vec<_>, Vec<_>) = good_phrases_and_persons.into_iter().unzip();
+
+    // `grouped_by()` requires parents to be unique.
+    let mut unique_persons = persons.clone();
+    unique_persons.sort_by_key(|person| person.person_id);
+    unique_persons.dedup_by_key(|person| person.person_id);
+    let mut urls = HashMap::with_capacity(unique_persons.len());
+    for persons_chunk in unique_persons.chunks(MAX_IDS_PER_QUERY) {
+        let urls_chunk = models::PersonUrl::belonging_to(persons_chunk)
+            .load::<models::PersonUrl>(conn)?
+            .grouped_by(persons_chunk);
+        urls.extend(
+            persons_chunk
+                .iter()
+                .map(|person| person.person_id)
+                .zip(urls_chunk),
+        );
+    }
+
+    let mut tags = Vec::with_capacity(good_phrases.len());
+    for good_phrases_chunk in good_phrases.chunks(MAX_IDS_PER_QUERY) {
+        tags.extend(
+            models::GoodPhraseAndTag::belonging_to(good_phrases_chunk)
                 .inner_join(schema::good_phrase_tags::table)
-                .load::<(models::GoodPhraseAndTag, models::GoodPhraseTag)>(conn)?;
+                .filter(schema::good_phrase_tags::columns::retracted_at.is_null())
+                .load::<(models::GoodPhraseAndTag, models::GoodPhraseTag)>(conn)?
+                .grouped_by(good_phrases_chunk),
+        );
+    }
+
+    let good_phrase_ids = good_phrases
+        .iter()
+        .map(|good_phrase| good_phrase.good_phrase_id)
+        .collect::<Vec<_>>();
+    let metas = PhraseMetas::load(conn, &good_phrase_ids, user_id)?;
 
+    let result = good_phrases
+        .into_iter()
+        .zip(persons)
+        .zip(tags)
+        .map(|((good_phrase, person), tags)| {
             let phrase = response::Phrase {
                 internal_id: good_phrase.good_phrase_id,
-                title: good_phrase.title.clone(),
-                phrase: good_phrase.phrase.clone(),
+                title: good_phrase.title,
+                phrase: good_phrase.phrase,
                 created: DateTime::from_utc(good_phrase.created_at, tz_offset),
-                url: good_phrase.url.clone(),
+                url: good_phrase.url,
                 deleted: good_phrase.deleted,
                 datetime: good_phrase
                     .published_at
                     .map(|t| DateTime::from_utc(t, tz_offset)),
             };
+            let person_urls = urls.get(&person.person_id).map_or_else(Vec::new, |urls| {
+                urls.iter().map(|url| url.url.clone()).collect()
+            });
             let person = response::Person {
                 internal_id: person.person_id,
                 created: DateTime::from_utc(person.created_at, tz_offset),
-                real_name: person.real_name.clone(),
-                display_name: person.display_name.clone(),
-                url: urls.into_iter().map(|url| url.url).collect(),
-                twitter: person.twitter.clone(),
-            };
-            let sys_meta = response::SysMeta {
-                use_count: 0,
-                fav_count: 0,
-                tags: tags.into_iter().map(|(_tag_rel, tag)| tag.name).collect(),
-            };
-            let user_meta = response::UserMeta {
-                favorite: false,
-                use_count: 0,
-                mylists: vec![],
+                real_name: person.real_name,
+                display_name: person.display_name,
+                url: person_urls,
+                twitter: person.twitter,
             };
-            result.push(response::GoodPhrase {
+            let sys_meta = metas.sys_meta(
+                phrase.internal_id,
+                tags.into_iter().map(|(_tag_rel, tag)| tag.name).collect(),
+            );
+            let user_meta = metas.user_meta(phrase.internal_id);
+            response::GoodPhrase {
                 phrase,
                 person,
                 sys_meta,
                 user_meta,
-            });
+            }
+        }).collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing::{self, CountingConnection};
+
+    /// Number of persons in the seeded DB.
+    const NUM_PERSONS: usize = 600;
+
+    /// Number of phrases in the seeded DB.
+    const NUM_PHRASES: usize = 10_000;
+
+    /// Opens a DB seeded with phrases, each of which has a URL and two tags,
+    /// and whose persons have two URLs.
+    fn seeded() -> CountingConnection {
+        let conn = testing::establish();
+        conn.batch_execute(&format!(
+            "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {0})
+             INSERT INTO persons (person_id, display_name) SELECT n, 'person' || n FROM seq;
+             WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {0})
+             INSERT INTO person_urls (person_id, url)
+                 SELECT n, 'https://example.com/' || n FROM seq
+                 UNION ALL SELECT n, 'https://example.org/' || n FROM seq;
+             INSERT INTO good_phrase_tags (good_phrase_tag_id, name)
+                 VALUES (1, 'tag1'), (2, 'tag2'), (3, 'tag3');
+             WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {1})
+             INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id, url)
+                 SELECT n, 'title' || n, 'phrase' || n, n % {0} + 1,
+                     'https://example.com/phrase/' || n
+                 FROM seq;
+             INSERT INTO good_phrases_and_tags (good_phrase_id, good_phrase_tag_id)
+                 SELECT good_phrase_id, good_phrase_id % 3 + 1 FROM good_phrases
+                 UNION ALL SELECT good_phrase_id, (good_phrase_id + 1) % 3 + 1 FROM good_phrases;",
+            NUM_PERSONS, NUM_PHRASES
+        )).unwrap();
+        CountingConnection::new(conn)
+    }
+
+    /// Returns the number of chunks of IDs.
+    fn num_chunks(num_ids: usize) -> usize {
+        (0..num_ids).step_by(MAX_IDS_PER_QUERY).count()
+    }
+
+    /// Gets phrases without user-local metadata.
+    fn get(conn: &CountingConnection, limit: Option<i64>) -> response::GoodPhrasePage {
+        let msg = GetGoodPhrases {
+            filter: GoodPhraseFilter::default(),
+            cursor: None,
+            limit,
+            device_token: None,
+        };
+        get_good_phrases(conn, msg).unwrap()
+    }
+
+    #[test]
+    fn query_count_does_not_depend_on_page_size() {
+        let conn = seeded();
+        conn.take_count();
+
+        // The phrases and their persons, URLs of the persons, tags of the
+        // phrases, and use and favorite counts of the phrases.
+        let page = get(&conn, Some(10));
+        assert_eq!(page.good_phrases.len(), 10);
+        assert_eq!(conn.take_count(), 5);
+
+        let page = get(&conn, Some(MAX_IDS_PER_QUERY as i64));
+        assert_eq!(page.good_phrases.len(), MAX_IDS_PER_QUERY);
+        assert_eq!(conn.take_count(), 5);
+    }
+
+    #[test]
+    fn query_count_of_all_phrases_depends_only_on_chunks() {
+        let conn = seeded();
+        conn.take_count();
+
+        let page = get(&conn, None);
+        assert_eq!(page.good_phrases.len(), NUM_PHRASES);
+        assert!(page.next_cursor.is_none());
+        // URLs are loaded for each chunk of persons, and tags, use counts,
+        // and favorite counts are loaded for each chunk of phrases.
+        let expected = 1 + num_chunks(NUM_PERSONS) + 3 * num_chunks(NUM_PHRASES);
+        assert_eq!(conn.take_count(), expected);
+
+        for good_phrase in &page.good_phrases {
+            let id = good_phrase.phrase.internal_id;
+            assert_eq!(
+                good_phrase.phrase.url,
+                Some(format!("https://example.com/phrase/{}", id))
+            );
+            assert_eq!(good_phrase.person.internal_id as usize, id as usize % NUM_PERSONS + 1);
+            assert_eq!(good_phrase.person.url.len(), 2);
+            assert_eq!(good_phrase.sys_meta.tags.len(), 2);
         }
-        Ok(result)
     }
 }
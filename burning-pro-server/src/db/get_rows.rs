@@ -1,9 +1,13 @@
 //! DB mesasages to get rows of a specific type.
 
+use std::collections::HashMap;
+
 use actix::prelude::*;
 use diesel::associations::HasTable;
+use diesel::dsl::sql;
 use diesel::prelude::*;
 use diesel::query_source::Table;
+use diesel::sql_types::BigInt;
 
 use db::{DbExecutor, Error};
 use models;
@@ -35,6 +39,8 @@ pub trait RowQuery {
 pub enum GoodPhraseTagQuery {
     /// Query all rows.
     All,
+    /// Query rows not retracted.
+    Visible,
     /// Query rows for the given tag id.
     TagId(i32),
     /// Query rows for the given phrase id.
@@ -58,6 +64,9 @@ impl Handler<GoodPhraseTagQuery> for DbExecutor {
             GoodPhraseTagQuery::All => {
                 GoodPhraseTagQuery::table().load::<models::GoodPhraseTag>(conn)?
             }
+            GoodPhraseTagQuery::Visible => GoodPhraseTagQuery::table()
+                .filter(schema::good_phrase_tags::columns::retracted_at.is_null())
+                .load::<models::GoodPhraseTag>(conn)?,
             GoodPhraseTagQuery::TagId(tag_id) => {
                 vec![GoodPhraseTagQuery::table().find(tag_id).first(conn)?]
             }
@@ -103,6 +112,64 @@ impl Handler<GoodPhraseQuery> for DbExecutor {
     }
 }
 
+/// Query type for the number of [`GoodPhrase`][`models::GoodPhrase`]s.
+///
+/// Phrases hidden by retraction are not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GoodPhraseCountQuery {
+    /// Count phrases for each person id.
+    ByPerson,
+    /// Count phrases of the given person id.
+    PersonId(i32),
+    /// Count phrases for each tag id.
+    ByTag,
+}
+
+impl Message for GoodPhraseCountQuery {
+    type Result = Result<HashMap<i32, i64>, Error>;
+}
+
+impl Handler<GoodPhraseCountQuery> for DbExecutor {
+    type Result = <GoodPhraseCountQuery as Message>::Result;
+
+    fn handle(&mut self, msg: GoodPhraseCountQuery, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let res = match msg {
+            GoodPhraseCountQuery::ByPerson => {
+                use schema::good_phrases::columns;
+
+                GoodPhraseQuery::table()
+                    .filter(columns::retracted_at.is_null())
+                    .group_by(columns::person_id)
+                    .select((columns::person_id, sql::<BigInt>("COUNT(*)")))
+                    .load::<(i32, i64)>(conn)?
+            }
+            GoodPhraseCountQuery::PersonId(person_id) => {
+                use schema::good_phrases::columns;
+
+                let count = GoodPhraseQuery::table()
+                    .filter(columns::person_id.eq(person_id))
+                    .filter(columns::retracted_at.is_null())
+                    .count()
+                    .get_result::<i64>(conn)?;
+                vec![(person_id, count)]
+            }
+            GoodPhraseCountQuery::ByTag => {
+                use schema::good_phrases_and_tags::columns;
+
+                schema::good_phrases_and_tags::table
+                    .inner_join(schema::good_phrases::table.inner_join(schema::persons::table))
+                    .filter(schema::good_phrases::columns::retracted_at.is_null())
+                    .filter(schema::persons::columns::retracted_at.is_null())
+                    .group_by(columns::good_phrase_tag_id)
+                    .select((columns::good_phrase_tag_id, sql::<BigInt>("COUNT(*)")))
+                    .load::<(i32, i64)>(conn)?
+            }
+        };
+        Ok(res.into_iter().collect())
+    }
+}
+
 /// Query type for [`PersonUrl`][`models::PersonUrl`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PersonUrlQuery {
@@ -145,6 +212,8 @@ impl Handler<PersonUrlQuery> for DbExecutor {
 pub enum PersonQuery {
     /// Query all rows.
     All,
+    /// Query rows not retracted.
+    Visible,
     /// Query rows for the given person id.
     PersonId(i32),
 }
@@ -164,6 +233,9 @@ impl Handler<PersonQuery> for DbExecutor {
         let conn = &self.pool().get()?;
         let res = match msg {
             PersonQuery::All => PersonQuery::table().load::<models::Person>(conn)?,
+            PersonQuery::Visible => PersonQuery::table()
+                .filter(schema::persons::columns::retracted_at.is_null())
+                .load::<models::Person>(conn)?,
             PersonQuery::PersonId(person_id) => {
                 vec![PersonQuery::table().find(person_id).first(conn)?]
             }
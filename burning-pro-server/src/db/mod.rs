@@ -7,13 +7,63 @@ use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use r2d2;
 
-pub use self::get_good_phrases::GetGoodPhrases;
+pub use self::admin_api_token::{
+    AdminApiTokenInfo, CreateAdminApiToken, ListAdminApiTokens, RevokeAdminApiToken,
+    UseAdminApiToken,
+};
+pub use self::admin_user::{
+    AdminRole, CreateAdminUser, GetAdminUser, ResetAdminUser, VerifyAdminLogin,
+};
+pub use self::audit::{AuditAction, AuditLogFilter, AuditTable, Audited, GetAuditLog};
+pub use self::feed_version::{FeedVersion, GetFeedVersion};
+pub use self::get_good_phrases::{
+    GetGoodPhrases, GetGoodPhrasesByIds, GoodPhraseCursor, GoodPhraseFilter,
+};
 pub use self::get_rows::*;
+pub use self::mylist::{
+    CreateMylist, DeleteMylist, GetMylist, GetMylists, RenameMylist, ReorderMylists, SetMylistEntry,
+};
+pub use self::phrase_request::{
+    ApprovePhraseRequest, GetPhraseRequest, GetPhraseRequestDraft, ModerationResult,
+    PhraseRequestDraft, PhraseRequestStatus, RejectPhraseRequest, RequestedPerson,
+};
+pub use self::pick_good_phrase::{GetDailyGoodPhrase, GetRandomGoodPhrase};
+pub use self::ranking::{
+    ComputeRanking, PhraseEventKind, RankedPhrase, RankingKind, RankingWindow, RecordEvent,
+};
+pub use self::retract::{RetractTarget, SetRetracted};
+pub use self::revision::{GetGoodPhraseRevision, GetGoodPhraseRevisions, GoodPhraseRevision};
+pub use self::search_good_phrases::{IndexPhraseNgrams, SearchGoodPhrases, SearchPhraseNgrams};
+pub use self::sync::{Changes, GetChanges};
+pub use self::user::SetFavorite;
 
 pub mod admin;
+mod admin_api_token;
+mod admin_user;
+mod audit;
+mod feed_version;
 mod get_good_phrases;
 mod get_rows;
+mod mylist;
+mod ngram;
+mod phrase_meta;
+mod phrase_request;
+mod pick_good_phrase;
+mod ranking;
+mod retract;
+mod revision;
+mod search_good_phrases;
+mod sync;
+#[cfg(test)]
+mod testing;
 pub mod upsert_entry;
+mod user;
+
+/// Maximum number of IDs used in a single `IN (...)` expression.
+///
+/// SQLite limits the number of bound parameters in a statement (999 by default
+/// for older versions), so IDs are split into chunks of this size.
+const MAX_IDS_PER_QUERY: usize = 500;
 
 /// DB operation error.
 #[derive(Debug, Fail)]
@@ -0,0 +1,470 @@
+//! DB messages for users' mylists.
+
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use chrono::{DateTime, FixedOffset, Local};
+use diesel;
+use diesel::prelude::*;
+
+use db::get_good_phrases::{ensure_visible, load_responses};
+use db::user::{find_user_id, get_or_create_user_id};
+use db::{DbExecutor, Error};
+use models;
+use mylist::response;
+use schema;
+
+/// Returns the user ID for the given device token.
+///
+/// Returns `NotFound` error if the token is not registered, since such user
+/// has no mylists.
+fn registered_user_id(conn: &SqliteConnection, device_token: &str) -> Result<i32, Error> {
+    Ok(find_user_id(conn, device_token)?.ok_or(diesel::result::Error::NotFound)?)
+}
+
+/// Returns the mylist if it is owned by the user.
+///
+/// Returns `NotFound` error if the mylist does not exist or is owned by
+/// another user.
+fn find_owned_mylist(
+    conn: &SqliteConnection,
+    user_id: i32,
+    mylist_id: i32,
+) -> Result<models::Mylist, Error> {
+    use schema::mylists::columns;
+
+    Ok(schema::mylists::table
+        .find(mylist_id)
+        .filter(columns::user_id.eq(user_id))
+        .first::<models::Mylist>(conn)?)
+}
+
+/// Returns all mylists of the user, in the user's order.
+fn load_user_mylists(
+    conn: &SqliteConnection,
+    user_id: i32,
+) -> Result<Vec<models::Mylist>, Error> {
+    use schema::mylists::columns;
+
+    Ok(schema::mylists::table
+        .filter(columns::user_id.eq(user_id))
+        .order((columns::position.asc(), columns::mylist_id.asc()))
+        .load::<models::Mylist>(conn)?)
+}
+
+/// Builds response objects for the given mylists.
+fn load_mylist_responses(
+    conn: &SqliteConnection,
+    mylists: Vec<models::Mylist>,
+) -> Result<Vec<response::Mylist>, Error> {
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+
+    // Retracted phrases are hidden as `GetMylist` does.
+    let entries = models::MylistEntry::belonging_to(&mylists)
+        .inner_join(schema::good_phrases::table.inner_join(schema::persons::table))
+        .filter(schema::good_phrases::columns::retracted_at.is_null())
+        .filter(schema::persons::columns::retracted_at.is_null())
+        .order(schema::mylist_entries::columns::mylist_entry_id.asc())
+        .select(schema::mylist_entries::all_columns)
+        .load::<models::MylistEntry>(conn)?
+        .grouped_by(&mylists);
+    Ok(mylists
+        .into_iter()
+        .zip(entries)
+        .map(|(mylist, entries)| response::Mylist {
+            internal_id: mylist.mylist_id,
+            name: mylist.name,
+            created: DateTime::from_utc(mylist.created_at, tz_offset),
+            good_phrase_ids: entries.iter().map(|entry| entry.good_phrase_id).collect(),
+        }).collect())
+}
+
+/// Updates the modified datetime of the mylist.
+fn touch_mylist(conn: &SqliteConnection, mylist_id: i32) -> Result<(), Error> {
+    use schema::mylists::columns;
+
+    let now_utc = Local::now().naive_utc();
+    diesel::update(schema::mylists::table.find(mylist_id))
+        .set(columns::modified_at.eq(&now_utc))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// A message type to get the user's mylists.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetMylists {
+    /// Device token of the user.
+    pub device_token: String,
+}
+
+impl Message for GetMylists {
+    type Result = Result<Vec<response::Mylist>, Error>;
+}
+
+impl Handler<GetMylists> for DbExecutor {
+    type Result = <GetMylists as Message>::Result;
+
+    fn handle(&mut self, msg: GetMylists, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let user_id = match find_user_id(conn, &msg.device_token)? {
+            Some(v) => v,
+            None => return Ok(Vec::new()),
+        };
+        let mylists = load_user_mylists(conn, user_id)?;
+        load_mylist_responses(conn, mylists)
+    }
+}
+
+/// A message type to get the user's mylist with its phrases.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetMylist {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist ID.
+    pub mylist_id: i32,
+}
+
+impl Message for GetMylist {
+    type Result = Result<response::MylistWithPhrases, Error>;
+}
+
+impl Handler<GetMylist> for DbExecutor {
+    type Result = <GetMylist as Message>::Result;
+
+    fn handle(&mut self, msg: GetMylist, _ctx: &mut Self::Context) -> Self::Result {
+        // Local offset.
+        let tz_offset = FixedOffset::east(9 * 60 * 60);
+
+        let conn = &self.pool().get()?;
+        let user_id = registered_user_id(conn, &msg.device_token)?;
+        let mylist = find_owned_mylist(conn, user_id, msg.mylist_id)?;
+
+        let good_phrases_and_persons = models::MylistEntry::belonging_to(&mylist)
+            .inner_join(schema::good_phrases::table.inner_join(schema::persons::table))
+            .filter(schema::good_phrases::columns::retracted_at.is_null())
+            .filter(schema::persons::columns::retracted_at.is_null())
+            .order(schema::mylist_entries::columns::mylist_entry_id.asc())
+            .select((
+                schema::good_phrases::all_columns,
+                schema::persons::all_columns,
+            )).load::<(models::GoodPhrase, models::Person)>(conn)?;
+
+        Ok(response::MylistWithPhrases {
+            internal_id: mylist.mylist_id,
+            name: mylist.name,
+            created: DateTime::from_utc(mylist.created_at, tz_offset),
+            good_phrases: load_responses(conn, good_phrases_and_persons, Some(user_id))?,
+        })
+    }
+}
+
+/// A message type to create a mylist.
+///
+/// The new mylist is placed at the end of the user's mylists.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CreateMylist {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist name.
+    pub name: String,
+}
+
+impl Message for CreateMylist {
+    type Result = Result<response::Mylist, Error>;
+}
+
+impl Handler<CreateMylist> for DbExecutor {
+    type Result = <CreateMylist as Message>::Result;
+
+    fn handle(&mut self, msg: CreateMylist, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::mylists::columns;
+
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let user_id = get_or_create_user_id(conn, &msg.device_token)?;
+            let last_position = schema::mylists::table
+                .filter(columns::user_id.eq(user_id))
+                .select(diesel::dsl::max(columns::position))
+                .first::<Option<i32>>(conn)?;
+            let now_utc = Local::now().naive_utc();
+            let new_row = models::NewMylist {
+                mylist_id: None,
+                created_at: &now_utc,
+                modified_at: &now_utc,
+                user_id,
+                name: &msg.name,
+                position: last_position.map_or(0, |pos| pos + 1),
+            };
+            diesel::insert_into(schema::mylists::table)
+                .values(new_row)
+                .execute(conn)?;
+
+            let mylist = schema::mylists::table
+                .filter(columns::user_id.eq(user_id))
+                .filter(columns::name.eq(&msg.name))
+                .first::<models::Mylist>(conn)?;
+            Ok(load_mylist_responses(conn, vec![mylist])?.remove(0))
+        })
+    }
+}
+
+/// A message type to rename the user's mylist.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenameMylist {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist ID.
+    pub mylist_id: i32,
+    /// New mylist name.
+    pub name: String,
+}
+
+impl Message for RenameMylist {
+    type Result = Result<response::Mylist, Error>;
+}
+
+impl Handler<RenameMylist> for DbExecutor {
+    type Result = <RenameMylist as Message>::Result;
+
+    fn handle(&mut self, msg: RenameMylist, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::mylists::columns;
+
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let user_id = registered_user_id(conn, &msg.device_token)?;
+            let mylist = find_owned_mylist(conn, user_id, msg.mylist_id)?;
+            let now_utc = Local::now().naive_utc();
+            diesel::update(&mylist)
+                .set((
+                    columns::modified_at.eq(&now_utc),
+                    columns::name.eq(&msg.name),
+                )).execute(conn)?;
+
+            let mylist = find_owned_mylist(conn, user_id, msg.mylist_id)?;
+            Ok(load_mylist_responses(conn, vec![mylist])?.remove(0))
+        })
+    }
+}
+
+/// A message type to delete the user's mylist and its entries.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteMylist {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist ID.
+    pub mylist_id: i32,
+}
+
+impl Message for DeleteMylist {
+    type Result = Result<(), Error>;
+}
+
+impl Handler<DeleteMylist> for DbExecutor {
+    type Result = <DeleteMylist as Message>::Result;
+
+    fn handle(&mut self, msg: DeleteMylist, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let user_id = registered_user_id(conn, &msg.device_token)?;
+            let mylist = find_owned_mylist(conn, user_id, msg.mylist_id)?;
+            diesel::delete(models::MylistEntry::belonging_to(&mylist)).execute(conn)?;
+            diesel::delete(&mylist).execute(conn)?;
+            Ok(())
+        })
+    }
+}
+
+/// A message type to reorder the user's mylists.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReorderMylists {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist IDs in the new order.
+    ///
+    /// Mylists not listed here are placed after the listed ones, keeping their
+    /// relative order.
+    pub mylist_ids: Vec<i32>,
+}
+
+impl Message for ReorderMylists {
+    type Result = Result<Vec<response::Mylist>, Error>;
+}
+
+impl Handler<ReorderMylists> for DbExecutor {
+    type Result = <ReorderMylists as Message>::Result;
+
+    fn handle(&mut self, msg: ReorderMylists, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::mylists::columns;
+
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let user_id = registered_user_id(conn, &msg.device_token)?;
+            let mut mylists = load_user_mylists(conn, user_id)?;
+
+            let mut new_positions = HashMap::with_capacity(mylists.len());
+            for &mylist_id in &msg.mylist_ids {
+                if !mylists.iter().any(|mylist| mylist.mylist_id == mylist_id) {
+                    return Err(diesel::result::Error::NotFound.into());
+                }
+                let next_position = new_positions.len();
+                new_positions.entry(mylist_id).or_insert(next_position);
+            }
+            // Stable sort keeps the current order of unlisted mylists.
+            mylists.sort_by_key(|mylist| {
+                new_positions
+                    .get(&mylist.mylist_id)
+                    .cloned()
+                    .unwrap_or(new_positions.len())
+            });
+
+            let now_utc = Local::now().naive_utc();
+            for (position, mylist) in mylists.iter_mut().enumerate() {
+                let position = position as i32;
+                if mylist.position != position {
+                    diesel::update(&*mylist)
+                        .set((
+                            columns::modified_at.eq(&now_utc),
+                            columns::position.eq(position),
+                        )).execute(conn)?;
+                    mylist.position = position;
+                }
+            }
+            load_mylist_responses(conn, mylists)
+        })
+    }
+}
+
+/// A message type to add or remove the phrase to / from the user's mylist.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SetMylistEntry {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Mylist ID.
+    pub mylist_id: i32,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Whether the phrase should be in the mylist.
+    pub contained: bool,
+}
+
+impl Message for SetMylistEntry {
+    type Result = Result<(), Error>;
+}
+
+impl Handler<SetMylistEntry> for DbExecutor {
+    type Result = <SetMylistEntry as Message>::Result;
+
+    fn handle(&mut self, msg: SetMylistEntry, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        set_mylist_entry(conn, &msg)
+    }
+}
+
+/// Adds or removes the phrase to / from the user's mylist.
+///
+/// Retracted phrases can be removed, but cannot be added.
+fn set_mylist_entry(conn: &SqliteConnection, msg: &SetMylistEntry) -> Result<(), Error> {
+    use schema::mylist_entries::columns;
+
+    conn.transaction::<_, Error, _>(|| {
+        let user_id = registered_user_id(conn, &msg.device_token)?;
+        let mylist = find_owned_mylist(conn, user_id, msg.mylist_id)?;
+        if msg.contained {
+            // Ensure the phrase exists and is not retracted.
+            ensure_visible(conn, msg.good_phrase_id)?;
+        }
+
+        let current = models::MylistEntry::belonging_to(&mylist)
+            .filter(columns::good_phrase_id.eq(msg.good_phrase_id));
+        let is_contained = diesel::select(diesel::dsl::exists(current)).get_result(conn)?;
+        match (is_contained, msg.contained) {
+            (false, true) => {
+                let now_utc = Local::now().naive_utc();
+                let new_row = models::NewMylistEntry {
+                    mylist_entry_id: None,
+                    created_at: &now_utc,
+                    modified_at: &now_utc,
+                    mylist_id: mylist.mylist_id,
+                    good_phrase_id: msg.good_phrase_id,
+                };
+                diesel::insert_into(schema::mylist_entries::table)
+                    .values(new_row)
+                    .execute(conn)?;
+                touch_mylist(conn, mylist.mylist_id)?;
+            }
+            (true, false) => {
+                diesel::delete(current).execute(conn)?;
+                touch_mylist(conn, mylist.mylist_id)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    /// Opens a DB with a user who has a mylist with phrases 1 and 2, and
+    /// retracts phrase 1.
+    fn seeded() -> SqliteConnection {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name) VALUES (1, 'person');
+             INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id)
+                 VALUES (1, 'title1', 'phrase1', 1), (2, 'title2', 'phrase2', 1);
+             INSERT INTO users (user_id, device_token) VALUES (1, 'token');
+             INSERT INTO mylists (mylist_id, user_id, name) VALUES (1, 1, 'mylist');
+             INSERT INTO mylist_entries (mylist_id, good_phrase_id) VALUES (1, 1), (1, 2);
+             UPDATE good_phrases SET retracted_at = CURRENT_TIMESTAMP
+                 WHERE good_phrase_id = 1;",
+        ).unwrap();
+        conn
+    }
+
+    /// Returns the message to set the phrase in the mylist.
+    fn set_msg(good_phrase_id: i32, contained: bool) -> SetMylistEntry {
+        SetMylistEntry {
+            device_token: "token".to_owned(),
+            mylist_id: 1,
+            good_phrase_id,
+            contained,
+        }
+    }
+
+    /// Returns phrase IDs in the mylist, including retracted ones.
+    fn entry_ids(conn: &SqliteConnection) -> Vec<i32> {
+        schema::mylist_entries::table
+            .select(schema::mylist_entries::columns::good_phrase_id)
+            .order(schema::mylist_entries::columns::good_phrase_id.asc())
+            .load::<i32>(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn retracted_phrase_can_be_removed_but_not_added() {
+        let conn = seeded();
+        set_mylist_entry(&conn, &set_msg(1, false)).unwrap();
+        assert_eq!(entry_ids(&conn), vec![2]);
+
+        match set_mylist_entry(&conn, &set_msg(1, true)) {
+            Err(Error::Db(diesel::result::Error::NotFound)) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(entry_ids(&conn), vec![2]);
+    }
+
+    #[test]
+    fn mylists_hide_retracted_phrases() {
+        let conn = seeded();
+        let mylists = load_user_mylists(&conn, 1).unwrap();
+        let responses = load_mylist_responses(&conn, mylists).unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].good_phrase_ids, vec![2]);
+    }
+}
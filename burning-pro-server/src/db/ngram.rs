@@ -0,0 +1,245 @@
+//! N-gram index for Japanese-aware phrase search.
+//!
+//! Japanese texts have no whitespaces between words, so word-based tokenizers
+//! do not work for them.
+//! Instead, phrases are normalized and split into character bigrams, and the
+//! bigrams are stored to `good_phrase_ngrams` FTS5 table as whitespace
+//! separated tokens.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use std::cmp::Ordering;
+
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use unicode_normalization::UnicodeNormalization;
+
+use db::Error;
+
+/// Normalizes the text for n-gram index and query.
+///
+/// * Full-width and half-width variants are unified by NFKC.
+/// * Latin letters are lowercased.
+/// * Katakana is converted to hiragana.
+/// * Non-alphanumeric characters (whitespaces and punctuations) are removed.
+pub fn normalize(s: &str) -> Vec<char> {
+    s.nfkc()
+        .flat_map(char::to_lowercase)
+        .map(katakana_to_hiragana)
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Converts a katakana character to the corresponding hiragana.
+///
+/// Characters without corresponding hiragana (such as `ヷ`) are returned as is.
+fn katakana_to_hiragana(c: char) -> char {
+    const KATAKANA_TO_HIRAGANA: u32 = 0x60;
+    match c {
+        // `ァ`..=`ヶ`, `ヽ`, `ヾ`.
+        '\u{30A1}'..='\u{30F6}' | '\u{30FD}' | '\u{30FE}' => {
+            ::std::char::from_u32(c as u32 - KATAKANA_TO_HIRAGANA).unwrap_or(c)
+        }
+        c => c,
+    }
+}
+
+/// Returns whitespace-separated tokens to be indexed.
+///
+/// Tokens are bigrams of the normalized text, followed by the last character
+/// (so that a query with single character can match the end of the text).
+pub fn index_tokens(s: &str) -> String {
+    let chars = normalize(s);
+    let mut tokens = chars
+        .windows(2)
+        .map(|w| w.iter().collect::<String>())
+        .collect::<Vec<_>>();
+    if let Some(last) = chars.last() {
+        tokens.push(last.to_string());
+    }
+    tokens.join(" ")
+}
+
+/// Returns FTS5 match expression for the given query.
+///
+/// Each whitespace-separated word of the query is converted to a phrase of
+/// bigrams, which matches texts containing the word as a substring.
+/// All words should match.
+///
+/// Returns `None` if the query has no searchable characters.
+pub fn match_expr(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(normalize)
+        .filter(|chars| !chars.is_empty())
+        .map(|chars| {
+            if chars.len() == 1 {
+                // Prefix query matches bigrams starting with the character and
+                // the last character token.
+                format!("\"{}\"*", chars[0])
+            } else {
+                let bigrams = chars
+                    .windows(2)
+                    .map(|w| w.iter().collect::<String>())
+                    .collect::<Vec<_>>();
+                format!("\"{}\"", bigrams.join(" "))
+            }
+        }).collect::<Vec<_>>();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Returns the similarity (from 0.0 to 1.0) of the given texts.
+///
+/// This is the Dice coefficient of bigrams of the normalized texts, so that
+/// texts with different widths or kana types are regarded as the same.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = normalize(a);
+    let b = normalize(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let mut a_bigrams = a.windows(2).collect::<Vec<_>>();
+    let mut b_bigrams = b.windows(2).collect::<Vec<_>>();
+    if a_bigrams.is_empty() || b_bigrams.is_empty() {
+        // Different single characters.
+        return 0.0;
+    }
+    a_bigrams.sort();
+    b_bigrams.sort();
+
+    // Count common bigrams (with duplicates) of the sorted bigrams.
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a_bigrams.len() && j < b_bigrams.len() {
+        match a_bigrams[i].cmp(b_bigrams[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2.0 * f64::from(common) / (a_bigrams.len() + b_bigrams.len()) as f64
+}
+
+/// Updates n-gram index of the given phrase.
+pub fn reindex_phrase(
+    conn: &SqliteConnection,
+    good_phrase_id: i32,
+    phrase: &str,
+) -> Result<(), Error> {
+    diesel::sql_query("DELETE FROM good_phrase_ngrams WHERE rowid = ?")
+        .bind::<Integer, _>(good_phrase_id)
+        .execute(conn)?;
+    diesel::sql_query("INSERT INTO good_phrase_ngrams(rowid, phrase) VALUES (?, ?)")
+        .bind::<Integer, _>(good_phrase_id)
+        .bind::<Text, _>(index_tokens(phrase))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use db::testing;
+
+    /// A row of n-gram search result.
+    #[derive(Debug, Clone, Copy, QueryableByName)]
+    struct Hit {
+        /// Phrase ID.
+        #[sql_type = "Integer"]
+        rowid: i32,
+    }
+
+    /// Returns the normalized text as a string.
+    fn normalized(s: &str) -> String {
+        normalize(s).into_iter().collect()
+    }
+
+    /// Query to search the n-gram index.
+    const SEARCH_QUERY: &str =
+        "SELECT rowid FROM good_phrase_ngrams WHERE good_phrase_ngrams MATCH ?";
+
+    /// Returns IDs of phrases matching the query in ascending order.
+    fn search(conn: &SqliteConnection, query: &str) -> Vec<i32> {
+        let match_expr = match_expr(query).unwrap();
+        let hits = diesel::sql_query(SEARCH_QUERY)
+            .bind::<Text, _>(match_expr)
+            .load::<Hit>(conn)
+            .unwrap();
+        let mut ids = hits.iter().map(|hit| hit.rowid).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn nfkc_unifies_widths() {
+        assert_eq!(normalized("ＡＢＣ　１２３"), "abc123");
+        assert_eq!(normalized("Phrase"), "phrase");
+        // Half-width katakana with voiced sound marks are composed.
+        assert_eq!(normalized("ｶﾞｯｺｳ"), "がっこう");
+    }
+
+    #[test]
+    fn katakana_is_folded_to_hiragana() {
+        assert_eq!(normalized("カタカナ"), "かたかな");
+        assert_eq!(normalized("ヴァイオリン"), "ゔぁいおりん");
+        assert_eq!(normalized("ヽヾ"), "ゝゞ");
+        // The prolonged sound mark and katakana without hiragana are kept.
+        assert_eq!(normalized("ヷー"), "ヷー");
+    }
+
+    #[test]
+    fn punctuations_are_removed() {
+        assert_eq!(normalized("こんにちは、 世界！"), "こんにちは世界");
+        assert_eq!(normalized("\"*-^()"), "");
+    }
+
+    #[test]
+    fn index_tokens_are_bigrams_and_last_character() {
+        assert_eq!(index_tokens(""), "");
+        assert_eq!(index_tokens("あ"), "あ");
+        assert_eq!(index_tokens("アイウ"), "あい いう う");
+    }
+
+    #[test]
+    fn match_expr_quotes_terms() {
+        assert_eq!(match_expr(""), None);
+        assert_eq!(match_expr(" 、！ "), None);
+        assert_eq!(match_expr("あ"), Some("\"あ\"*".to_owned()));
+        assert_eq!(
+            match_expr("カタカナ  ＡＢ"),
+            Some("\"かた たか かな\" \"ab\"".to_owned())
+        );
+        // FTS5 syntax in user input is not interpreted.
+        assert_eq!(
+            match_expr("a\" OR \"b* NEAR(c d) -e ^f"),
+            Some("\"a\"* \"or\" \"b\"* \"ne ea ar rc\" \"d\"* \"e\"* \"f\"*".to_owned())
+        );
+    }
+
+    #[test]
+    fn queries_match_phrases_regardless_of_width_and_kana() {
+        let conn = testing::establish();
+        reindex_phrase(&conn, 1, "カタカナのフレーズ").unwrap();
+        reindex_phrase(&conn, 2, "ｈｉｒａｇａｎａ").unwrap();
+        assert_eq!(search(&conn, "かたかな"), vec![1]);
+        assert_eq!(search(&conn, "ﾌﾚｰｽﾞ"), vec![1]);
+        assert_eq!(search(&conn, "ず"), vec![1]);
+        assert_eq!(search(&conn, "Hiragana"), vec![2]);
+        assert_eq!(search(&conn, "かな gana"), Vec::<i32>::new());
+        assert_eq!(search(&conn, "\"OR\" NOT"), Vec::<i32>::new());
+    }
+}
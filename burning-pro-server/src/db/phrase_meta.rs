@@ -0,0 +1,131 @@
+//! System-wide and user-local metadata of phrases.
+
+use std::collections::{HashMap, HashSet};
+
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use diesel::sqlite::Sqlite;
+
+use db::{Error, MAX_IDS_PER_QUERY};
+use good_phrase::response;
+use schema;
+
+/// Metadata of phrases, loaded in batch.
+#[derive(Default, Debug, Clone)]
+pub(super) struct PhraseMetas {
+    /// Count of all users' use for each phrase.
+    use_counts: HashMap<i32, i64>,
+    /// Count of all users' favorites for each phrase.
+    fav_counts: HashMap<i32, i64>,
+    /// Count of the user's use for each phrase.
+    user_use_counts: HashMap<i32, i64>,
+    /// Phrases favorited by the user.
+    user_favorites: HashSet<i32>,
+    /// Names of the user's mylists for each phrase.
+    user_mylists: HashMap<i32, Vec<String>>,
+}
+
+impl PhraseMetas {
+    /// Loads metadata of the given phrases.
+    ///
+    /// User-local metadata is loaded only if `user_id` is given.
+    pub(super) fn load<C>(
+        conn: &C,
+        good_phrase_ids: &[i32],
+        user_id: Option<i32>,
+    ) -> Result<Self, Error>
+    where
+        C: Connection<Backend = Sqlite>,
+    {
+        let mut metas = Self::default();
+        for ids in good_phrase_ids.chunks(MAX_IDS_PER_QUERY) {
+            {
+                use schema::good_phrase_uses::columns;
+
+                metas.use_counts.extend(
+                    schema::good_phrase_uses::table
+                        .filter(columns::good_phrase_id.eq_any(ids))
+                        .group_by(columns::good_phrase_id)
+                        .select((columns::good_phrase_id, sql::<BigInt>("COUNT(*)")))
+                        .load::<(i32, i64)>(conn)?,
+                );
+                if let Some(user_id) = user_id {
+                    metas.user_use_counts.extend(
+                        schema::good_phrase_uses::table
+                            .filter(columns::good_phrase_id.eq_any(ids))
+                            .filter(columns::user_id.eq(user_id))
+                            .group_by(columns::good_phrase_id)
+                            .select((columns::good_phrase_id, sql::<BigInt>("COUNT(*)")))
+                            .load::<(i32, i64)>(conn)?,
+                    );
+                }
+            }
+            {
+                use schema::good_phrase_favorites::columns;
+
+                metas.fav_counts.extend(
+                    schema::good_phrase_favorites::table
+                        .filter(columns::good_phrase_id.eq_any(ids))
+                        .group_by(columns::good_phrase_id)
+                        .select((columns::good_phrase_id, sql::<BigInt>("COUNT(*)")))
+                        .load::<(i32, i64)>(conn)?,
+                );
+                if let Some(user_id) = user_id {
+                    metas.user_favorites.extend(
+                        schema::good_phrase_favorites::table
+                            .filter(columns::good_phrase_id.eq_any(ids))
+                            .filter(columns::user_id.eq(user_id))
+                            .select(columns::good_phrase_id)
+                            .load::<i32>(conn)?,
+                    );
+                }
+            }
+            if let Some(user_id) = user_id {
+                let entries = schema::mylist_entries::table
+                    .inner_join(schema::mylists::table)
+                    .filter(schema::mylist_entries::columns::good_phrase_id.eq_any(ids))
+                    .filter(schema::mylists::columns::user_id.eq(user_id))
+                    .select((
+                        schema::mylist_entries::columns::good_phrase_id,
+                        schema::mylists::columns::name,
+                    )).load::<(i32, String)>(conn)?;
+                for (good_phrase_id, name) in entries {
+                    metas
+                        .user_mylists
+                        .entry(good_phrase_id)
+                        .or_insert_with(Vec::new)
+                        .push(name);
+                }
+            }
+        }
+        Ok(metas)
+    }
+
+    /// Returns system-wide metadata of the phrase.
+    pub(super) fn sys_meta(&self, good_phrase_id: i32, tags: Vec<String>) -> response::SysMeta {
+        response::SysMeta {
+            use_count: count(&self.use_counts, good_phrase_id),
+            fav_count: count(&self.fav_counts, good_phrase_id),
+            tags,
+        }
+    }
+
+    /// Returns user-local metadata of the phrase.
+    pub(super) fn user_meta(&self, good_phrase_id: i32) -> response::UserMeta {
+        response::UserMeta {
+            favorite: self.user_favorites.contains(&good_phrase_id),
+            use_count: count(&self.user_use_counts, good_phrase_id),
+            mylists: self
+                .user_mylists
+                .get(&good_phrase_id)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Returns the count for the phrase.
+fn count(counts: &HashMap<i32, i64>, good_phrase_id: i32) -> u64 {
+    counts.get(&good_phrase_id).map_or(0, |&count| count as u64)
+}
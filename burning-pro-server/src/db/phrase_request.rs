@@ -0,0 +1,527 @@
+//! Moderation of phrase requests.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use chrono::{DateTime, Local, NaiveDateTime};
+use diesel;
+use diesel::prelude::*;
+
+use db::audit::{self, AuditAction, AuditTable, Audited};
+use db::upsert_entry::{self, find_unknown_tag, person_exists, upsert_good_phrase, upsert_person};
+use db::{ngram, DbExecutor, Error};
+use models;
+use schema;
+
+/// Minimum similarity of names to regard the requested person as an existing
+/// person.
+const PERSON_SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// Moderation status of phrase requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhraseRequestStatus {
+    /// Not moderated yet.
+    Pending,
+    /// Accepted and registered as a phrase.
+    Accepted,
+    /// Rejected.
+    Rejected,
+}
+
+impl PhraseRequestStatus {
+    /// Returns the name stored in the DB.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PhraseRequestStatus::Pending => "pending",
+            PhraseRequestStatus::Accepted => "accepted",
+            PhraseRequestStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Result of moderation messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModerationResult {
+    /// The request is accepted and registered as the phrase with the given ID.
+    Accepted(i32),
+    /// The request is rejected.
+    Rejected,
+    /// The request is not found.
+    NotFound,
+    /// The request is already accepted or rejected.
+    AlreadyModerated,
+    /// The person with the given ID does not exist.
+    UnknownPerson(i32),
+    /// The tag with the given ID does not exist.
+    UnknownTag(i32),
+}
+
+/// Returns the ID of the existing person most similar to the given name.
+///
+/// Both display names and real names are compared, and `None` is returned if
+/// no persons are similar enough.
+fn find_similar_person(persons: &[models::Person], name: &str) -> Option<i32> {
+    let mut best = None;
+    for person in persons {
+        let mut score = ngram::similarity(&person.display_name, name);
+        if let Some(ref real_name) = person.real_name {
+            score = score.max(ngram::similarity(real_name, name));
+        }
+        let is_better = match best {
+            Some((_, best_score)) => score > best_score,
+            None => score >= PERSON_SIMILARITY_THRESHOLD,
+        };
+        if is_better {
+            best = Some((person.person_id, score));
+        }
+    }
+    best.map(|(person_id, _)| person_id)
+}
+
+/// Returns the ID of the existing tag with the same name as the given one.
+///
+/// Names are compared after normalization, so that differences of widths and
+/// kana types are ignored.
+fn find_same_tag(tags: &[models::GoodPhraseTag], name: &str) -> Option<i32> {
+    let name = ngram::normalize(name);
+    if name.is_empty() {
+        return None;
+    }
+    tags.iter()
+        .find(|tag| ngram::normalize(&tag.name) == name)
+        .map(|tag| tag.good_phrase_tag_id)
+}
+
+/// A message type to get a phrase request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetPhraseRequest {
+    /// Phrase request ID.
+    pub good_phrase_request_id: i32,
+}
+
+impl Message for GetPhraseRequest {
+    type Result = Result<Option<models::GoodPhraseRequest>, Error>;
+}
+
+impl Handler<GetPhraseRequest> for DbExecutor {
+    type Result = <GetPhraseRequest as Message>::Result;
+
+    fn handle(&mut self, msg: GetPhraseRequest, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        schema::good_phrase_requests::table
+            .find(msg.good_phrase_request_id)
+            .first::<models::GoodPhraseRequest>(conn)
+            .optional()
+            .map_err(Into::into)
+    }
+}
+
+/// A message type to get a phrase request and the person guessed from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetPhraseRequestDraft {
+    /// Phrase request ID.
+    pub good_phrase_request_id: i32,
+}
+
+/// A phrase request with the person and tags guessed from it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhraseRequestDraft {
+    /// Phrase request.
+    pub request: models::GoodPhraseRequest,
+    /// ID of the existing person similar to the requested person (if found).
+    pub person_id: Option<i32>,
+    /// IDs of the existing tags proposed by the request.
+    ///
+    /// This contains tags with the same names as the proposed free-text tags.
+    pub tag_ids: Vec<i32>,
+    /// Names of the proposed free-text tags which do not exist.
+    pub new_tags: Vec<String>,
+}
+
+impl Message for GetPhraseRequestDraft {
+    type Result = Result<Option<PhraseRequestDraft>, Error>;
+}
+
+impl Handler<GetPhraseRequestDraft> for DbExecutor {
+    type Result = <GetPhraseRequestDraft as Message>::Result;
+
+    fn handle(&mut self, msg: GetPhraseRequestDraft, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        load_draft(conn, msg.good_phrase_request_id)
+    }
+}
+
+/// Loads the phrase request, and guesses the person and tags from it.
+///
+/// Returns `None` if the request is not found.
+fn load_draft(
+    conn: &SqliteConnection,
+    good_phrase_request_id: i32,
+) -> Result<Option<PhraseRequestDraft>, Error> {
+    let request = match schema::good_phrase_requests::table
+        .find(good_phrase_request_id)
+        .first::<models::GoodPhraseRequest>(conn)
+        .optional()?
+    {
+        Some(request) => request,
+        None => return Ok(None),
+    };
+    // Retracted persons are not suggested, since their phrases are hidden.
+    let persons = schema::persons::table
+        .filter(schema::persons::columns::retracted_at.is_null())
+        .load::<models::Person>(conn)?;
+    let person_id = find_similar_person(&persons, &request.person);
+
+    let request_tags = models::GoodPhraseRequestTag::belonging_to(&request)
+        .load::<models::GoodPhraseRequestTag>(conn)?;
+    let all_tags = schema::good_phrase_tags::table.load::<models::GoodPhraseTag>(conn)?;
+    let mut tag_ids = Vec::new();
+    let mut new_tags = Vec::<String>::new();
+    for request_tag in request_tags {
+        let tag_id = match (request_tag.good_phrase_tag_id, request_tag.name) {
+            (Some(tag_id), _) => tag_id,
+            (None, Some(name)) => match find_same_tag(&all_tags, &name) {
+                Some(tag_id) => tag_id,
+                None => {
+                    let normalized = ngram::normalize(&name);
+                    if !new_tags.iter().any(|v| ngram::normalize(v) == normalized) {
+                        new_tags.push(name);
+                    }
+                    continue;
+                }
+            },
+            // Prohibited by the table constraint.
+            (None, None) => continue,
+        };
+        if !tag_ids.contains(&tag_id) {
+            tag_ids.push(tag_id);
+        }
+    }
+
+    Ok(Some(PhraseRequestDraft {
+        request,
+        person_id,
+        tag_ids,
+        new_tags,
+    }))
+}
+
+/// Person of the approved phrase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RequestedPerson {
+    /// Existing person with the given ID.
+    Existing(i32),
+    /// New person with the given display name.
+    New(String),
+}
+
+/// A message type to approve a phrase request and register it as a phrase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApprovePhraseRequest {
+    /// Phrase request ID.
+    pub good_phrase_request_id: i32,
+    /// Title (short summary).
+    pub title: String,
+    /// Phrase.
+    pub phrase: String,
+    /// Author.
+    pub person: RequestedPerson,
+    /// URL of the phrase if it is posted or published to the WWW.
+    pub url: Option<String>,
+    /// Whether the source web page is deleted or not.
+    pub deleted: bool,
+    /// Datetime when the phrase is published.
+    pub published_at: Option<DateTime<Local>>,
+    /// Tag ids.
+    pub tag_ids: Vec<i32>,
+}
+
+impl Message for ApprovePhraseRequest {
+    type Result = Result<ModerationResult, Error>;
+}
+
+impl Handler<Audited<ApprovePhraseRequest>> for DbExecutor {
+    type Result = <ApprovePhraseRequest as Message>::Result;
+
+    fn handle(
+        &mut self,
+        msg: Audited<ApprovePhraseRequest>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+        approve(conn, msg, &now_utc)
+    }
+}
+
+/// Approves the phrase request, and registers it as a phrase.
+fn approve(
+    conn: &SqliteConnection,
+    msg: Audited<ApprovePhraseRequest>,
+    now_utc: &NaiveDateTime,
+) -> Result<ModerationResult, Error> {
+    let Audited { editor, msg } = msg;
+    let ApprovePhraseRequest {
+        good_phrase_request_id,
+        title,
+        phrase,
+        person,
+        url,
+        deleted,
+        published_at,
+        tag_ids,
+    } = msg;
+
+    // The write lock is taken first, so that the request is not approved
+    // twice by concurrent moderators.
+    conn.immediate_transaction::<_, Error, _>(|| {
+        if let Some(result) = check_pending(conn, good_phrase_request_id)? {
+            return Ok(result);
+        }
+        // IDs are given by the moderation form, and checked before the
+        // new person is created.
+        if let RequestedPerson::Existing(person_id) = person {
+            if !person_exists(conn, person_id)? {
+                return Ok(ModerationResult::UnknownPerson(person_id));
+            }
+        }
+        if let Some(tag_id) = find_unknown_tag(conn, &tag_ids)? {
+            return Ok(ModerationResult::UnknownTag(tag_id));
+        }
+
+        let person_id = match person {
+            RequestedPerson::Existing(person_id) => person_id,
+            RequestedPerson::New(display_name) => {
+                let new_person = upsert_entry::Person {
+                    person_id: None,
+                    real_name: None,
+                    display_name,
+                    url: Vec::new(),
+                    twitter: None,
+                };
+                let person_id = upsert_person(conn, new_person, now_utc)?;
+                let after = audit::snapshot(conn, AuditTable::Persons, person_id)?;
+                audit::record(
+                    conn,
+                    &editor,
+                    AuditAction::Create,
+                    AuditTable::Persons,
+                    person_id,
+                    None,
+                    after,
+                )?;
+                person_id
+            }
+        };
+        let new_phrase = upsert_entry::GoodPhrase {
+            good_phrase_id: None,
+            title,
+            phrase,
+            person_id,
+            url,
+            deleted,
+            published_at,
+            tag_ids,
+        };
+        let good_phrase_id = upsert_good_phrase(conn, new_phrase, &editor, now_utc)?;
+        let after = audit::snapshot(conn, AuditTable::GoodPhrases, good_phrase_id)?;
+        audit::record(
+            conn,
+            &editor,
+            AuditAction::Create,
+            AuditTable::GoodPhrases,
+            good_phrase_id,
+            None,
+            after,
+        )?;
+
+        let before =
+            audit::snapshot(conn, AuditTable::GoodPhraseRequests, good_phrase_request_id)?;
+        {
+            use schema::good_phrase_requests::{columns, table};
+
+            diesel::update(table.find(good_phrase_request_id))
+                .set((
+                    columns::status.eq(PhraseRequestStatus::Accepted.as_str()),
+                    columns::good_phrase_id.eq(good_phrase_id),
+                    columns::moderated_at.eq(*now_utc),
+                )).execute(conn)?;
+        }
+        let after =
+            audit::snapshot(conn, AuditTable::GoodPhraseRequests, good_phrase_request_id)?;
+        audit::record(
+            conn,
+            &editor,
+            AuditAction::Approve,
+            AuditTable::GoodPhraseRequests,
+            good_phrase_request_id,
+            before,
+            after,
+        )?;
+
+        Ok(ModerationResult::Accepted(good_phrase_id))
+    })
+}
+
+/// A message type to reject a phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RejectPhraseRequest {
+    /// Phrase request ID.
+    pub good_phrase_request_id: i32,
+    /// Reason why the request is rejected.
+    pub reason: String,
+}
+
+impl Message for RejectPhraseRequest {
+    type Result = Result<ModerationResult, Error>;
+}
+
+impl Handler<Audited<RejectPhraseRequest>> for DbExecutor {
+    type Result = <RejectPhraseRequest as Message>::Result;
+
+    fn handle(
+        &mut self,
+        msg: Audited<RejectPhraseRequest>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        use schema::good_phrase_requests::{columns, table};
+
+        let conn = &self.pool().get()?;
+        let now_utc = Local::now().naive_utc();
+        let Audited { editor, msg } = msg;
+        let good_phrase_request_id = msg.good_phrase_request_id;
+
+        conn.immediate_transaction::<_, Error, _>(|| {
+            if let Some(result) = check_pending(conn, good_phrase_request_id)? {
+                return Ok(result);
+            }
+
+            let before =
+                audit::snapshot(conn, AuditTable::GoodPhraseRequests, good_phrase_request_id)?;
+            diesel::update(table.find(good_phrase_request_id))
+                .set((
+                    columns::status.eq(PhraseRequestStatus::Rejected.as_str()),
+                    columns::reject_reason.eq(&msg.reason),
+                    columns::moderated_at.eq(now_utc),
+                )).execute(conn)?;
+            let after =
+                audit::snapshot(conn, AuditTable::GoodPhraseRequests, good_phrase_request_id)?;
+            audit::record(
+                conn,
+                &editor,
+                AuditAction::Reject,
+                AuditTable::GoodPhraseRequests,
+                good_phrase_request_id,
+                before,
+                after,
+            )?;
+
+            Ok(ModerationResult::Rejected)
+        })
+    }
+}
+
+/// Checks the request is pending.
+///
+/// Returns the result to be returned if the request cannot be moderated.
+fn check_pending(
+    conn: &SqliteConnection,
+    good_phrase_request_id: i32,
+) -> Result<Option<ModerationResult>, Error> {
+    use schema::good_phrase_requests::{columns, table};
+
+    let status = table
+        .find(good_phrase_request_id)
+        .select(columns::status)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(match status {
+        None => Some(ModerationResult::NotFound),
+        Some(ref status) if status != PhraseRequestStatus::Pending.as_str() => {
+            Some(ModerationResult::AlreadyModerated)
+        }
+        Some(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    /// Opens a DB with a pending request for a phrase of `person`, a
+    /// retracted person with the same name, and a tag.
+    fn seeded() -> SqliteConnection {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name, retracted_at)
+                 VALUES (1, 'person', CURRENT_TIMESTAMP);
+             INSERT INTO good_phrase_tags (good_phrase_tag_id, name) VALUES (1, 'tag');
+             INSERT INTO good_phrase_requests (good_phrase_request_id, phrase, person)
+                 VALUES (1, 'phrase', 'person');",
+        ).unwrap();
+        conn
+    }
+
+    /// Returns the message to approve the request.
+    fn approve_msg(person: RequestedPerson, tag_ids: Vec<i32>) -> Audited<ApprovePhraseRequest> {
+        Audited::new(
+            "editor",
+            ApprovePhraseRequest {
+                good_phrase_request_id: 1,
+                title: "title".to_owned(),
+                phrase: "phrase".to_owned(),
+                person,
+                url: None,
+                deleted: false,
+                published_at: None,
+                tag_ids,
+            },
+        )
+    }
+
+    /// Returns the numbers of persons and phrases, and the request status.
+    fn counts(conn: &SqliteConnection) -> (i64, i64, String) {
+        let persons = schema::persons::table.count().get_result(conn).unwrap();
+        let phrases = schema::good_phrases::table.count().get_result(conn).unwrap();
+        let status = schema::good_phrase_requests::table
+            .select(schema::good_phrase_requests::columns::status)
+            .first(conn)
+            .unwrap();
+        (persons, phrases, status)
+    }
+
+    #[test]
+    fn draft_does_not_suggest_retracted_persons() {
+        let conn = seeded();
+        let draft = load_draft(&conn, 1).unwrap().unwrap();
+        assert_eq!(draft.person_id, None);
+    }
+
+    #[test]
+    fn approval_rejects_unknown_ids() {
+        let conn = seeded();
+        let now_utc = Local::now().naive_utc();
+
+        let msg = approve_msg(RequestedPerson::Existing(2), vec![1]);
+        let result = approve(&conn, msg, &now_utc).unwrap();
+        assert_eq!(result, ModerationResult::UnknownPerson(2));
+
+        // The new person is not created either.
+        let msg = approve_msg(RequestedPerson::New("new".to_owned()), vec![1, 2]);
+        let result = approve(&conn, msg, &now_utc).unwrap();
+        assert_eq!(result, ModerationResult::UnknownTag(2));
+        assert_eq!(counts(&conn), (1, 0, "pending".to_owned()));
+
+        let msg = approve_msg(RequestedPerson::New("new".to_owned()), vec![1]);
+        match approve(&conn, msg, &now_utc).unwrap() {
+            ModerationResult::Accepted(_) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert_eq!(counts(&conn), (2, 1, "accepted".to_owned()));
+    }
+}
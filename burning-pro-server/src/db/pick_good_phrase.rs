@@ -0,0 +1,210 @@
+//! `GetRandomGoodPhrase` and `GetDailyGoodPhrase` messages.
+
+use actix::prelude::*;
+use chrono::{Duration, FixedOffset, NaiveDate};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+
+use db::get_good_phrases::{load_responses, GoodPhraseAndPersonQuery};
+use db::user::find_user_id;
+use db::{DbExecutor, Error, GoodPhraseFilter};
+use good_phrase::response;
+use models;
+use schema;
+
+/// A message type to get a good_phrase picked at random.
+///
+/// Returns `None` if no phrases match the filter.
+#[derive(Debug, Clone)]
+pub struct GetRandomGoodPhrase {
+    /// Filter conditions.
+    pub filter: GoodPhraseFilter,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
+
+impl Message for GetRandomGoodPhrase {
+    type Result = Result<Option<response::GoodPhrase>, Error>;
+}
+
+impl Handler<GetRandomGoodPhrase> for DbExecutor {
+    type Result = <GetRandomGoodPhrase as Message>::Result;
+
+    fn handle(&mut self, msg: GetRandomGoodPhrase, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let row = msg
+            .filter
+            .apply(
+                schema::good_phrases::table
+                    .inner_join(schema::persons::table)
+                    .into_boxed(),
+            ).order(sql::<Integer>("RANDOM()"))
+            .first::<(models::GoodPhrase, models::Person)>(conn)
+            .optional()?;
+        load_response(conn, row, msg.device_token.as_ref().map(AsRef::as_ref))
+    }
+}
+
+/// A message type to get the good_phrase of the day.
+///
+/// The phrase is picked deterministically from phrases created before the
+/// day starts, so that all clients get the same phrase through the day even
+/// if new phrases are added.
+/// The phrase with the smallest hash of the date and its ID is picked, so the
+/// pick changes only if the picked phrase itself is retracted.
+///
+/// Returns `None` if there are no candidate phrases.
+#[derive(Debug, Clone)]
+pub struct GetDailyGoodPhrase {
+    /// The day (in the local timezone).
+    pub date: NaiveDate,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
+
+impl Message for GetDailyGoodPhrase {
+    type Result = Result<Option<response::GoodPhrase>, Error>;
+}
+
+impl Handler<GetDailyGoodPhrase> for DbExecutor {
+    type Result = <GetDailyGoodPhrase as Message>::Result;
+
+    fn handle(&mut self, msg: GetDailyGoodPhrase, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let row = pick_daily(conn, msg.date)?;
+        load_response(conn, row, msg.device_token.as_ref().map(AsRef::as_ref))
+    }
+}
+
+/// Picks the phrase of the day.
+fn pick_daily(
+    conn: &SqliteConnection,
+    date: NaiveDate,
+) -> Result<Option<(models::GoodPhrase, models::Person)>, Error> {
+    use schema::good_phrases::columns;
+
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+    let day_start =
+        date.and_hms(0, 0, 0) - Duration::seconds(i64::from(tz_offset.local_minus_utc()));
+
+    let candidates = || -> GoodPhraseAndPersonQuery {
+        schema::good_phrases::table
+            .inner_join(schema::persons::table)
+            .filter(columns::created_at.lt(day_start))
+            .filter(columns::retracted_at.is_null())
+            .filter(schema::persons::columns::retracted_at.is_null())
+            .into_boxed()
+    };
+    let picked = candidates()
+        .select(columns::good_phrase_id)
+        .load::<i32>(conn)?
+        .into_iter()
+        .min_by_key(|&id| pick_hash(date, id));
+    let good_phrase_id = match picked {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    candidates()
+        .filter(columns::good_phrase_id.eq(good_phrase_id))
+        .first::<(models::GoodPhrase, models::Person)>(conn)
+        .optional()
+        .map_err(Into::into)
+}
+
+/// Returns a hash value of the date and the phrase ID.
+///
+/// This uses SplitMix64 finalizer to be stable across builds and platforms
+/// (unlike `std::collections::hash_map::DefaultHasher`).
+fn pick_hash(date: NaiveDate, good_phrase_id: i32) -> u64 {
+    let days = date.signed_duration_since(NaiveDate::from_ymd(1970, 1, 1)).num_days();
+    splitmix64(splitmix64(days as u64) ^ good_phrase_id as u64)
+}
+
+/// Returns the SplitMix64 finalizer of the value.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Builds the response object for the picked phrase.
+fn load_response(
+    conn: &SqliteConnection,
+    row: Option<(models::GoodPhrase, models::Person)>,
+    device_token: Option<&str>,
+) -> Result<Option<response::GoodPhrase>, Error> {
+    let row = match row {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let user_id = match device_token {
+        Some(token) => find_user_id(conn, token)?,
+        None => None,
+    };
+    Ok(load_responses(conn, vec![row], user_id)?.pop())
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    /// Returns the ID of the phrase of the day.
+    fn daily_id(conn: &SqliteConnection, date: NaiveDate) -> Option<i32> {
+        pick_daily(conn, date)
+            .unwrap()
+            .map(|(phrase, _)| phrase.good_phrase_id)
+    }
+
+    #[test]
+    fn daily_pick_is_stable_while_other_phrases_are_retracted() {
+        let conn = testing::establish();
+        conn.batch_execute("INSERT INTO persons (person_id, display_name) VALUES (1, 'person');")
+            .unwrap();
+        for id in 1..=10 {
+            conn.batch_execute(&format!(
+                "INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id, created_at)
+                     VALUES ({0}, 'title{0}', 'phrase{0}', 1, '2000-01-01 00:00:00');",
+                id
+            )).unwrap();
+        }
+        assert_eq!(daily_id(&conn, NaiveDate::from_ymd(1999, 12, 31)), None);
+
+        let mut picks = Vec::new();
+        for day in 1..=10 {
+            let date = NaiveDate::from_ymd(2018, 12, day);
+            let picked = daily_id(&conn, date).unwrap();
+            picks.push(picked);
+
+            // Retract all the other phrases.
+            conn.batch_execute(&format!(
+                "UPDATE good_phrases SET retracted_at = CURRENT_TIMESTAMP
+                     WHERE good_phrase_id <> {};",
+                picked
+            )).unwrap();
+            assert_eq!(daily_id(&conn, date), Some(picked));
+
+            // Only the retraction of the picked phrase changes the pick.
+            conn.batch_execute(&format!(
+                "UPDATE good_phrases SET retracted_at = NULL;
+                 UPDATE good_phrases SET retracted_at = CURRENT_TIMESTAMP
+                     WHERE good_phrase_id = {};",
+                picked
+            )).unwrap();
+            let next = daily_id(&conn, date).unwrap();
+            assert_ne!(next, picked);
+            conn.batch_execute("UPDATE good_phrases SET retracted_at = NULL;")
+                .unwrap();
+            assert_eq!(daily_id(&conn, date), Some(picked));
+        }
+        // Different days pick different phrases.
+        picks.sort();
+        picks.dedup();
+        assert!(picks.len() > 1);
+    }
+}
@@ -0,0 +1,298 @@
+//! Phrase events and rankings computed from them.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use chrono::{Duration, Local, NaiveDateTime};
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text, Timestamp};
+
+use db::get_good_phrases::ensure_visible;
+use db::user::get_or_create_user_id;
+use db::{DbExecutor, Error};
+use models;
+use schema;
+
+/// Kind of phrase events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhraseEventKind {
+    /// The phrase is viewed.
+    View,
+    /// The phrase is used (copied or sent).
+    Use,
+}
+
+impl PhraseEventKind {
+    /// Returns the name stored in the DB.
+    fn as_str(self) -> &'static str {
+        match self {
+            PhraseEventKind::View => "view",
+            PhraseEventKind::Use => "use",
+        }
+    }
+}
+
+/// Returns the weight of the event stored with the given kind name.
+///
+/// Uses are more important than views for rankings.
+fn event_weight(kind: &str) -> f64 {
+    match kind {
+        "use" => 3.0,
+        _ => 1.0,
+    }
+}
+
+/// Kind of rankings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankingKind {
+    /// Phrases with most events in the window.
+    Popular,
+    /// Phrases with highest time-decayed scores in the window.
+    Trending,
+    /// Phrases added recently in the window.
+    New,
+}
+
+impl RankingKind {
+    /// Returns all kinds.
+    pub fn all() -> &'static [RankingKind] {
+        &[RankingKind::Popular, RankingKind::Trending, RankingKind::New]
+    }
+}
+
+/// Time window of rankings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RankingWindow {
+    /// 1 day.
+    Day,
+    /// 7 days.
+    Week,
+    /// 30 days.
+    Month,
+}
+
+impl RankingWindow {
+    /// Returns all windows.
+    pub fn all() -> &'static [RankingWindow] {
+        &[RankingWindow::Day, RankingWindow::Week, RankingWindow::Month]
+    }
+
+    /// Parses the window string such as `7d` or `24h`.
+    ///
+    /// Returns `None` if the window is unsupported.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "1d" | "24h" => Some(RankingWindow::Day),
+            "7d" => Some(RankingWindow::Week),
+            "30d" => Some(RankingWindow::Month),
+            _ => None,
+        }
+    }
+
+    /// Returns the window string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RankingWindow::Day => "1d",
+            RankingWindow::Week => "7d",
+            RankingWindow::Month => "30d",
+        }
+    }
+
+    /// Returns the length of the window.
+    pub fn duration(self) -> Duration {
+        match self {
+            RankingWindow::Day => Duration::days(1),
+            RankingWindow::Week => Duration::days(7),
+            RankingWindow::Month => Duration::days(30),
+        }
+    }
+
+    /// Returns the half-life (in hours) of the event scores for trending
+    /// ranking.
+    fn half_life_hours(self) -> f64 {
+        self.duration().num_hours() as f64 / 4.0
+    }
+}
+
+/// A phrase in a ranking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedPhrase {
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Score of the phrase (`None` for rankings without scores).
+    pub score: Option<f64>,
+}
+
+/// Number of events of a phrase in an hour.
+#[derive(Debug, Clone, QueryableByName)]
+struct EventBucket {
+    /// Phrase ID.
+    #[sql_type = "Integer"]
+    good_phrase_id: i32,
+    /// Event kind.
+    #[sql_type = "Text"]
+    kind: String,
+    /// Hours since the Unix epoch.
+    #[sql_type = "BigInt"]
+    hour: i64,
+    /// Number of events.
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// A message type to record an event of the phrase.
+///
+/// Events are recorded to `good_phrase_events` for rankings.
+/// Use events are also recorded to `good_phrase_uses`, which is the source of
+/// use counts in phrase metadata.
+/// Use counts are not computed from `good_phrase_events`, because the feed
+/// version is computed from the tables of the feed, and views (which do not
+/// change the feed) would change it if the events table was one of them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordEvent {
+    /// Device token of the user (if known).
+    pub device_token: Option<String>,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Event kind.
+    pub kind: PhraseEventKind,
+}
+
+impl Message for RecordEvent {
+    type Result = Result<(), Error>;
+}
+
+impl Handler<RecordEvent> for DbExecutor {
+    type Result = <RecordEvent as Message>::Result;
+
+    fn handle(&mut self, msg: RecordEvent, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            // Ensure the phrase exists and is not retracted.
+            ensure_visible(conn, msg.good_phrase_id)?;
+
+            let user_id = match msg.device_token {
+                Some(ref token) => Some(get_or_create_user_id(conn, token)?),
+                None => None,
+            };
+            let now_utc = Local::now().naive_utc();
+            let new_row = models::NewGoodPhraseEvent {
+                good_phrase_event_id: None,
+                created_at: &now_utc,
+                modified_at: &now_utc,
+                good_phrase_id: msg.good_phrase_id,
+                user_id,
+                kind: msg.kind.as_str(),
+            };
+            diesel::insert_into(schema::good_phrase_events::table)
+                .values(new_row)
+                .execute(conn)?;
+            // Both rows are inserted in the same transaction, so that use
+            // counts and rankings agree.
+            if msg.kind == PhraseEventKind::Use {
+                let new_row = models::NewGoodPhraseUse {
+                    good_phrase_use_id: None,
+                    created_at: &now_utc,
+                    modified_at: &now_utc,
+                    user_id,
+                    good_phrase_id: msg.good_phrase_id,
+                };
+                diesel::insert_into(schema::good_phrase_uses::table)
+                    .values(new_row)
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A message type to compute a ranking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ComputeRanking {
+    /// Ranking kind.
+    pub kind: RankingKind,
+    /// Time window.
+    pub window: RankingWindow,
+    /// UTC datetime the ranking is computed at.
+    pub now: NaiveDateTime,
+    /// Maximum number of phrases in the ranking.
+    pub limit: i64,
+}
+
+impl Message for ComputeRanking {
+    type Result = Result<Vec<RankedPhrase>, Error>;
+}
+
+impl Handler<ComputeRanking> for DbExecutor {
+    type Result = <ComputeRanking as Message>::Result;
+
+    fn handle(&mut self, msg: ComputeRanking, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let since = msg.now - msg.window.duration();
+        if msg.kind == RankingKind::New {
+            use schema::good_phrases::columns;
+
+            let ids = schema::good_phrases::table
+                .inner_join(schema::persons::table)
+                .filter(columns::created_at.ge(since))
+                .filter(columns::retracted_at.is_null())
+                .filter(schema::persons::columns::retracted_at.is_null())
+                .order((columns::created_at.desc(), columns::good_phrase_id.desc()))
+                .limit(msg.limit)
+                .select(columns::good_phrase_id)
+                .load::<i32>(conn)?;
+            return Ok(ids
+                .into_iter()
+                .map(|good_phrase_id| RankedPhrase {
+                    good_phrase_id,
+                    score: None,
+                }).collect());
+        }
+
+        let buckets = diesel::sql_query(
+            "SELECT good_phrase_id, kind, \
+             CAST(strftime('%s', created_at) AS INTEGER) / 3600 AS hour, COUNT(*) AS count \
+             FROM good_phrase_events WHERE created_at >= ? \
+             AND good_phrase_id IN (SELECT good_phrase_id FROM good_phrases \
+             INNER JOIN persons ON good_phrases.person_id = persons.person_id \
+             WHERE good_phrases.retracted_at IS NULL AND persons.retracted_at IS NULL) \
+             GROUP BY good_phrase_id, kind, hour",
+        ).bind::<Timestamp, _>(since)
+        .load::<EventBucket>(conn)?;
+
+        let now_hours = msg.now.timestamp() as f64 / 3600.0;
+        let half_life_hours = msg.window.half_life_hours();
+        let mut scores = HashMap::new();
+        for bucket in buckets {
+            let mut score = event_weight(&bucket.kind) * bucket.count as f64;
+            if msg.kind == RankingKind::Trending {
+                // Use the middle of the hour as the event time.
+                let age_hours = (now_hours - (bucket.hour as f64 + 0.5)).max(0.0);
+                score *= 0.5f64.powf(age_hours / half_life_hours);
+            }
+            *scores.entry(bucket.good_phrase_id).or_insert(0.0) += score;
+        }
+        let mut ranking = scores
+            .into_iter()
+            .map(|(good_phrase_id, score)| RankedPhrase {
+                good_phrase_id,
+                score: Some(score),
+            }).collect::<Vec<_>>();
+        // Higher score first, and newer phrase first for the same score.
+        ranking.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then(b.good_phrase_id.cmp(&a.good_phrase_id))
+        });
+        ranking.truncate(msg.limit as usize);
+        Ok(ranking)
+    }
+}
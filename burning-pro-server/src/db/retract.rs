@@ -0,0 +1,201 @@
+//! `SetRetracted` message.
+
+use actix::prelude::*;
+use chrono::Local;
+use diesel;
+use diesel::prelude::*;
+
+use db::audit::{self, AuditAction, AuditTable, Audited};
+use db::{DbExecutor, Error};
+
+/// Entry to be retracted or restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetractTarget {
+    /// Phrase with the given ID.
+    GoodPhrase(i32),
+    /// Person with the given ID.
+    ///
+    /// Phrases of the person are hidden while the person is retracted.
+    Person(i32),
+    /// Tag with the given ID.
+    GoodPhraseTag(i32),
+}
+
+/// A message type to retract (hide from clients) or restore an entry.
+///
+/// Retracted entries are kept in the DB, and reported to clients as
+/// tombstones.
+/// The result is `false` if the entry does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetRetracted {
+    /// Target entry.
+    pub target: RetractTarget,
+    /// Whether the entry should be retracted.
+    pub retracted: bool,
+}
+
+impl Message for SetRetracted {
+    type Result = Result<bool, Error>;
+}
+
+impl RetractTarget {
+    /// Returns the table and the row ID of the target.
+    fn audit_entry(self) -> (AuditTable, i32) {
+        match self {
+            RetractTarget::GoodPhrase(id) => (AuditTable::GoodPhrases, id),
+            RetractTarget::Person(id) => (AuditTable::Persons, id),
+            RetractTarget::GoodPhraseTag(id) => (AuditTable::GoodPhraseTags, id),
+        }
+    }
+}
+
+impl Handler<Audited<SetRetracted>> for DbExecutor {
+    type Result = <SetRetracted as Message>::Result;
+
+    fn handle(&mut self, msg: Audited<SetRetracted>, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        let Audited { editor, msg } = msg;
+        let (audit_table, row_id) = msg.target.audit_entry();
+        let action = if msg.retracted {
+            AuditAction::Retract
+        } else {
+            AuditAction::Restore
+        };
+
+        conn.transaction::<_, Error, _>(|| {
+            let before = audit::snapshot(conn, audit_table, row_id)?;
+            if !set_retracted(conn, &msg)? {
+                return Ok(false);
+            }
+            let after = audit::snapshot(conn, audit_table, row_id)?;
+            // Nothing is recorded if the entry is already in the requested
+            // state.
+            audit::record(conn, &editor, action, audit_table, row_id, before, after)?;
+            Ok(true)
+        })
+    }
+}
+
+/// Updates `retracted_at` of the row with the given ID in the table.
+///
+/// Evaluates to `true` if the row exists.
+macro_rules! update_retracted {
+    ($conn:expr, $table:ident, $id_column:ident, $id:expr, $retracted_at:expr, $now_utc:expr) => {{
+        use schema::$table::{columns, table};
+
+        let target = table.filter(columns::$id_column.eq($id));
+        diesel::update(target.filter(columns::retracted_at.is_null().eq($retracted_at.is_some())))
+            .set((
+                columns::retracted_at.eq($retracted_at),
+                columns::modified_at.eq($now_utc),
+            )).execute($conn)?;
+        target.count().get_result::<i64>($conn)? > 0
+    }};
+}
+
+/// Retracts or restores the entry.
+///
+/// Returns `false` if the entry is not found.
+fn set_retracted(conn: &SqliteConnection, msg: &SetRetracted) -> Result<bool, Error> {
+    let now_utc = Local::now().naive_utc();
+    let retracted_at = if msg.retracted { Some(now_utc) } else { None };
+
+    // Rows already in the requested state are not updated, so that
+    // tombstones are not duplicated.
+    // Triggers create tombstones and touch related rows for sync.
+    let found = match msg.target {
+        RetractTarget::GoodPhrase(id) => {
+            update_retracted!(conn, good_phrases, good_phrase_id, id, retracted_at, now_utc)
+        }
+        RetractTarget::Person(id) => {
+            update_retracted!(conn, persons, person_id, id, retracted_at, now_utc)
+        }
+        RetractTarget::GoodPhraseTag(id) => {
+            update_retracted!(conn, good_phrase_tags, good_phrase_tag_id, id, retracted_at, now_utc)
+        }
+    };
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+    use schema;
+
+    /// Returns the message to retract or restore the phrase.
+    fn phrase_msg(good_phrase_id: i32, retracted: bool) -> SetRetracted {
+        SetRetracted {
+            target: RetractTarget::GoodPhrase(good_phrase_id),
+            retracted,
+        }
+    }
+
+    /// Returns the number of tombstones of the phrase.
+    fn num_phrase_tombstones(conn: &SqliteConnection, good_phrase_id: i32) -> i64 {
+        use schema::tombstones::{columns, table};
+
+        table
+            .filter(columns::table_name.eq("good_phrases"))
+            .filter(columns::row_id.eq(good_phrase_id))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    /// Returns `modified_at` of the phrase.
+    fn phrase_modified_at(conn: &SqliteConnection, good_phrase_id: i32) -> NaiveDateTime {
+        use schema::good_phrases::{columns, table};
+
+        table
+            .find(good_phrase_id)
+            .select(columns::modified_at)
+            .get_result(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn retract_and_restore_phrase() {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name) VALUES (1, 'person');
+             INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id, modified_at)
+                 VALUES (1, 'title', 'phrase', 1, '2000-01-01 00:00:00');",
+        ).unwrap();
+        let initial = phrase_modified_at(&conn, 1);
+
+        assert!(set_retracted(&conn, &phrase_msg(1, true)).unwrap());
+        assert_eq!(num_phrase_tombstones(&conn, 1), 1);
+        let retracted = phrase_modified_at(&conn, 1);
+        assert!(retracted > initial);
+
+        // Retracting again neither duplicates the tombstone nor touches the row.
+        assert!(set_retracted(&conn, &phrase_msg(1, true)).unwrap());
+        assert_eq!(num_phrase_tombstones(&conn, 1), 1);
+        assert_eq!(phrase_modified_at(&conn, 1), retracted);
+
+        assert!(set_retracted(&conn, &phrase_msg(1, false)).unwrap());
+        assert_eq!(num_phrase_tombstones(&conn, 1), 1);
+        assert!(phrase_modified_at(&conn, 1) >= retracted);
+        let retracted_at = schema::good_phrases::table
+            .find(1)
+            .select(schema::good_phrases::columns::retracted_at)
+            .get_result::<Option<NaiveDateTime>>(&conn)
+            .unwrap();
+        assert_eq!(retracted_at, None);
+    }
+
+    #[test]
+    fn missing_entries_are_not_found() {
+        let conn = testing::establish();
+        assert!(!set_retracted(&conn, &phrase_msg(1, true)).unwrap());
+        let msg = SetRetracted {
+            target: RetractTarget::Person(1),
+            retracted: false,
+        };
+        assert!(!set_retracted(&conn, &msg).unwrap());
+    }
+}
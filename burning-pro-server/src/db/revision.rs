@@ -0,0 +1,204 @@
+//! Revisions of phrases.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use diesel;
+use diesel::prelude::*;
+
+use db::upsert_entry;
+use db::{DbExecutor, Error};
+use models;
+use schema;
+
+/// Revision (snapshot) of a phrase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct GoodPhraseRevision {
+    /// Revision ID.
+    pub good_phrase_revision_id: i32,
+    /// UTC datetime the revision is made at.
+    pub created_at: NaiveDateTime,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Name of the admin user who made the revision (empty if unknown).
+    pub user_name: String,
+    /// Title.
+    pub title: String,
+    /// Phrase.
+    pub phrase: String,
+    /// Person ID of the author.
+    pub person_id: i32,
+    /// URL of the phrase if exists.
+    pub url: Option<String>,
+    /// Whether the phrase is deleted.
+    pub deleted: bool,
+    /// UTC datetime the phrase is published at (if known).
+    pub published_at: Option<NaiveDateTime>,
+    /// Tag IDs of the phrase, in ascending order.
+    pub tag_ids: Vec<i32>,
+}
+
+impl GoodPhraseRevision {
+    /// Returns the upsert message to restore the phrase to this revision.
+    pub fn to_upsert(&self) -> upsert_entry::GoodPhrase {
+        upsert_entry::GoodPhrase {
+            good_phrase_id: Some(self.good_phrase_id),
+            title: self.title.clone(),
+            phrase: self.phrase.clone(),
+            person_id: self.person_id,
+            url: self.url.clone(),
+            deleted: self.deleted,
+            published_at: self.published_at.map(|dt| Local.from_utc_datetime(&dt)),
+            tag_ids: self.tag_ids.clone(),
+        }
+    }
+}
+
+impl From<models::GoodPhraseRevision> for GoodPhraseRevision {
+    fn from(row: models::GoodPhraseRevision) -> Self {
+        Self {
+            good_phrase_revision_id: row.good_phrase_revision_id,
+            created_at: row.created_at,
+            good_phrase_id: row.good_phrase_id,
+            user_name: row.user_name,
+            title: row.title,
+            phrase: row.phrase,
+            person_id: row.person_id,
+            url: row.url,
+            deleted: row.deleted,
+            published_at: row.published_at,
+            tag_ids: parse_tag_ids(&row.tag_ids),
+        }
+    }
+}
+
+/// Parses the comma-separated tag IDs, and returns them in ascending order.
+fn parse_tag_ids(s: &str) -> Vec<i32> {
+    let mut tag_ids = s
+        .split(',')
+        .filter_map(|id| id.trim().parse::<i32>().ok())
+        .collect::<Vec<_>>();
+    tag_ids.sort();
+    tag_ids
+}
+
+/// Records the current contents of the phrase as a new revision.
+///
+/// Nothing is recorded if the phrase is not changed from the latest revision.
+pub(super) fn record_revision(
+    conn: &SqliteConnection,
+    good_phrase_id: i32,
+    editor: &str,
+    now_utc: &NaiveDateTime,
+) -> Result<(), Error> {
+    use schema::good_phrase_revisions::{columns, table};
+
+    let phrase = schema::good_phrases::table
+        .find(good_phrase_id)
+        .first::<models::GoodPhrase>(conn)?;
+    let tag_ids = schema::good_phrases_and_tags::table
+        .filter(schema::good_phrases_and_tags::columns::good_phrase_id.eq(good_phrase_id))
+        .select(schema::good_phrases_and_tags::columns::good_phrase_tag_id)
+        .order(schema::good_phrases_and_tags::columns::good_phrase_tag_id.asc())
+        .load::<i32>(conn)?;
+
+    let latest = table
+        .filter(columns::good_phrase_id.eq(good_phrase_id))
+        .order(columns::good_phrase_revision_id.desc())
+        .first::<models::GoodPhraseRevision>(conn)
+        .optional()?
+        .map(GoodPhraseRevision::from);
+    if let Some(latest) = latest {
+        if latest.title == phrase.title
+            && latest.phrase == phrase.phrase
+            && latest.person_id == phrase.person_id
+            && latest.url == phrase.url
+            && latest.deleted == phrase.deleted
+            && latest.published_at == phrase.published_at
+            && latest.tag_ids == tag_ids
+        {
+            return Ok(());
+        }
+    }
+
+    let tag_ids = tag_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let new_row = models::NewGoodPhraseRevision {
+        good_phrase_revision_id: None,
+        created_at: now_utc,
+        modified_at: now_utc,
+        good_phrase_id,
+        user_name: editor,
+        title: &phrase.title,
+        phrase: &phrase.phrase,
+        person_id: phrase.person_id,
+        url: phrase.url.as_ref().map(AsRef::as_ref),
+        deleted: phrase.deleted,
+        published_at: phrase.published_at.as_ref(),
+        tag_ids: &tag_ids,
+    };
+    diesel::insert_into(table).values(new_row).execute(conn)?;
+    Ok(())
+}
+
+/// A message type to get revisions of a phrase.
+///
+/// Revisions are ordered from newest to oldest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetGoodPhraseRevisions {
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+}
+
+impl Message for GetGoodPhraseRevisions {
+    type Result = Result<Vec<GoodPhraseRevision>, Error>;
+}
+
+impl Handler<GetGoodPhraseRevisions> for DbExecutor {
+    type Result = <GetGoodPhraseRevisions as Message>::Result;
+
+    fn handle(&mut self, msg: GetGoodPhraseRevisions, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::good_phrase_revisions::{columns, table};
+
+        let conn = &self.pool().get()?;
+
+        let rows = table
+            .filter(columns::good_phrase_id.eq(msg.good_phrase_id))
+            .order(columns::good_phrase_revision_id.desc())
+            .load::<models::GoodPhraseRevision>(conn)?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A message type to get a revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetGoodPhraseRevision {
+    /// Revision ID.
+    pub good_phrase_revision_id: i32,
+}
+
+impl Message for GetGoodPhraseRevision {
+    type Result = Result<Option<GoodPhraseRevision>, Error>;
+}
+
+impl Handler<GetGoodPhraseRevision> for DbExecutor {
+    type Result = <GetGoodPhraseRevision as Message>::Result;
+
+    fn handle(&mut self, msg: GetGoodPhraseRevision, _ctx: &mut Self::Context) -> Self::Result {
+        use schema::good_phrase_revisions::table;
+
+        let conn = &self.pool().get()?;
+
+        let row = table
+            .find(msg.good_phrase_revision_id)
+            .first::<models::GoodPhraseRevision>(conn)
+            .optional()?;
+        Ok(row.map(Into::into))
+    }
+}
@@ -0,0 +1,244 @@
+//! `SearchGoodPhrases` and `SearchPhraseNgrams` messages.
+
+// Temporal silence until diesel-1.4.
+// See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
+#![allow(proc_macro_derive_resolution_fallback)]
+
+use actix::prelude::*;
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text};
+
+use db::get_good_phrases::load_responses_by_ids;
+use db::user::find_user_id;
+use db::{ngram, DbExecutor, Error};
+use good_phrase::response;
+
+/// A row of full-text search result.
+#[derive(Debug, Clone, Copy, QueryableByName)]
+struct SearchHit {
+    /// Phrase ID.
+    #[sql_type = "Integer"]
+    good_phrase_id: i32,
+}
+
+/// A phrase not yet added to the n-gram index.
+#[derive(Debug, Clone, QueryableByName)]
+struct UnindexedPhrase {
+    /// Phrase ID.
+    #[sql_type = "Integer"]
+    good_phrase_id: i32,
+    /// Phrase.
+    #[sql_type = "Text"]
+    phrase: String,
+}
+
+/// A message type to search good_phrases by full-text search.
+///
+/// Titles, phrases, and display names and real names of the authors are
+/// searched.
+#[derive(Debug, Clone)]
+pub struct SearchGoodPhrases {
+    /// Search words separated by whitespaces.
+    pub query: String,
+    /// Maximum number of phrases to return.
+    pub limit: i64,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
+
+impl Message for SearchGoodPhrases {
+    type Result = Result<Vec<response::GoodPhrase>, Error>;
+}
+
+impl Handler<SearchGoodPhrases> for DbExecutor {
+    type Result = <SearchGoodPhrases as Message>::Result;
+
+    fn handle(&mut self, msg: SearchGoodPhrases, _ctx: &mut Self::Context) -> Self::Result {
+        let match_expr = match fts_match_expr(&msg.query) {
+            Some(v) => v,
+            None => return Ok(Vec::new()),
+        };
+
+        let conn = &self.pool().get()?;
+        let hits = search_hits(conn, "good_phrases_fts", &match_expr, msg.limit)?;
+        load_hits(conn, &hits, msg.device_token.as_ref().map(AsRef::as_ref))
+    }
+}
+
+/// A message type to search good_phrases by substrings of phrases.
+///
+/// This uses n-gram index, so that Japanese texts can be searched by partial
+/// words.
+/// Width of characters and kana types (hiragana / katakana) are ignored.
+#[derive(Debug, Clone)]
+pub struct SearchPhraseNgrams {
+    /// Search words separated by whitespaces.
+    pub query: String,
+    /// Maximum number of phrases to return.
+    pub limit: i64,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
+
+impl Message for SearchPhraseNgrams {
+    type Result = Result<Vec<response::GoodPhrase>, Error>;
+}
+
+impl Handler<SearchPhraseNgrams> for DbExecutor {
+    type Result = <SearchPhraseNgrams as Message>::Result;
+
+    fn handle(&mut self, msg: SearchPhraseNgrams, _ctx: &mut Self::Context) -> Self::Result {
+        let match_expr = match ngram::match_expr(&msg.query) {
+            Some(v) => v,
+            None => return Ok(Vec::new()),
+        };
+
+        let conn = &self.pool().get()?;
+        let hits = search_hits(conn, "good_phrase_ngrams", &match_expr, msg.limit)?;
+        load_hits(conn, &hits, msg.device_token.as_ref().map(AsRef::as_ref))
+    }
+}
+
+/// A message type to add phrases missing in the n-gram index to the index.
+///
+/// This should be sent on startup, since phrases inserted before the n-gram
+/// index is created are not indexed.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexPhraseNgrams;
+
+impl Message for IndexPhraseNgrams {
+    type Result = Result<usize, Error>;
+}
+
+impl Handler<IndexPhraseNgrams> for DbExecutor {
+    type Result = <IndexPhraseNgrams as Message>::Result;
+
+    fn handle(&mut self, _msg: IndexPhraseNgrams, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let missing = diesel::sql_query(
+                "SELECT good_phrase_id, phrase FROM good_phrases \
+                 WHERE good_phrase_id NOT IN (SELECT rowid FROM good_phrase_ngrams)",
+            ).load::<UnindexedPhrase>(conn)?;
+            for row in &missing {
+                ngram::reindex_phrase(conn, row.good_phrase_id, &row.phrase)?;
+            }
+            Ok(missing.len())
+        })
+    }
+}
+
+/// Searches the full-text index table for visible phrases.
+///
+/// Retracted phrases and phrases of retracted persons are filtered out before
+/// `LIMIT` is applied, so that they do not take up the slots of the result.
+fn search_hits(
+    conn: &SqliteConnection,
+    index_table: &'static str,
+    match_expr: &str,
+    limit: i64,
+) -> Result<Vec<SearchHit>, Error> {
+    let query = format!(
+        "SELECT {index}.rowid AS good_phrase_id FROM {index} \
+         INNER JOIN good_phrases ON good_phrases.good_phrase_id = {index}.rowid \
+         INNER JOIN persons ON persons.person_id = good_phrases.person_id \
+         WHERE {index} MATCH ? \
+         AND good_phrases.retracted_at IS NULL AND persons.retracted_at IS NULL \
+         ORDER BY {index}.rank LIMIT ?",
+        index = index_table
+    );
+    diesel::sql_query(query)
+        .bind::<Text, _>(match_expr)
+        .bind::<BigInt, _>(limit)
+        .load::<SearchHit>(conn)
+        .map_err(Into::into)
+}
+
+/// Loads phrases for the search result, preserving the order.
+fn load_hits(
+    conn: &SqliteConnection,
+    hits: &[SearchHit],
+    device_token: Option<&str>,
+) -> Result<Vec<response::GoodPhrase>, Error> {
+    let user_id = match device_token {
+        Some(token) => find_user_id(conn, token)?,
+        None => None,
+    };
+    let good_phrase_ids = hits
+        .iter()
+        .map(|hit| hit.good_phrase_id)
+        .collect::<Vec<_>>();
+    load_responses_by_ids(conn, &good_phrase_ids, user_id)
+}
+
+/// Converts the user input into FTS5 match expression.
+///
+/// Each whitespace-separated word is quoted (to escape FTS5 query syntax) and
+/// used as a prefix.
+/// All words should match.
+///
+/// Returns `None` if the input has no words.
+fn fts_match_expr(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    /// Opens a DB with five indexed phrases starting with `word`, and retracts the
+    /// first phrase and the person of the second phrase.
+    fn seeded() -> SqliteConnection {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name) VALUES (1, 'alice'), (2, 'bob');
+             INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id)
+                 VALUES (1, 'title1', 'word1', 1), (2, 'title2', 'word2', 2),
+                     (3, 'title3', 'word3', 1), (4, 'title4', 'word4', 1),
+                     (5, 'title5', 'word5', 1);",
+        ).unwrap();
+        for id in 1..=5 {
+            ngram::reindex_phrase(&conn, id, &format!("word{}", id)).unwrap();
+        }
+        conn.batch_execute(
+            "UPDATE good_phrases SET retracted_at = CURRENT_TIMESTAMP WHERE good_phrase_id = 1;
+             UPDATE persons SET retracted_at = CURRENT_TIMESTAMP WHERE person_id = 2;",
+        ).unwrap();
+        conn
+    }
+
+    /// Returns IDs of the hits in ascending order.
+    fn hit_ids(hits: &[SearchHit]) -> Vec<i32> {
+        let mut ids = hits.iter().map(|hit| hit.good_phrase_id).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn fts_search_fills_limit_with_visible_phrases() {
+        let conn = seeded();
+        let match_expr = fts_match_expr("word").unwrap();
+        let hits = search_hits(&conn, "good_phrases_fts", &match_expr, 3).unwrap();
+        assert_eq!(hit_ids(&hits), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn ngram_search_fills_limit_with_visible_phrases() {
+        let conn = seeded();
+        let match_expr = ngram::match_expr("word").unwrap();
+        let hits = search_hits(&conn, "good_phrase_ngrams", &match_expr, 3).unwrap();
+        assert_eq!(hit_ids(&hits), vec![3, 4, 5]);
+    }
+}
@@ -0,0 +1,246 @@
+//! `GetChanges` message.
+
+use std::collections::BTreeSet;
+
+use actix::prelude::*;
+use chrono::{Duration, Local, NaiveDateTime};
+use diesel::prelude::*;
+
+use db::get_good_phrases::load_responses_by_ids;
+use db::user::find_user_id;
+use db::{DbExecutor, Error, MAX_IDS_PER_QUERY};
+use good_phrase::response;
+use models;
+use schema;
+
+/// Margin (in seconds) subtracted from `since` of incremental sync.
+///
+/// Rows written by transactions which started before the previous sync but
+/// committed after it have timestamps older than the watermark.
+/// The margin makes such rows be returned in the next sync.
+const SYNC_MARGIN_SECS: i64 = 10;
+
+/// A message type to get rows changed since the given datetime.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GetChanges {
+    /// UTC datetime of the previous sync, or `None` for full sync.
+    pub since: Option<NaiveDateTime>,
+    /// Device token of the user to get user-local metadata.
+    pub device_token: Option<String>,
+}
+
+impl Message for GetChanges {
+    type Result = Result<Changes, Error>;
+}
+
+/// Rows changed since the previous sync.
+#[derive(Debug, Clone)]
+pub struct Changes {
+    /// UTC datetime to be used as `since` of the next sync.
+    pub watermark: NaiveDateTime,
+    /// Created or updated phrases (including ones whose tags are changed).
+    pub good_phrases: Vec<response::GoodPhrase>,
+    /// Created or updated persons (including ones whose URLs or phrases are
+    /// changed), and their URLs.
+    pub persons: Vec<(models::Person, Vec<models::PersonUrl>)>,
+    /// Created or updated tags (including ones whose phrases are changed).
+    pub tags: Vec<models::GoodPhraseTag>,
+    /// Tombstones of rows deleted or retracted since the previous sync.
+    ///
+    /// This is empty for full sync.
+    /// Restored rows may have tombstones, but they are returned as created or
+    /// updated rows as well.
+    pub tombstones: Vec<models::Tombstone>,
+}
+
+impl Handler<GetChanges> for DbExecutor {
+    type Result = <GetChanges as Message>::Result;
+
+    fn handle(&mut self, msg: GetChanges, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        // Read all rows in a transaction to get a consistent snapshot.
+        conn.transaction::<_, Error, _>(|| {
+            let watermark = Local::now().naive_utc();
+            let user_id = match msg.device_token {
+                Some(ref token) => find_user_id(conn, token)?,
+                None => None,
+            };
+            let (good_phrase_ids, person_ids, tag_ids, tombstones) = match msg.since {
+                Some(since) => changed_ids(conn, since - Duration::seconds(SYNC_MARGIN_SECS))?,
+                None => all_ids(conn)?,
+            };
+            let good_phrase_ids = good_phrase_ids.into_iter().collect::<Vec<_>>();
+            Ok(Changes {
+                watermark,
+                good_phrases: load_responses_by_ids(conn, &good_phrase_ids, user_id)?,
+                persons: load_persons(conn, &person_ids)?,
+                tags: load_tags(conn, &tag_ids)?,
+                tombstones,
+            })
+        })
+    }
+}
+
+/// IDs of phrases, persons, and tags to be returned, and tombstones.
+type ChangedIds = (
+    BTreeSet<i32>,
+    BTreeSet<i32>,
+    BTreeSet<i32>,
+    Vec<models::Tombstone>,
+);
+
+/// Returns IDs of all phrases, persons, and tags not hidden by retraction.
+fn all_ids(conn: &SqliteConnection) -> Result<ChangedIds, Error> {
+    let good_phrase_ids = schema::good_phrases::table
+        .inner_join(schema::persons::table)
+        .filter(schema::good_phrases::columns::retracted_at.is_null())
+        .filter(schema::persons::columns::retracted_at.is_null())
+        .select(schema::good_phrases::columns::good_phrase_id)
+        .load::<i32>(conn)?;
+    let person_ids = schema::persons::table
+        .filter(schema::persons::columns::retracted_at.is_null())
+        .select(schema::persons::columns::person_id)
+        .load::<i32>(conn)?;
+    let tag_ids = schema::good_phrase_tags::table
+        .filter(schema::good_phrase_tags::columns::retracted_at.is_null())
+        .select(schema::good_phrase_tags::columns::good_phrase_tag_id)
+        .load::<i32>(conn)?;
+    Ok((
+        good_phrase_ids.into_iter().collect(),
+        person_ids.into_iter().collect(),
+        tag_ids.into_iter().collect(),
+        Vec::new(),
+    ))
+}
+
+/// Returns IDs of phrases, persons, and tags changed since the given UTC
+/// datetime, and tombstones created since then.
+///
+/// Rows are regarded as changed if their child rows (URLs and tag links) or
+/// phrases counted in them are changed.
+fn changed_ids(conn: &SqliteConnection, since: NaiveDateTime) -> Result<ChangedIds, Error> {
+    let tombstones = {
+        use schema::tombstones::columns;
+
+        schema::tombstones::table
+            .filter(columns::created_at.ge(since))
+            .order(columns::tombstone_id.asc())
+            .load::<models::Tombstone>(conn)?
+    };
+    let good_phrases = {
+        use schema::good_phrases::columns;
+
+        schema::good_phrases::table
+            .filter(columns::modified_at.ge(since))
+            .select((columns::good_phrase_id, columns::person_id))
+            .load::<(i32, i32)>(conn)?
+    };
+    let links = {
+        use schema::good_phrases_and_tags::columns;
+
+        schema::good_phrases_and_tags::table
+            .filter(columns::modified_at.ge(since))
+            .select((columns::good_phrase_id, columns::good_phrase_tag_id))
+            .load::<(i32, i32)>(conn)?
+    };
+    let person_url_person_ids = {
+        use schema::person_urls::columns;
+
+        schema::person_urls::table
+            .filter(columns::modified_at.ge(since))
+            .select(columns::person_id)
+            .load::<i32>(conn)?
+    };
+    let person_ids = {
+        use schema::persons::columns;
+
+        schema::persons::table
+            .filter(columns::modified_at.ge(since))
+            .select(columns::person_id)
+            .load::<i32>(conn)?
+    };
+    let tag_ids = {
+        use schema::good_phrase_tags::columns;
+
+        schema::good_phrase_tags::table
+            .filter(columns::modified_at.ge(since))
+            .select(columns::good_phrase_tag_id)
+            .load::<i32>(conn)?
+    };
+
+    let mut changed_good_phrase_ids = BTreeSet::new();
+    let mut changed_person_ids = person_ids
+        .into_iter()
+        .chain(person_url_person_ids)
+        .collect::<BTreeSet<_>>();
+    let mut changed_tag_ids = tag_ids.into_iter().collect::<BTreeSet<_>>();
+    for (good_phrase_id, person_id) in good_phrases {
+        changed_good_phrase_ids.insert(good_phrase_id);
+        changed_person_ids.insert(person_id);
+    }
+    for (good_phrase_id, tag_id) in links {
+        changed_good_phrase_ids.insert(good_phrase_id);
+        changed_tag_ids.insert(tag_id);
+    }
+    for tombstone in &tombstones {
+        match tombstone.table_name.as_str() {
+            "good_phrases" | "person_urls" => changed_person_ids.extend(tombstone.parent_id),
+            "good_phrases_and_tags" => {
+                changed_good_phrase_ids.extend(tombstone.parent_id);
+                changed_tag_ids.extend(tombstone.linked_id);
+            }
+            _ => {}
+        }
+    }
+    Ok((
+        changed_good_phrase_ids,
+        changed_person_ids,
+        changed_tag_ids,
+        tombstones,
+    ))
+}
+
+/// Loads persons with the given IDs and their URLs, ordered by ID.
+///
+/// Nonexistent IDs and IDs of retracted persons are ignored.
+fn load_persons(
+    conn: &SqliteConnection,
+    person_ids: &BTreeSet<i32>,
+) -> Result<Vec<(models::Person, Vec<models::PersonUrl>)>, Error> {
+    let person_ids = person_ids.iter().cloned().collect::<Vec<_>>();
+    let mut res = Vec::with_capacity(person_ids.len());
+    for ids in person_ids.chunks(MAX_IDS_PER_QUERY) {
+        let persons = schema::persons::table
+            .filter(schema::persons::columns::person_id.eq_any(ids))
+            .filter(schema::persons::columns::retracted_at.is_null())
+            .order(schema::persons::columns::person_id.asc())
+            .load::<models::Person>(conn)?;
+        let urls = models::PersonUrl::belonging_to(&persons)
+            .order(schema::person_urls::columns::person_url_id.asc())
+            .load::<models::PersonUrl>(conn)?
+            .grouped_by(&persons);
+        res.extend(persons.into_iter().zip(urls));
+    }
+    Ok(res)
+}
+
+/// Loads tags with the given IDs, ordered by ID.
+///
+/// Nonexistent IDs and IDs of retracted tags are ignored.
+fn load_tags(
+    conn: &SqliteConnection,
+    tag_ids: &BTreeSet<i32>,
+) -> Result<Vec<models::GoodPhraseTag>, Error> {
+    let tag_ids = tag_ids.iter().cloned().collect::<Vec<_>>();
+    let mut res = Vec::with_capacity(tag_ids.len());
+    for ids in tag_ids.chunks(MAX_IDS_PER_QUERY) {
+        res.extend(
+            schema::good_phrase_tags::table
+                .filter(schema::good_phrase_tags::columns::good_phrase_tag_id.eq_any(ids))
+                .filter(schema::good_phrase_tags::columns::retracted_at.is_null())
+                .order(schema::good_phrase_tags::columns::good_phrase_tag_id.asc())
+                .load::<models::GoodPhraseTag>(conn)?,
+        );
+    }
+    Ok(res)
+}
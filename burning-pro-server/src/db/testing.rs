@@ -0,0 +1,112 @@
+//! Helpers for tests of DB operations.
+
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+
+use diesel::connection::{AnsiTransactionManager, SimpleConnection};
+use diesel::deserialize::{Queryable, QueryableByName};
+use diesel::prelude::*;
+use diesel::query_builder::{AsQuery, QueryFragment, QueryId};
+use diesel::sql_types::HasSqlType;
+use diesel::sqlite::Sqlite;
+
+/// Opens a new in-memory DB with all migrations applied.
+pub(super) fn establish() -> SqliteConnection {
+    let conn = SqliteConnection::establish(":memory:").expect("Failed to open in-memory DB");
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+    let mut migrations = fs::read_dir(&dir)
+        .expect("Failed to read migrations")
+        .map(|entry| entry.expect("Failed to read migrations").path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    // Migrations are named with their datetimes.
+    migrations.sort();
+    for migration in migrations {
+        let sql = fs::read_to_string(migration.join("up.sql")).expect("Failed to read up.sql");
+        conn.batch_execute(&sql)
+            .unwrap_or_else(|e| panic!("Failed to run {}: {}", migration.display(), e));
+    }
+    conn
+}
+
+/// SQLite connection which counts queries.
+pub(super) struct CountingConnection {
+    /// Connection.
+    conn: SqliteConnection,
+    /// Number of queries.
+    count: Cell<usize>,
+}
+
+impl CountingConnection {
+    /// Creates a new `CountingConnection` wrapping the connection.
+    pub(super) fn new(conn: SqliteConnection) -> Self {
+        Self {
+            conn,
+            count: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of queries since the last call.
+    pub(super) fn take_count(&self) -> usize {
+        self.count.replace(0)
+    }
+
+    /// Counts a query.
+    fn increment(&self) {
+        self.count.set(self.count.get() + 1);
+    }
+}
+
+impl SimpleConnection for CountingConnection {
+    fn batch_execute(&self, query: &str) -> QueryResult<()> {
+        self.increment();
+        self.conn.batch_execute(query)
+    }
+}
+
+impl Connection for CountingConnection {
+    type Backend = Sqlite;
+    type TransactionManager = AnsiTransactionManager;
+
+    fn establish(database_url: &str) -> ConnectionResult<Self> {
+        SqliteConnection::establish(database_url).map(Self::new)
+    }
+
+    fn execute(&self, query: &str) -> QueryResult<usize> {
+        self.increment();
+        self.conn.execute(query)
+    }
+
+    fn query_by_index<T, U>(&self, source: T) -> QueryResult<Vec<U>>
+    where
+        T: AsQuery,
+        T::Query: QueryFragment<Self::Backend> + QueryId,
+        Self::Backend: HasSqlType<T::SqlType>,
+        U: Queryable<T::SqlType, Self::Backend>,
+    {
+        self.increment();
+        self.conn.query_by_index(source)
+    }
+
+    fn query_by_name<T, U>(&self, source: &T) -> QueryResult<Vec<U>>
+    where
+        T: QueryFragment<Self::Backend> + QueryId,
+        U: QueryableByName<Self::Backend>,
+    {
+        self.increment();
+        self.conn.query_by_name(source)
+    }
+
+    fn execute_returning_count<T>(&self, source: &T) -> QueryResult<usize>
+    where
+        T: QueryFragment<Self::Backend> + QueryId,
+    {
+        self.increment();
+        self.conn.execute_returning_count(source)
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        self.conn.transaction_manager()
+    }
+}
@@ -5,15 +5,18 @@
 #![allow(proc_macro_derive_resolution_fallback)]
 
 use actix::prelude::*;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDateTime};
 use diesel;
 use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use serde_json::{Map, Value};
 
-use db::{DbExecutor, Error};
+use db::audit::{self, AuditAction, AuditTable, Audited};
+use db::{ngram, revision, DbExecutor, Error, PhraseRequestStatus};
 use models;
 use schema;
 
-no_arg_sql_function!(last_insert_rowid, i32, "Returns last inserted row ID.");
+no_arg_sql_function!(last_insert_rowid, Integer, "Returns last inserted row ID.");
 
 /// A phrase.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -37,107 +40,198 @@ pub struct GoodPhrase {
 }
 
 impl Message for GoodPhrase {
-    type Result = Result<(), Error>;
+    type Result = Result<UpsertResult, Error>;
 }
 
-impl Handler<GoodPhrase> for DbExecutor {
-    type Result = <GoodPhrase as Message>::Result;
+/// Result of upsert messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UpsertResult {
+    /// The entry is inserted or updated, with the given row ID.
+    Upserted(i32),
+    /// The entry to be updated is not found.
+    NotFound,
+    /// The person with the given ID does not exist.
+    UnknownPerson(i32),
+    /// The tag with the given ID does not exist.
+    UnknownTag(i32),
+}
 
-    fn handle(&mut self, msg: GoodPhrase, _ctx: &mut Self::Context) -> Self::Result {
-        use schema::good_phrases::columns;
+impl From<Option<i32>> for UpsertResult {
+    fn from(id: Option<i32>) -> Self {
+        match id {
+            Some(id) => UpsertResult::Upserted(id),
+            None => UpsertResult::NotFound,
+        }
+    }
+}
+
+impl Handler<Audited<GoodPhrase>> for DbExecutor {
+    type Result = <GoodPhrase as Message>::Result;
 
+    fn handle(&mut self, msg: Audited<GoodPhrase>, _ctx: &mut Self::Context) -> Self::Result {
         let conn = &self.pool().get()?;
         let now_utc = Local::now().naive_utc();
-
-        let GoodPhrase {
-            good_phrase_id,
-            title,
-            phrase,
-            person_id,
-            url,
-            deleted,
-            published_at,
-            tag_ids,
-        } = msg;
+        let Audited { editor, msg } = msg;
 
         // Use transaction to get correct `last_insert_rowid` result.
         conn.transaction::<_, Error, _>(|| {
-            let good_phrase_id = match good_phrase_id {
+            let before = match msg.good_phrase_id {
                 Some(good_phrase_id) => {
-                    // Update.
-                    diesel::update(
-                        schema::good_phrases::table
-                            .filter(columns::good_phrase_id.eq(good_phrase_id)),
-                    ).set((
-                        columns::modified_at.eq(now_utc),
-                        columns::title.eq(title),
-                        columns::phrase.eq(phrase),
-                        columns::person_id.eq(person_id),
-                        columns::url.eq(url),
-                        columns::deleted.eq(deleted),
-                        columns::published_at.eq(published_at.map(|dt| dt.naive_utc())),
-                    )).execute(conn)?;
-                    good_phrase_id
-                }
-                None => {
-                    // Insert.
-                    let published_at_utc = published_at.map(|dt| dt.naive_utc());
-                    let new_row = models::NewGoodPhrase {
-                        good_phrase_id: None,
-                        created_at: &now_utc,
-                        modified_at: &now_utc,
-                        title: &title,
-                        phrase: &phrase,
-                        person_id,
-                        url: url.as_ref().map(AsRef::as_ref),
-                        deleted,
-                        published_at: published_at_utc.as_ref(),
-                    };
-                    // NOTE: SQLite backend does not support "returning clause".
-                    // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
-                    // Although you can retrieve last inserted row ID:
-                    // See <https://github.com/diesel-rs/diesel/issues/771>.
-                    diesel::insert_into(schema::good_phrases::table)
-                        .values(new_row)
-                        .execute(conn)?;
-                    diesel::select(last_insert_rowid).execute(conn)? as i32
+                    match audit::snapshot(conn, AuditTable::GoodPhrases, good_phrase_id)? {
+                        Some(before) => Some(before),
+                        None => return Ok(UpsertResult::NotFound),
+                    }
                 }
+                None => None,
             };
-
-            // Update tags relations.
-            let current_ids = schema::good_phrases_and_tags::table
-                .filter(schema::good_phrases_and_tags::columns::good_phrase_id.eq(good_phrase_id))
-                .select(schema::good_phrases_and_tags::columns::good_phrase_tag_id)
-                .load::<i32>(conn)?;
-            for delete_id in current_ids.iter().filter(|id| !tag_ids.contains(id)) {
-                diesel::delete(
-                    schema::good_phrases_and_tags::table
-                        .filter(
-                            schema::good_phrases_and_tags::columns::good_phrase_id
-                                .eq(good_phrase_id),
-                        ).filter(
-                            schema::good_phrases_and_tags::columns::good_phrase_tag_id
-                                .eq(delete_id),
-                        ),
-                ).execute(conn)?;
+            if let Some(unknown) = check_references(conn, msg.person_id, &msg.tag_ids)? {
+                return Ok(unknown);
             }
-            for insert_id in tag_ids.iter().filter(|id| !current_ids.contains(id)) {
-                let row = models::NewGoodPhraseAndTag {
-                    good_phrase_and_tag_id: None,
-                    created_at: &now_utc,
-                    modified_at: &now_utc,
-                    good_phrase_id,
-                    good_phrase_tag_id: *insert_id,
-                };
-                diesel::insert_into(schema::good_phrases_and_tags::table)
-                    .values(row)
-                    .execute(conn)?;
-            }
-            Ok(())
+            let good_phrase_id = upsert_good_phrase(conn, msg, &editor, &now_utc)?;
+            record_upsert(conn, &editor, AuditTable::GoodPhrases, good_phrase_id, before)?;
+            Ok(UpsertResult::Upserted(good_phrase_id))
         })
     }
 }
 
+/// Checks whether the person and the tags referred by a phrase exist.
+///
+/// Returns `UnknownPerson` or `UnknownTag` for the first missing one, since
+/// SQLite does not check foreign keys by default.
+/// Retracted persons and tags exist, so that phrases referring to them can
+/// still be edited.
+pub(super) fn check_references(
+    conn: &SqliteConnection,
+    person_id: i32,
+    tag_ids: &[i32],
+) -> Result<Option<UpsertResult>, Error> {
+    if !person_exists(conn, person_id)? {
+        return Ok(Some(UpsertResult::UnknownPerson(person_id)));
+    }
+    Ok(find_unknown_tag(conn, tag_ids)?.map(UpsertResult::UnknownTag))
+}
+
+/// Checks whether the person with the given ID exists.
+pub(super) fn person_exists(conn: &SqliteConnection, person_id: i32) -> Result<bool, Error> {
+    Ok(diesel::select(diesel::dsl::exists(
+        schema::persons::table.find(person_id),
+    )).get_result::<bool>(conn)?)
+}
+
+/// Returns the first tag ID which does not exist.
+pub(super) fn find_unknown_tag(
+    conn: &SqliteConnection,
+    tag_ids: &[i32],
+) -> Result<Option<i32>, Error> {
+    use schema::good_phrase_tags::columns;
+
+    let existing_tag_ids = schema::good_phrase_tags::table
+        .filter(columns::good_phrase_tag_id.eq_any(tag_ids))
+        .select(columns::good_phrase_tag_id)
+        .load::<i32>(conn)?;
+    Ok(tag_ids
+        .iter()
+        .find(|id| !existing_tag_ids.contains(id))
+        .cloned())
+}
+
+/// Inserts or updates the phrase, and returns the row ID of the phrase.
+///
+/// The new contents are recorded as a revision made by `editor`.
+/// This should be called in a transaction to get correct `last_insert_rowid`
+/// result.
+pub(super) fn upsert_good_phrase(
+    conn: &SqliteConnection,
+    msg: GoodPhrase,
+    editor: &str,
+    now_utc: &NaiveDateTime,
+) -> Result<i32, Error> {
+    use schema::good_phrases::columns;
+
+    let GoodPhrase {
+        good_phrase_id,
+        title,
+        phrase,
+        person_id,
+        url,
+        deleted,
+        published_at,
+        tag_ids,
+    } = msg;
+
+    let good_phrase_id = match good_phrase_id {
+        Some(good_phrase_id) => {
+            // Update.
+            diesel::update(
+                schema::good_phrases::table.filter(columns::good_phrase_id.eq(good_phrase_id)),
+            ).set((
+                columns::modified_at.eq(*now_utc),
+                columns::title.eq(&title),
+                columns::phrase.eq(&phrase),
+                columns::person_id.eq(person_id),
+                columns::url.eq(url),
+                columns::deleted.eq(deleted),
+                columns::published_at.eq(published_at.map(|dt| dt.naive_utc())),
+            )).execute(conn)?;
+            good_phrase_id
+        }
+        None => {
+            // Insert.
+            let published_at_utc = published_at.map(|dt| dt.naive_utc());
+            let new_row = models::NewGoodPhrase {
+                good_phrase_id: None,
+                created_at: now_utc,
+                modified_at: now_utc,
+                title: &title,
+                phrase: &phrase,
+                person_id,
+                url: url.as_ref().map(AsRef::as_ref),
+                deleted,
+                published_at: published_at_utc.as_ref(),
+            };
+            // NOTE: SQLite backend does not support "returning clause".
+            // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
+            // Although you can retrieve last inserted row ID:
+            // See <https://github.com/diesel-rs/diesel/issues/771>.
+            diesel::insert_into(schema::good_phrases::table)
+                .values(new_row)
+                .execute(conn)?;
+            diesel::select(last_insert_rowid).get_result::<i32>(conn)?
+        }
+    };
+
+    ngram::reindex_phrase(conn, good_phrase_id, &phrase)?;
+
+    // Update tags relations.
+    let current_ids = schema::good_phrases_and_tags::table
+        .filter(schema::good_phrases_and_tags::columns::good_phrase_id.eq(good_phrase_id))
+        .select(schema::good_phrases_and_tags::columns::good_phrase_tag_id)
+        .load::<i32>(conn)?;
+    for delete_id in current_ids.iter().filter(|id| !tag_ids.contains(id)) {
+        diesel::delete(
+            schema::good_phrases_and_tags::table
+                .filter(schema::good_phrases_and_tags::columns::good_phrase_id.eq(good_phrase_id))
+                .filter(schema::good_phrases_and_tags::columns::good_phrase_tag_id.eq(delete_id)),
+        ).execute(conn)?;
+    }
+    for insert_id in tag_ids.iter().filter(|id| !current_ids.contains(id)) {
+        let row = models::NewGoodPhraseAndTag {
+            good_phrase_and_tag_id: None,
+            created_at: now_utc,
+            modified_at: now_utc,
+            good_phrase_id,
+            good_phrase_tag_id: *insert_id,
+        };
+        diesel::insert_into(schema::good_phrases_and_tags::table)
+            .values(row)
+            .execute(conn)?;
+    }
+
+    revision::record_revision(conn, good_phrase_id, editor, now_utc)?;
+
+    Ok(good_phrase_id)
+}
+
 /// A phrase request
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct GoodPhraseRequest {
@@ -151,29 +245,122 @@ pub struct GoodPhraseRequest {
     pub deleted: bool,
     /// Datetime when the phrase is published.
     pub published_at: Option<DateTime<Local>>,
+    /// Digest of the receipt token given to the submitter.
+    pub receipt_token_digest: String,
+    /// Proposed tags.
+    pub tags: Vec<RequestedTag>,
+}
+
+/// A tag proposed by a phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RequestedTag {
+    /// Existing tag with the given ID.
+    Existing(i32),
+    /// New tag with the given name.
+    Proposed(String),
+}
+
+/// Result of `GoodPhraseRequest` message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GoodPhraseRequestResult {
+    /// The request is inserted with the given ID.
+    Inserted(i32),
+    /// The same phrase of the same person is already registered as the
+    /// good_phrase with the given ID.
+    DuplicateGoodPhrase(i32),
+    /// The same phrase of the same person is already requested as the request
+    /// with the given ID.
+    DuplicateRequest(i32),
+    /// The tag with the given ID does not exist.
+    UnknownTag(i32),
 }
 
 impl Message for GoodPhraseRequest {
-    type Result = Result<(), Error>;
+    type Result = Result<GoodPhraseRequestResult, Error>;
 }
 
 impl Handler<GoodPhraseRequest> for DbExecutor {
     type Result = <GoodPhraseRequest as Message>::Result;
 
     fn handle(&mut self, msg: GoodPhraseRequest, _ctx: &mut Self::Context) -> Self::Result {
-        use schema::good_phrase_requests::table;
-
         let conn = &self.pool().get()?;
+        insert_phrase_request(conn, msg)
+    }
+}
 
-        let GoodPhraseRequest {
-            phrase,
-            person,
-            url,
-            deleted,
-            published_at,
-        } = msg;
+/// Inserts the phrase request unless it is a duplicate or has unknown tags.
+fn insert_phrase_request(
+    conn: &SqliteConnection,
+    msg: GoodPhraseRequest,
+) -> Result<GoodPhraseRequestResult, Error> {
+    use schema::good_phrase_requests::table;
+
+    let GoodPhraseRequest {
+        phrase,
+        person,
+        url,
+        deleted,
+        published_at,
+        receipt_token_digest,
+        tags,
+    } = msg;
+
+    let published_at_utc = published_at.map(|dt| dt.naive_utc());
+
+    // Check duplicates and insert in a transaction, so that concurrent
+    // requests for the same phrase are not inserted twice.
+    // The write lock is taken first to avoid deadlocks between readers
+    // trying to upgrade their locks.
+    conn.immediate_transaction::<_, Error, _>(|| {
+        let duplicate_good_phrase_id = {
+            use schema::good_phrases::columns;
+            use schema::persons::columns::{display_name, real_name};
+
+            schema::good_phrases::table
+                .inner_join(schema::persons::table)
+                .filter(columns::phrase.eq(&phrase))
+                .filter(display_name.eq(&person).or(real_name.eq(&person)))
+                .select(columns::good_phrase_id)
+                .first::<i32>(conn)
+                .optional()?
+        };
+        if let Some(id) = duplicate_good_phrase_id {
+            return Ok(GoodPhraseRequestResult::DuplicateGoodPhrase(id));
+        }
+        let duplicate_request_id = {
+            use schema::good_phrase_requests::columns;
+
+            // Only pending requests are duplicates, so that rejected phrases
+            // can be requested again.
+            table
+                .filter(columns::phrase.eq(&phrase))
+                .filter(columns::person.eq(&person))
+                .filter(columns::status.eq(PhraseRequestStatus::Pending.as_str()))
+                .select(columns::good_phrase_request_id)
+                .first::<i32>(conn)
+                .optional()?
+        };
+        if let Some(id) = duplicate_request_id {
+            return Ok(GoodPhraseRequestResult::DuplicateRequest(id));
+        }
 
-        let published_at_utc = published_at.map(|dt| dt.naive_utc());
+        let tag_ids = tags
+            .iter()
+            .filter_map(|tag| match *tag {
+                RequestedTag::Existing(id) => Some(id),
+                RequestedTag::Proposed(_) => None,
+            }).collect::<Vec<_>>();
+        let existing_tag_ids = {
+            use schema::good_phrase_tags::columns;
+
+            schema::good_phrase_tags::table
+                .filter(columns::good_phrase_tag_id.eq_any(&tag_ids))
+                .select(columns::good_phrase_tag_id)
+                .load::<i32>(conn)?
+        };
+        if let Some(&id) = tag_ids.iter().find(|id| !existing_tag_ids.contains(id)) {
+            return Ok(GoodPhraseRequestResult::UnknownTag(id));
+        }
 
         let new_row = models::NewGoodPhraseRequest {
             good_phrase_request_id: None,
@@ -182,12 +369,29 @@ impl Handler<GoodPhraseRequest> for DbExecutor {
             url: url.as_ref().map(AsRef::as_ref),
             deleted,
             published_at: published_at_utc.as_ref(),
+            receipt_token_digest: Some(&receipt_token_digest),
         };
-
         diesel::insert_into(table).values(new_row).execute(conn)?;
+        let good_phrase_request_id =
+            diesel::select(last_insert_rowid).get_result::<i32>(conn)?;
 
-        Ok(())
-    }
+        for tag in &tags {
+            let (good_phrase_tag_id, name) = match *tag {
+                RequestedTag::Existing(id) => (Some(id), None),
+                RequestedTag::Proposed(ref name) => (None, Some(name.as_str())),
+            };
+            let row = models::NewGoodPhraseRequestTag {
+                good_phrase_request_id,
+                good_phrase_tag_id,
+                name,
+            };
+            diesel::insert_into(schema::good_phrase_request_tags::table)
+                .values(row)
+                .execute(conn)?;
+        }
+
+        Ok(GoodPhraseRequestResult::Inserted(good_phrase_request_id))
+    })
 }
 
 /// A person.
@@ -206,94 +410,115 @@ pub struct Person {
 }
 
 impl Message for Person {
-    type Result = Result<(), Error>;
+    /// Row ID of the person, or `None` if the person to be updated is not
+    /// found.
+    type Result = Result<Option<i32>, Error>;
 }
 
-impl Handler<Person> for DbExecutor {
+impl Handler<Audited<Person>> for DbExecutor {
     type Result = <Person as Message>::Result;
 
-    fn handle(&mut self, msg: Person, _ctx: &mut Self::Context) -> Self::Result {
-        use schema::persons::columns;
-
+    fn handle(&mut self, msg: Audited<Person>, _ctx: &mut Self::Context) -> Self::Result {
         let conn = &self.pool().get()?;
         let now_utc = Local::now().naive_utc();
-
-        let Person {
-            person_id,
-            real_name,
-            display_name,
-            url: urls,
-            twitter,
-        } = msg;
+        let Audited { editor, msg } = msg;
 
         // Use transaction to get correct `last_insert_rowid` result.
         conn.transaction::<_, Error, _>(|| {
-            let person_id = match person_id {
-                Some(person_id) => {
-                    // Update.
-                    diesel::update(schema::persons::table.filter(columns::person_id.eq(person_id)))
-                        .set((
-                            columns::modified_at.eq(now_utc),
-                            columns::real_name.eq(real_name),
-                            columns::display_name.eq(display_name),
-                            columns::twitter.eq(twitter),
-                        )).execute(conn)?;
-                    person_id
-                }
-                None => {
-                    let new_row = models::NewPerson {
-                        person_id: None,
-                        created_at: &now_utc,
-                        modified_at: &now_utc,
-                        real_name: real_name.as_ref().map(AsRef::as_ref),
-                        display_name: display_name.as_ref(),
-                        twitter: twitter.as_ref().map(AsRef::as_ref),
-                    };
-                    // NOTE: SQLite backend does not support "returning clause".
-                    // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
-                    // Although you can retrieve last inserted row ID:
-                    // See <https://github.com/diesel-rs/diesel/issues/771>.
-                    diesel::insert_into(schema::persons::table)
-                        .values(new_row)
-                        .execute(conn)?;
-                    diesel::select(last_insert_rowid).execute(conn)? as i32
-                }
+            let before = match msg.person_id {
+                Some(person_id) => match audit::snapshot(conn, AuditTable::Persons, person_id)? {
+                    Some(before) => Some(before),
+                    None => return Ok(None),
+                },
+                None => None,
             };
-
-            // Update URLs.
-            let current_urls = schema::person_urls::table
-                .filter(schema::person_urls::columns::person_id.eq(person_id))
-                .select((
-                    schema::person_urls::columns::person_url_id,
-                    schema::person_urls::columns::url,
-                )).load::<(i32, String)>(conn)?;
-
-            for (delete_id, _) in current_urls.iter().filter(|(_, url)| !urls.contains(url)) {
-                diesel::delete(
-                    schema::person_urls::table
-                        .filter(schema::person_urls::columns::person_url_id.eq(delete_id)),
-                ).execute(conn)?;
-            }
-            for url in urls
-                .iter()
-                .filter(|url| !current_urls.iter().any(|(_, v)| v == *url))
-            {
-                let row = models::NewPersonUrl {
-                    person_url_id: None,
-                    created_at: &now_utc,
-                    modified_at: &now_utc,
-                    person_id,
-                    url,
-                };
-                diesel::insert_into(schema::person_urls::table)
-                    .values(row)
-                    .execute(conn)?;
-            }
-            Ok(())
+            let person_id = upsert_person(conn, msg, &now_utc)?;
+            record_upsert(conn, &editor, AuditTable::Persons, person_id, before)?;
+            Ok(Some(person_id))
         })
     }
 }
 
+/// Inserts or updates the person, and returns the row ID of the person.
+///
+/// This should be called in a transaction to get correct `last_insert_rowid`
+/// result.
+pub(super) fn upsert_person(
+    conn: &SqliteConnection,
+    msg: Person,
+    now_utc: &NaiveDateTime,
+) -> Result<i32, Error> {
+    use schema::persons::columns;
+
+    let Person {
+        person_id,
+        real_name,
+        display_name,
+        url: urls,
+        twitter,
+    } = msg;
+
+    let person_id = match person_id {
+        Some(person_id) => {
+            // Update.
+            diesel::update(schema::persons::table.filter(columns::person_id.eq(person_id)))
+                .set((
+                    columns::modified_at.eq(*now_utc),
+                    columns::real_name.eq(real_name),
+                    columns::display_name.eq(display_name),
+                    columns::twitter.eq(twitter),
+                )).execute(conn)?;
+            person_id
+        }
+        None => {
+            let new_row = models::NewPerson {
+                person_id: None,
+                created_at: now_utc,
+                modified_at: now_utc,
+                real_name: real_name.as_ref().map(AsRef::as_ref),
+                display_name: display_name.as_ref(),
+                twitter: twitter.as_ref().map(AsRef::as_ref),
+            };
+            // NOTE: SQLite backend does not support "returning clause".
+            // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
+            // Although you can retrieve last inserted row ID:
+            // See <https://github.com/diesel-rs/diesel/issues/771>.
+            diesel::insert_into(schema::persons::table)
+                .values(new_row)
+                .execute(conn)?;
+            diesel::select(last_insert_rowid).get_result::<i32>(conn)?
+        }
+    };
+
+    // Update URLs.
+    let current_urls = schema::person_urls::table
+        .filter(schema::person_urls::columns::person_id.eq(person_id))
+        .select((
+            schema::person_urls::columns::person_url_id,
+            schema::person_urls::columns::url,
+        )).load::<(i32, String)>(conn)?;
+
+    for (delete_id, _) in current_urls.iter().filter(|(_, url)| !urls.contains(url)) {
+        diesel::delete(
+            schema::person_urls::table
+                .filter(schema::person_urls::columns::person_url_id.eq(delete_id)),
+        ).execute(conn)?;
+    }
+    for url in urls.iter().filter(|url| !current_urls.iter().any(|(_, v)| v == *url)) {
+        let row = models::NewPersonUrl {
+            person_url_id: None,
+            created_at: now_utc,
+            modified_at: now_utc,
+            person_id,
+            url,
+        };
+        diesel::insert_into(schema::person_urls::table)
+            .values(row)
+            .execute(conn)?;
+    }
+    Ok(person_id)
+}
+
 /// A tag.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct GoodPhraseTag {
@@ -306,47 +531,171 @@ pub struct GoodPhraseTag {
 }
 
 impl Message for GoodPhraseTag {
-    type Result = Result<(), Error>;
+    /// Row ID of the tag, or `None` if the tag to be updated is not found.
+    type Result = Result<Option<i32>, Error>;
 }
 
-impl Handler<GoodPhraseTag> for DbExecutor {
+impl Handler<Audited<GoodPhraseTag>> for DbExecutor {
     type Result = <GoodPhraseTag as Message>::Result;
 
-    fn handle(&mut self, msg: GoodPhraseTag, _ctx: &mut Self::Context) -> Self::Result {
-        use schema::good_phrase_tags::{columns, table};
-
+    fn handle(&mut self, msg: Audited<GoodPhraseTag>, _ctx: &mut Self::Context) -> Self::Result {
         let conn = &self.pool().get()?;
-
-        let GoodPhraseTag {
-            good_phrase_tag_id,
-            name,
-            description,
-        } = msg;
+        let now_utc = Local::now().naive_utc();
+        let Audited { editor, msg } = msg;
 
         // Use transaction to get correct `last_insert_rowid` result.
-        match good_phrase_tag_id {
-            Some(good_phrase_tag_id) => {
-                // Update.
-                diesel::update(table.filter(columns::good_phrase_tag_id.eq(good_phrase_tag_id)))
-                    .set((columns::name.eq(name), columns::description.eq(description)))
-                    .execute(conn)?;
-            }
-            None => {
-                let now_utc = Local::now().naive_utc();
-                let new_row = models::NewGoodPhraseTag {
-                    good_phrase_tag_id: None,
-                    created_at: &now_utc,
-                    modified_at: &now_utc,
-                    name: &name,
-                    description: description.as_ref().map(AsRef::as_ref),
-                };
-                // NOTE: SQLite backend does not support "returning clause".
-                // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
-                // Although you can retrieve last inserted row ID:
-                // See <https://github.com/diesel-rs/diesel/issues/771>.
-                diesel::insert_into(table).values(new_row).execute(conn)?;
-            }
+        conn.transaction::<_, Error, _>(|| {
+            let before = match msg.good_phrase_tag_id {
+                Some(good_phrase_tag_id) => {
+                    match audit::snapshot(conn, AuditTable::GoodPhraseTags, good_phrase_tag_id)? {
+                        Some(before) => Some(before),
+                        None => return Ok(None),
+                    }
+                }
+                None => None,
+            };
+            let good_phrase_tag_id = upsert_good_phrase_tag(conn, msg, &now_utc)?;
+            record_upsert(
+                conn,
+                &editor,
+                AuditTable::GoodPhraseTags,
+                good_phrase_tag_id,
+                before,
+            )?;
+            Ok(Some(good_phrase_tag_id))
+        })
+    }
+}
+
+/// Inserts or updates the tag, and returns the row ID of the tag.
+///
+/// This should be called in a transaction to get correct `last_insert_rowid`
+/// result.
+pub(super) fn upsert_good_phrase_tag(
+    conn: &SqliteConnection,
+    msg: GoodPhraseTag,
+    now_utc: &NaiveDateTime,
+) -> Result<i32, Error> {
+    use schema::good_phrase_tags::{columns, table};
+
+    let GoodPhraseTag {
+        good_phrase_tag_id,
+        name,
+        description,
+    } = msg;
+
+    match good_phrase_tag_id {
+        Some(good_phrase_tag_id) => {
+            // Update.
+            diesel::update(table.filter(columns::good_phrase_tag_id.eq(good_phrase_tag_id)))
+                .set((
+                    columns::modified_at.eq(*now_utc),
+                    columns::name.eq(name),
+                    columns::description.eq(description),
+                )).execute(conn)?;
+            Ok(good_phrase_tag_id)
+        }
+        None => {
+            let new_row = models::NewGoodPhraseTag {
+                good_phrase_tag_id: None,
+                created_at: now_utc,
+                modified_at: now_utc,
+                name: &name,
+                description: description.as_ref().map(AsRef::as_ref),
+            };
+            // NOTE: SQLite backend does not support "returning clause".
+            // See <https://docs.diesel.rs/diesel/backend/trait.SupportsReturningClause.html>.
+            // Although you can retrieve last inserted row ID:
+            // See <https://github.com/diesel-rs/diesel/issues/771>.
+            diesel::insert_into(table).values(new_row).execute(conn)?;
+            Ok(diesel::select(last_insert_rowid).get_result::<i32>(conn)?)
+        }
+    }
+}
+
+/// Records the upsert of the entry to the audit log.
+///
+/// `before` is the snapshot before the upsert, or `None` if the entry is
+/// created.
+fn record_upsert(
+    conn: &SqliteConnection,
+    editor: &str,
+    table: AuditTable,
+    row_id: i32,
+    before: Option<Map<String, Value>>,
+) -> Result<(), Error> {
+    let action = if before.is_some() {
+        AuditAction::Update
+    } else {
+        AuditAction::Create
+    };
+    // Nothing is changed if the entry to be updated does not exist.
+    match audit::snapshot(conn, table, row_id)? {
+        Some(after) => audit::record(conn, editor, action, table, row_id, before, Some(after)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    #[test]
+    fn references_of_phrases_should_exist() {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name, retracted_at)
+                 VALUES (1, 'person', NULL), (2, 'retracted', CURRENT_TIMESTAMP);
+             INSERT INTO good_phrase_tags (good_phrase_tag_id, name) VALUES (1, 'tag');",
+        ).unwrap();
+
+        assert_eq!(check_references(&conn, 1, &[]).unwrap(), None);
+        assert_eq!(check_references(&conn, 2, &[1]).unwrap(), None);
+        assert_eq!(
+            check_references(&conn, 3, &[1]).unwrap(),
+            Some(UpsertResult::UnknownPerson(3))
+        );
+        assert_eq!(
+            check_references(&conn, 1, &[1, 2]).unwrap(),
+            Some(UpsertResult::UnknownTag(2))
+        );
+    }
+
+    /// Returns the request for the phrase.
+    fn request(phrase: &str) -> GoodPhraseRequest {
+        GoodPhraseRequest {
+            phrase: phrase.to_owned(),
+            person: "person".to_owned(),
+            url: None,
+            deleted: false,
+            published_at: None,
+            receipt_token_digest: "digest".to_owned(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_pending_requests_are_duplicates() {
+        let conn = testing::establish();
+        let pending_id = match insert_phrase_request(&conn, request("pending")).unwrap() {
+            GoodPhraseRequestResult::Inserted(id) => id,
+            result => panic!("Unexpected result: {:?}", result),
+        };
+        assert_eq!(
+            insert_phrase_request(&conn, request("pending")).unwrap(),
+            GoodPhraseRequestResult::DuplicateRequest(pending_id)
+        );
+
+        conn.batch_execute(
+            "INSERT INTO good_phrase_requests (phrase, person, status)
+                 VALUES ('rejected', 'person', 'rejected');",
+        ).unwrap();
+        match insert_phrase_request(&conn, request("rejected")).unwrap() {
+            GoodPhraseRequestResult::Inserted(_) => {}
+            result => panic!("Unexpected result: {:?}", result),
         }
-        Ok(())
     }
 }
@@ -0,0 +1,162 @@
+//! DB messages for app users.
+
+use actix::prelude::*;
+use chrono::Local;
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+
+use db::get_good_phrases::ensure_visible;
+use db::{DbExecutor, Error};
+use models;
+use schema;
+
+/// Returns the user ID for the given device token, if registered.
+pub(super) fn find_user_id<C>(conn: &C, device_token: &str) -> Result<Option<i32>, Error>
+where
+    C: Connection<Backend = Sqlite>,
+{
+    use schema::users::columns;
+
+    Ok(schema::users::table
+        .filter(columns::device_token.eq(device_token))
+        .select(columns::user_id)
+        .first::<i32>(conn)
+        .optional()?)
+}
+
+/// Returns the user ID for the given device token, registering the token if
+/// necessary.
+pub(super) fn get_or_create_user_id(
+    conn: &SqliteConnection,
+    device_token: &str,
+) -> Result<i32, Error> {
+    if let Some(user_id) = find_user_id(conn, device_token)? {
+        return Ok(user_id);
+    }
+    let now_utc = Local::now().naive_utc();
+    let new_row = models::NewUser {
+        user_id: None,
+        created_at: &now_utc,
+        modified_at: &now_utc,
+        device_token,
+    };
+    diesel::insert_into(schema::users::table)
+        .values(new_row)
+        .execute(conn)?;
+    let user_id = find_user_id(conn, device_token)?.ok_or(diesel::result::Error::NotFound)?;
+    Ok(user_id)
+}
+
+/// A message type to add or remove the phrase to / from the user's favorites.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SetFavorite {
+    /// Device token of the user.
+    pub device_token: String,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Whether the phrase should be favorited.
+    pub favorite: bool,
+}
+
+impl Message for SetFavorite {
+    type Result = Result<(), Error>;
+}
+
+impl Handler<SetFavorite> for DbExecutor {
+    type Result = <SetFavorite as Message>::Result;
+
+    fn handle(&mut self, msg: SetFavorite, _ctx: &mut Self::Context) -> Self::Result {
+        let conn = &self.pool().get()?;
+        set_favorite(conn, &msg)
+    }
+}
+
+/// Adds or removes the phrase to / from the user's favorites.
+///
+/// Retracted phrases can be removed, but cannot be added.
+fn set_favorite(conn: &SqliteConnection, msg: &SetFavorite) -> Result<(), Error> {
+    use schema::good_phrase_favorites::columns;
+
+    conn.transaction::<_, Error, _>(|| {
+        if msg.favorite {
+            // Ensure the phrase exists and is not retracted.
+            ensure_visible(conn, msg.good_phrase_id)?;
+        }
+
+        let user_id = get_or_create_user_id(conn, &msg.device_token)?;
+        let current = schema::good_phrase_favorites::table
+            .filter(columns::user_id.eq(user_id))
+            .filter(columns::good_phrase_id.eq(msg.good_phrase_id));
+        let is_favorited = diesel::select(diesel::dsl::exists(current)).get_result(conn)?;
+        match (is_favorited, msg.favorite) {
+            (false, true) => {
+                let now_utc = Local::now().naive_utc();
+                let new_row = models::NewGoodPhraseFavorite {
+                    good_phrase_favorite_id: None,
+                    created_at: &now_utc,
+                    modified_at: &now_utc,
+                    user_id,
+                    good_phrase_id: msg.good_phrase_id,
+                };
+                diesel::insert_into(schema::good_phrase_favorites::table)
+                    .values(new_row)
+                    .execute(conn)?;
+            }
+            (true, false) => {
+                diesel::delete(current).execute(conn)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+    use db::testing;
+
+    /// Returns the message to set the phrase as a favorite.
+    fn set_msg(good_phrase_id: i32, favorite: bool) -> SetFavorite {
+        SetFavorite {
+            device_token: "token".to_owned(),
+            good_phrase_id,
+            favorite,
+        }
+    }
+
+    /// Returns the number of favorites.
+    fn num_favorites(conn: &SqliteConnection) -> i64 {
+        schema::good_phrase_favorites::table
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn retracted_phrase_can_be_unfavorited_but_not_favorited() {
+        let conn = testing::establish();
+        conn.batch_execute(
+            "INSERT INTO persons (person_id, display_name) VALUES (1, 'person');
+             INSERT INTO good_phrases (good_phrase_id, title, phrase, person_id)
+                 VALUES (1, 'title', 'phrase', 1);",
+        ).unwrap();
+        set_favorite(&conn, &set_msg(1, true)).unwrap();
+        assert_eq!(num_favorites(&conn), 1);
+
+        // Retraction of the person hides the phrase.
+        conn.batch_execute("UPDATE persons SET retracted_at = CURRENT_TIMESTAMP;")
+            .unwrap();
+        set_favorite(&conn, &set_msg(1, false)).unwrap();
+        assert_eq!(num_favorites(&conn), 0);
+
+        match set_favorite(&conn, &set_msg(1, true)) {
+            Err(Error::Db(diesel::result::Error::NotFound)) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(num_favorites(&conn), 0);
+    }
+}
@@ -1,30 +1,438 @@
 //! GoodPhrase service.
 
+use std::collections::HashMap;
+
 use actix::prelude::*;
-use actix_web::{AsyncResponder, Error, FutureResponse, HttpRequest, HttpResponse};
-use futures::future::Future;
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::header;
+use actix_web::{AsyncResponder, Error, FutureResponse, HttpRequest, HttpResponse, Path, Query};
+use chrono::{FixedOffset, Utc};
+use diesel;
+use futures::future::{self, Future};
 
-use app::AppState;
-use db::{DbExecutor, GetGoodPhrases};
+use app::{
+    client_ip, device_token, require_device_token, AppState, JsonError, Validators,
+    DEVICE_TOKEN_HEADER,
+};
+use db::{
+    self, DbExecutor, GetDailyGoodPhrase, GetFeedVersion, GetGoodPhrases, GetGoodPhrasesByIds,
+    GetRandomGoodPhrase, GoodPhraseQuery, PhraseEventKind, RecordEvent, SearchGoodPhrases,
+    SearchPhraseNgrams, SetFavorite,
+};
+use good_phrase::ranking::GetRanking;
 
+pub mod query;
+pub mod ranking;
 pub mod response;
 
+/// `max-age` (in seconds) of the good_phrases feed responses without
+/// user-local metadata.
+const FEED_MAX_AGE_SECS: u32 = 60;
+
 /// Processes the request for good_phrase texts.
+///
+/// Conditional requests with `If-None-Match` or `If-Modified-Since` are
+/// responded with `304 Not Modified` if no phrases (and related rows) are
+/// changed since the previous response.
 #[allow(unknown_lints, needless_pass_by_value)]
-pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+pub fn index(
+    req: HttpRequest<AppState>,
+    query: Query<query::GoodPhrases>,
+) -> FutureResponse<HttpResponse> {
     debug!("request for `good_phrases::index()`: {:?}", req);
-    fetch_good_phrases(req.state().db()).responder()
+    let cursor = match query.cursor() {
+        Ok(cursor) => cursor,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = GetGoodPhrases {
+        filter: query.filter(),
+        cursor,
+        limit: Some(query.limit()),
+        device_token: device_token(&req),
+    };
+    let db = req.state().db().clone();
+    db.send(GetFeedVersion)
+        .from_err()
+        .and_then(move |res| -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+            let version = match res {
+                Ok(version) => version,
+                Err(e) => {
+                    error!("`good_phrases::index()`: {}", e);
+                    return Box::new(future::err(JsonError::internal("DB error").into()));
+                }
+            };
+            let validators = Validators::new(version.fingerprint, version.last_modified);
+            let fresh = validators.is_fresh(&req);
+            let mut resp = if fresh {
+                HttpResponse::NotModified()
+            } else {
+                HttpResponse::Ok()
+            };
+            validators.set_headers(&mut resp);
+            resp.set(feed_cache_control(msg.device_token.is_some()))
+                .header(header::VARY, DEVICE_TOKEN_HEADER);
+            if fresh {
+                return Box::new(future::ok(resp.finish()));
+            }
+            Box::new(fetch_good_phrases(&db, msg, resp))
+        }).responder()
 }
 
-/// Returns the good_phrases.
-fn fetch_good_phrases(db: &Addr<DbExecutor>) -> impl Future<Item = HttpResponse, Error = Error> {
-    db.send(GetGoodPhrases)
+/// Processes the request for the good_phrase.
+///
+/// The phrase row is checked first, so that missing and retracted phrases are
+/// responded without building the response object.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn show(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::show()`: {:?}", req);
+    let good_phrase_id = path.into_inner();
+    let db = req.state().db().clone();
+    let msg = GetGoodPhrasesByIds {
+        good_phrase_ids: vec![good_phrase_id],
+        device_token: device_token(&req),
+    };
+    req.state()
+        .db()
+        .send(GoodPhraseQuery::PhraseId(good_phrase_id))
+        .from_err()
+        .and_then(|res| match res {
+            Ok(ref rows) if rows.iter().any(|row| row.retracted_at.is_none()) => Ok(()),
+            Ok(_) | Err(db::Error::Db(diesel::result::Error::NotFound)) => {
+                debug!("Phrase not found.");
+                Err(JsonError::not_found("Phrase not found").into())
+            }
+            Err(e) => {
+                error!("`good_phrases::show()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).and_then(move |()| db.send(msg).from_err())
+        .and_then(|res| match res {
+            // The phrase is hidden if the person is retracted.
+            Ok(mut contents) => match contents.pop() {
+                Some(content) => Ok(HttpResponse::Ok().json(content)),
+                None => Err(JsonError::not_found("Phrase not found").into()),
+            },
+            Err(e) => {
+                error!("`good_phrases::show()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for good_phrase search.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn search(
+    req: HttpRequest<AppState>,
+    query: Query<query::Search>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::search()`: {:?}", req);
+    let msg = SearchGoodPhrases {
+        query: query.q.clone(),
+        limit: query.limit(),
+        device_token: device_token(&req),
+    };
+    req.state()
+        .db()
+        .send(msg)
         .from_err()
         .and_then(|res| match res {
             Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => {
+                error!("`good_phrases::search()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for good_phrase search by substrings of phrases.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn search_phrase(
+    req: HttpRequest<AppState>,
+    query: Query<query::Search>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::search_phrase()`: {:?}", req);
+    let msg = SearchPhraseNgrams {
+        query: query.q.clone(),
+        limit: query.limit(),
+        device_token: device_token(&req),
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => {
+                error!("`good_phrases::search_phrase()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request to add the phrase to the user's favorites.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn favorite(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::favorite()`: {:?}", req);
+    set_favorite(path.into_inner(), true, &req)
+}
+
+/// Processes the request to remove the phrase from the user's favorites.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn unfavorite(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::unfavorite()`: {:?}", req);
+    set_favorite(path.into_inner(), false, &req)
+}
+
+/// Internal implementation for `favorite()` and `unfavorite()`.
+fn set_favorite(
+    good_phrase_id: i32,
+    favorite: bool,
+    req: &HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    let device_token = match require_device_token(req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = SetFavorite {
+        device_token,
+        good_phrase_id,
+        favorite,
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(()) => Ok(HttpResponse::NoContent().finish()),
+            Err(db::Error::Db(diesel::result::Error::NotFound)) => {
+                debug!("Phrase not found.");
+                Err(JsonError::not_found("Phrase not found").into())
+            }
+            Err(e) => {
+                error!("`good_phrases::set_favorite()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for a random good_phrase.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn random(
+    req: HttpRequest<AppState>,
+    query: Query<query::Random>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::random()`: {:?}", req);
+    let msg = GetRandomGoodPhrase {
+        filter: query.filter(),
+        device_token: device_token(&req),
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(Some(contents)) => Ok(HttpResponse::Ok().json(contents)),
+            Ok(None) => Err(JsonError::not_found("No phrases found").into()),
+            Err(e) => {
+                error!("`good_phrases::random()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for the good_phrase of the day.
+///
+/// The day changes at midnight in JST.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn daily(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::daily()`: {:?}", req);
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+    let date = Utc::now().with_timezone(&tz_offset).date().naive_local();
+    let msg = GetDailyGoodPhrase {
+        date,
+        device_token: device_token(&req),
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(Some(good_phrase)) => {
+                Ok(HttpResponse::Ok().json(response::DailyGoodPhrase { date, good_phrase }))
+            }
+            Ok(None) => Err(JsonError::not_found("No phrases found").into()),
+            Err(e) => {
+                error!("`good_phrases::daily()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Processes the request for good_phrase ranking.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn ranking(
+    req: HttpRequest<AppState>,
+    query: Query<query::Ranking>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::ranking()`: {:?}", req);
+    let window = match query.window() {
+        Ok(window) => window,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let kind = query.kind();
+    let limit = query.limit() as usize;
+    let device_token = device_token(&req);
+    let db = req.state().db().clone();
+    req.state()
+        .ranking_cache()
+        .send(GetRanking { kind, window })
+        .from_err()
+        .and_then(move |cached| {
+            let (updated, mut phrases) = match cached {
+                Some(cached) => (Some(cached.updated), cached.phrases),
+                None => (None, Vec::new()),
+            };
+            phrases.truncate(limit);
+            let msg = GetGoodPhrasesByIds {
+                good_phrase_ids: phrases.iter().map(|p| p.good_phrase_id).collect(),
+                device_token,
+            };
+            db.send(msg).from_err().and_then(move |res| match res {
+                Ok(good_phrases) => {
+                    let scores = phrases
+                        .iter()
+                        .map(|p| (p.good_phrase_id, p.score))
+                        .collect::<HashMap<_, _>>();
+                    let good_phrases = good_phrases
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, good_phrase)| response::RankedGoodPhrase {
+                            rank: i + 1,
+                            score: scores[&good_phrase.phrase.internal_id],
+                            good_phrase,
+                        }).collect();
+                    Ok(HttpResponse::Ok().json(response::Ranking {
+                        kind,
+                        window: window.as_str().to_owned(),
+                        updated,
+                        good_phrases,
+                    }))
+                }
+                Err(e) => {
+                    error!("`good_phrases::ranking()`: {}", e);
+                    Err(JsonError::internal("DB error").into())
+                }
+            })
+        }).responder()
+}
+
+/// Processes the report of the view of the phrase.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn report_view(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::report_view()`: {:?}", req);
+    report_event(path.into_inner(), PhraseEventKind::View, &req)
+}
+
+/// Processes the report of the use of the phrase (such as copying or sending).
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn report_use(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `good_phrases::report_use()`: {:?}", req);
+    report_event(path.into_inner(), PhraseEventKind::Use, &req)
+}
+
+/// Internal implementation for `report_view()` and `report_use()`.
+///
+/// The device token is optional.
+/// Reports from the same client (IP address and device token) for the same
+/// phrase and event kind are accepted at most once per interval.
+/// Device tokens are chosen by clients, so reports from the same IP address
+/// are also limited regardless of device tokens, allowing clients behind the
+/// same address to report.
+/// Other reports are rejected with `429 Too Many Requests`, and count toward
+/// neither limit.
+fn report_event(
+    good_phrase_id: i32,
+    kind: PhraseEventKind,
+    req: &HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    let device_token = device_token(req);
+    let ip = format!("ip:{}", client_ip(req).unwrap_or_default());
+    let client = match device_token {
+        Some(ref token) => format!("{}|token:{}", ip, token),
+        None => ip.clone(),
+    };
+    let event_key = format!("{}/{:?}/{}", client, kind, good_phrase_id);
+    let limited = req
+        .state()
+        .event_limiter()
+        .check(&event_key)
+        .and_then(|_| {
+            let ip_key = format!("{}/{:?}/{}", ip, kind, good_phrase_id);
+            let limited = req.state().event_ip_limiter().check(&ip_key);
+            if limited.is_err() {
+                // Rejected reports do not start the interval of the device.
+                req.state().event_limiter().refund(&event_key);
+            }
+            limited
+        });
+    if let Err(wait) = limited {
+        debug!("Too many {:?} reports from {:?}", kind, client);
+        let e = JsonError::too_many_requests("Too many reports", wait);
+        return Box::new(future::err(e.into()));
+    }
+    let msg = RecordEvent {
+        device_token,
+        good_phrase_id,
+        kind,
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(()) => Ok(HttpResponse::NoContent().finish()),
+            Err(db::Error::Db(diesel::result::Error::NotFound)) => {
+                debug!("Phrase not found.");
+                Err(JsonError::not_found("Phrase not found").into())
+            }
+            Err(e) => {
+                error!("`good_phrases::report_event()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Returns the good_phrases.
+fn fetch_good_phrases(
+    db: &Addr<DbExecutor>,
+    msg: GetGoodPhrases,
+    mut resp: HttpResponseBuilder,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    db.send(msg)
+        .from_err()
+        .and_then(move |res| match res {
+            Ok(contents) => Ok(resp.json(contents)),
             Err(e) => {
                 error!("`fetch_good_phrases()`: {}", e);
-                Ok(HttpResponse::InternalServerError().into())
+                Err(JsonError::internal("DB error").into())
             }
         })
 }
+
+/// Returns `Cache-Control` header for the good_phrases feed.
+///
+/// Responses with user-local metadata should not be stored in shared caches.
+fn feed_cache_control(private: bool) -> header::CacheControl {
+    if private {
+        header::CacheControl(vec![
+            header::CacheDirective::Private,
+            header::CacheDirective::NoCache,
+        ])
+    } else {
+        header::CacheControl(vec![
+            header::CacheDirective::Public,
+            header::CacheDirective::MaxAge(FEED_MAX_AGE_SECS),
+        ])
+    }
+}
@@ -0,0 +1,179 @@
+//! Query parameter types.
+
+use actix_web::error::Error;
+use chrono::{DateTime, FixedOffset};
+
+use app::JsonError;
+use db::{GoodPhraseCursor, GoodPhraseFilter, RankingKind, RankingWindow};
+use good_phrase::ranking::MAX_RANKING_LEN;
+
+/// Default number of good_phrases in a page.
+const DEFAULT_LIMIT: i64 = 100;
+/// Maximum number of good_phrases in a page.
+const MAX_LIMIT: i64 = 1000;
+/// Default number of search results.
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+/// Maximum number of search results.
+const MAX_SEARCH_LIMIT: i64 = 100;
+/// Default number of phrases in a ranking.
+const DEFAULT_RANKING_LIMIT: i64 = 20;
+
+/// Query parameters for good_phrases list.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GoodPhrases {
+    /// Tag name the phrase should have.
+    pub tag: Option<String>,
+    /// Person ID of the author.
+    pub person_id: Option<i32>,
+    /// Lower bound (inclusive) of the datetime the phrase is published at.
+    ///
+    /// This should be RFC 3339 datetime string.
+    pub published_after: Option<DateTime<FixedOffset>>,
+    /// Upper bound (exclusive) of the datetime the phrase is published at.
+    ///
+    /// This should be RFC 3339 datetime string.
+    pub published_before: Option<DateTime<FixedOffset>>,
+    /// Whether to include phrases whose source web page is deleted.
+    ///
+    /// Default is `true`.
+    pub include_deleted: Option<bool>,
+    /// Maximum number of phrases in a page.
+    ///
+    /// Default is 100, and the value larger than 1000 is regarded as 1000.
+    pub limit: Option<i64>,
+    /// Opaque cursor returned as `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+impl GoodPhrases {
+    /// Returns filter conditions for DB query.
+    pub fn filter(&self) -> GoodPhraseFilter {
+        GoodPhraseFilter {
+            tag: self.tag.clone(),
+            person_id: self.person_id,
+            published_after: self.published_after,
+            published_before: self.published_before,
+            include_deleted: self.include_deleted.unwrap_or(true),
+        }
+    }
+
+    /// Returns the page size.
+    pub fn limit(&self) -> i64 {
+        match self.limit {
+            Some(limit) if limit > MAX_LIMIT => MAX_LIMIT,
+            Some(limit) if limit < 1 => 1,
+            Some(limit) => limit,
+            None => DEFAULT_LIMIT,
+        }
+    }
+
+    /// Returns the decoded cursor.
+    ///
+    /// Returns `Err(_)` if the cursor is specified but invalid.
+    pub fn cursor(&self) -> Result<Option<GoodPhraseCursor>, Error> {
+        match self.cursor {
+            Some(ref cursor) => GoodPhraseCursor::decode(cursor)
+                .map(Some)
+                .ok_or_else(|| {
+                    debug!("Invalid cursor: {:?}", cursor);
+                    JsonError::bad_request("Invalid cursor").into()
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Query parameters for a random good_phrase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Random {
+    /// Tag name the phrase should have.
+    pub tag: Option<String>,
+    /// Person ID of the author.
+    pub person_id: Option<i32>,
+    /// Whether to include phrases whose source web page is deleted.
+    ///
+    /// Default is `true`.
+    pub include_deleted: Option<bool>,
+}
+
+impl Random {
+    /// Returns filter conditions for DB query.
+    pub fn filter(&self) -> GoodPhraseFilter {
+        GoodPhraseFilter {
+            tag: self.tag.clone(),
+            person_id: self.person_id,
+            include_deleted: self.include_deleted.unwrap_or(true),
+            ..Default::default()
+        }
+    }
+}
+
+/// Query parameters for good_phrases search.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Search {
+    /// Search words separated by whitespaces.
+    pub q: String,
+    /// Maximum number of results.
+    ///
+    /// Default is 20, and the value larger than 100 is regarded as 100.
+    pub limit: Option<i64>,
+}
+
+impl Search {
+    /// Returns the maximum number of results.
+    pub fn limit(&self) -> i64 {
+        match self.limit {
+            Some(limit) if limit > MAX_SEARCH_LIMIT => MAX_SEARCH_LIMIT,
+            Some(limit) if limit < 1 => 1,
+            Some(limit) => limit,
+            None => DEFAULT_SEARCH_LIMIT,
+        }
+    }
+}
+
+/// Query parameters for good_phrase rankings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ranking {
+    /// Ranking kind (`popular`, `trending`, or `new`).
+    ///
+    /// Default is `popular`.
+    pub kind: Option<RankingKind>,
+    /// Time window (`1d` (or `24h`), `7d`, or `30d`).
+    ///
+    /// Default is `7d`.
+    pub window: Option<String>,
+    /// Maximum number of phrases.
+    ///
+    /// Default is 20, and the value larger than 100 is regarded as 100.
+    pub limit: Option<i64>,
+}
+
+impl Ranking {
+    /// Returns the ranking kind.
+    pub fn kind(&self) -> RankingKind {
+        self.kind.unwrap_or(RankingKind::Popular)
+    }
+
+    /// Returns the time window.
+    ///
+    /// Returns `Err(_)` if the window is specified but unsupported.
+    pub fn window(&self) -> Result<RankingWindow, Error> {
+        match self.window {
+            Some(ref window) => RankingWindow::parse(window).ok_or_else(|| {
+                debug!("Unsupported window: {:?}", window);
+                JsonError::bad_request("Unsupported window").into()
+            }),
+            None => Ok(RankingWindow::Week),
+        }
+    }
+
+    /// Returns the maximum number of phrases.
+    pub fn limit(&self) -> i64 {
+        match self.limit {
+            Some(limit) if limit > MAX_RANKING_LEN => MAX_RANKING_LEN,
+            Some(limit) if limit < 1 => 1,
+            Some(limit) => limit,
+            None => DEFAULT_RANKING_LIMIT,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Ranking cache actor.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::prelude::*;
+use chrono::{DateTime, Local};
+
+use db::{ComputeRanking, DbExecutor, RankedPhrase, RankingKind, RankingWindow};
+
+/// Interval to refresh rankings.
+const REFRESH_INTERVAL_SECS: u64 = 5 * 60;
+/// Maximum number of phrases kept in each ranking.
+pub const MAX_RANKING_LEN: i64 = 100;
+
+/// A cached ranking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedRanking {
+    /// Datetime the ranking is computed at.
+    pub updated: DateTime<Local>,
+    /// Ranked phrases, highest first.
+    pub phrases: Vec<RankedPhrase>,
+}
+
+/// Ranking cache.
+///
+/// Objects of this type are actors, which compute all rankings on startup and
+/// refresh them periodically, so that requests are served without computing
+/// rankings.
+pub struct RankingCache {
+    /// Address of DB executor actor.
+    db: Addr<DbExecutor>,
+    /// Cached rankings.
+    rankings: HashMap<(RankingKind, RankingWindow), CachedRanking>,
+}
+
+impl RankingCache {
+    /// Creates a new `RankingCache`.
+    pub fn new(db: Addr<DbExecutor>) -> Self {
+        Self {
+            db,
+            rankings: HashMap::new(),
+        }
+    }
+
+    /// Requests the DB executor to compute all rankings, and updates the cache
+    /// when they are computed.
+    fn refresh(&mut self, ctx: &mut Context<Self>) {
+        let now = Local::now();
+        for &kind in RankingKind::all() {
+            for &window in RankingWindow::all() {
+                let msg = ComputeRanking {
+                    kind,
+                    window,
+                    now: now.naive_utc(),
+                    limit: MAX_RANKING_LEN,
+                };
+                let fut = self
+                    .db
+                    .send(msg)
+                    .into_actor(self)
+                    .map(move |res, act, _ctx| match res {
+                        Ok(phrases) => {
+                            let ranking = CachedRanking {
+                                updated: now,
+                                phrases,
+                            };
+                            act.rankings.insert((kind, window), ranking);
+                        }
+                        Err(e) => error!("Failed to compute ranking {:?}: {}", (kind, window), e),
+                    }).map_err(move |e, _act, _ctx| {
+                        error!("Failed to compute ranking {:?}: {}", (kind, window), e)
+                    });
+                ctx.spawn(fut);
+            }
+        }
+    }
+}
+
+impl Actor for RankingCache {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.refresh(ctx);
+        ctx.run_interval(Duration::from_secs(REFRESH_INTERVAL_SECS), |act, ctx| {
+            act.refresh(ctx)
+        });
+    }
+}
+
+/// A message type to get a cached ranking.
+///
+/// Returns `None` if the ranking is not computed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GetRanking {
+    /// Ranking kind.
+    pub kind: RankingKind,
+    /// Time window.
+    pub window: RankingWindow,
+}
+
+impl Message for GetRanking {
+    type Result = Option<CachedRanking>;
+}
+
+impl Handler<GetRanking> for RankingCache {
+    type Result = MessageResult<GetRanking>;
+
+    fn handle(&mut self, msg: GetRanking, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(self.rankings.get(&(msg.kind, msg.window)).cloned())
+    }
+}
@@ -1,6 +1,50 @@
 //! Response types.
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
+
+use db::RankingKind;
+
+/// A page of good_phrases.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GoodPhrasePage {
+    /// Good_phrases.
+    pub good_phrases: Vec<GoodPhrase>,
+    /// Cursor to get the next page, or `None` if this is the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// The good_phrase of the day.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DailyGoodPhrase {
+    /// The day (in JST).
+    pub date: NaiveDate,
+    /// Good_phrase.
+    pub good_phrase: GoodPhrase,
+}
+
+/// A ranking of good_phrases.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ranking {
+    /// Ranking kind.
+    pub kind: RankingKind,
+    /// Time window.
+    pub window: String,
+    /// Datetime when the ranking is computed, or `None` if not computed yet.
+    pub updated: Option<DateTime<Local>>,
+    /// Ranked good_phrases, highest first.
+    pub good_phrases: Vec<RankedGoodPhrase>,
+}
+
+/// A good_phrase in a ranking.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankedGoodPhrase {
+    /// Rank (1-origin).
+    pub rank: usize,
+    /// Score (`None` for `new` ranking).
+    pub score: Option<f64>,
+    /// Good_phrase.
+    pub good_phrase: GoodPhrase,
+}
 
 /// An good_phrase.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
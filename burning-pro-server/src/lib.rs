@@ -3,7 +3,10 @@
 
 extern crate actix;
 extern crate actix_web;
+extern crate argon2;
+extern crate base64;
 extern crate chrono;
+extern crate cookie;
 #[macro_use]
 extern crate diesel;
 extern crate dotenv;
@@ -13,19 +16,27 @@ extern crate futures;
 #[macro_use]
 extern crate log;
 extern crate r2d2;
+extern crate ring;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate serde_urlencoded;
 #[macro_use]
 extern crate tera;
+extern crate unicode_normalization;
+extern crate url;
 
 pub mod admin;
 pub mod app;
 pub mod db;
 pub mod good_phrase;
 pub(crate) mod models;
+pub mod mylist;
+pub mod person;
 // Temporal silence until diesel-1.4.
 // See <https://github.com/diesel-rs/diesel/issues/1785#issuecomment-422579609>.
 #[allow(proc_macro_derive_resolution_fallback)]
 mod schema;
+pub mod sync;
+pub mod tag;
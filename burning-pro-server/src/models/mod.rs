@@ -28,6 +28,8 @@ pub struct GoodPhraseTag {
     pub name: String,
     /// Tag description.
     pub description: Option<String>,
+    /// UTC datetime the tag is retracted at (if retracted).
+    pub retracted_at: Option<NaiveDateTime>,
 }
 
 /// GoodPhrase.
@@ -65,6 +67,8 @@ pub struct GoodPhrase {
     pub deleted: bool,
     /// UTC datetime the phrase is published at (if known).
     pub published_at: Option<NaiveDateTime>,
+    /// UTC datetime the phrase is retracted at (if retracted).
+    pub retracted_at: Option<NaiveDateTime>,
 }
 
 /// GoodPhrase and tag.
@@ -148,6 +152,46 @@ pub struct Person {
     pub display_name: String,
     /// Twitter account (if known).
     pub twitter: Option<String>,
+    /// UTC datetime the person is retracted at (if retracted).
+    pub retracted_at: Option<NaiveDateTime>,
+}
+
+/// Mylist of a user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Queryable)]
+#[primary_key(mylist_id)]
+pub struct Mylist {
+    /// Row ID.
+    pub mylist_id: i32,
+    /// UTC datetime the row is created at.
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// User ID of the owner.
+    pub user_id: i32,
+    /// Mylist name.
+    pub name: String,
+    /// Position of the mylist in the user's mylists (ascending).
+    pub position: i32,
+}
+
+/// Phrase registered to a mylist.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Associations, Identifiable, Queryable,
+)]
+#[belongs_to(Mylist)]
+#[table_name = "mylist_entries"]
+#[primary_key(mylist_entry_id)]
+pub struct MylistEntry {
+    /// Row ID.
+    pub mylist_entry_id: i32,
+    /// UTC datetime the row is created at.
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// Mylist ID.
+    pub mylist_id: i32,
+    /// GoodPhrase ID.
+    pub good_phrase_id: i32,
 }
 
 /// GoodPhraseRequest.
@@ -178,4 +222,168 @@ pub struct GoodPhraseRequest {
     pub deleted: bool,
     /// UTC datetime the phrase is published at (if known).
     pub published_at: Option<NaiveDateTime>,
+    /// Moderation status (`pending`, `accepted`, or `rejected`).
+    pub status: String,
+    /// Phrase registered from the request (if accepted).
+    pub good_phrase_id: Option<i32>,
+    /// Reason why the request is rejected (if rejected).
+    pub reject_reason: Option<String>,
+    /// UTC datetime the request is accepted or rejected at.
+    pub moderated_at: Option<NaiveDateTime>,
+    /// Digest of the receipt token given to the submitter (if exists).
+    #[serde(skip_serializing)]
+    pub receipt_token_digest: Option<String>,
+}
+
+/// Tag proposed by a phrase request.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Hash,
+    Serialize,
+    Associations,
+    Identifiable,
+    Queryable,
+)]
+#[belongs_to(GoodPhraseRequest)]
+#[primary_key(good_phrase_request_tag_id)]
+pub struct GoodPhraseRequestTag {
+    /// Row ID.
+    pub good_phrase_request_tag_id: i32,
+    /// UTC datetime the row is created at.
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// GoodPhraseRequest ID.
+    pub good_phrase_request_id: i32,
+    /// Existing tag ID (if an existing tag is proposed).
+    pub good_phrase_tag_id: Option<i32>,
+    /// Free-text tag name (if a new tag is proposed).
+    pub name: Option<String>,
+}
+
+/// Tombstone of a deleted row.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Queryable)]
+#[primary_key(tombstone_id)]
+pub struct Tombstone {
+    /// Row ID.
+    pub tombstone_id: i32,
+    /// UTC datetime the row is created at (i.e. the row is deleted at).
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// Name of the table the row is deleted from.
+    pub table_name: String,
+    /// Row ID of the deleted row.
+    pub row_id: i32,
+    /// Parent ID of the deleted row.
+    ///
+    /// This is `person_id` for `good_phrases` and `person_urls`, and
+    /// `good_phrase_id` for `good_phrases_and_tags`.
+    pub parent_id: Option<i32>,
+    /// Linked ID of the deleted row.
+    ///
+    /// This is `good_phrase_tag_id` for `good_phrases_and_tags`.
+    pub linked_id: Option<i32>,
+}
+
+/// Change of an entry made by an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Queryable)]
+#[table_name = "audit_log"]
+#[primary_key(audit_log_id)]
+pub struct AuditLog {
+    /// Row ID.
+    pub audit_log_id: i32,
+    /// UTC datetime the row is created at (i.e. the change is made at).
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// Name of the admin user who made the change.
+    pub user_name: String,
+    /// Kind of the change.
+    pub action: String,
+    /// Name of the table the changed row belongs to.
+    pub table_name: String,
+    /// Row ID of the changed row.
+    pub row_id: i32,
+    /// JSON object of the changed fields before the change.
+    pub old_values: Option<String>,
+    /// JSON object of the changed fields after the change.
+    pub new_values: Option<String>,
+}
+
+/// Revision (snapshot) of a phrase.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Queryable)]
+#[table_name = "good_phrase_revisions"]
+#[primary_key(good_phrase_revision_id)]
+pub struct GoodPhraseRevision {
+    /// Row ID.
+    pub good_phrase_revision_id: i32,
+    /// UTC datetime the row is created at (i.e. the revision is made at).
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Name of the admin user who made the revision (empty if unknown).
+    pub user_name: String,
+    /// Title.
+    pub title: String,
+    /// Phrase.
+    pub phrase: String,
+    /// Person ID of the author.
+    pub person_id: i32,
+    /// URL of the phrase if exists.
+    pub url: Option<String>,
+    /// Whether the phrase is deleted.
+    pub deleted: bool,
+    /// UTC datetime the phrase is published at (if known).
+    pub published_at: Option<NaiveDateTime>,
+    /// Comma-separated tag IDs of the phrase.
+    pub tag_ids: String,
+}
+
+/// User of the admin web UI.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Identifiable, Queryable)]
+#[table_name = "admin_users"]
+#[primary_key(admin_user_id)]
+pub struct AdminUser {
+    /// Row ID.
+    pub admin_user_id: i32,
+    /// UTC datetime the row is created at.
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// User name.
+    pub name: String,
+    /// Password hash.
+    pub password_hash: String,
+    /// Role.
+    pub role: String,
+}
+
+/// Bearer token of an admin user for the admin API.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Identifiable, Queryable)]
+#[table_name = "admin_api_tokens"]
+#[primary_key(admin_api_token_id)]
+pub struct AdminApiToken {
+    /// Row ID.
+    pub admin_api_token_id: i32,
+    /// UTC datetime the row is created at.
+    pub created_at: NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: NaiveDateTime,
+    /// Admin user ID of the owner.
+    pub admin_user_id: i32,
+    /// Digest of the token.
+    pub token_digest: String,
+    /// Label of the token.
+    pub label: String,
+    /// UTC datetime the token is last used at (if used).
+    pub last_used_at: Option<NaiveDateTime>,
+    /// UTC datetime the token is revoked at (if revoked).
+    pub revoked_at: Option<NaiveDateTime>,
 }
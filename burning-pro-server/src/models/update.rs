@@ -67,6 +67,20 @@ pub struct NewGoodPhraseRequest<'a> {
     pub deleted: bool,
     /// UTC datetime the phrase is published at (if known).
     pub published_at: Option<&'a NaiveDateTime>,
+    /// Digest of the receipt token given to the submitter.
+    pub receipt_token_digest: Option<&'a str>,
+}
+
+/// Tag proposed by a phrase request.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Insertable)]
+#[table_name = "good_phrase_request_tags"]
+pub struct NewGoodPhraseRequestTag<'a> {
+    /// GoodPhraseRequest ID.
+    pub good_phrase_request_id: i32,
+    /// Existing tag ID (if an existing tag is proposed).
+    pub good_phrase_tag_id: Option<i32>,
+    /// Free-text tag name (if a new tag is proposed).
+    pub name: Option<&'a str>,
 }
 
 /// GoodPhrase and tag.
@@ -127,3 +141,201 @@ pub struct NewPerson<'a> {
     /// Twitter account (if known).
     pub twitter: Option<&'a str>,
 }
+
+/// App user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "users"]
+#[primary_key(user_id)]
+pub struct NewUser<'a> {
+    /// Row ID.
+    pub user_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// Anonymous device token.
+    pub device_token: &'a str,
+}
+
+/// Favorite of a phrase by a user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "good_phrase_favorites"]
+#[primary_key(good_phrase_favorite_id)]
+pub struct NewGoodPhraseFavorite<'a> {
+    /// Row ID.
+    pub good_phrase_favorite_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// User ID.
+    pub user_id: i32,
+    /// GoodPhrase ID.
+    pub good_phrase_id: i32,
+}
+
+/// Event of a phrase.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "good_phrase_events"]
+#[primary_key(good_phrase_event_id)]
+pub struct NewGoodPhraseEvent<'a> {
+    /// Row ID.
+    pub good_phrase_event_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// GoodPhrase ID.
+    pub good_phrase_id: i32,
+    /// User ID (if known).
+    pub user_id: Option<i32>,
+    /// Event kind (`view` or `use`).
+    pub kind: &'a str,
+}
+
+/// Use of a phrase by a user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "good_phrase_uses"]
+#[primary_key(good_phrase_use_id)]
+pub struct NewGoodPhraseUse<'a> {
+    /// Row ID.
+    pub good_phrase_use_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// User ID (if known).
+    pub user_id: Option<i32>,
+    /// GoodPhrase ID.
+    pub good_phrase_id: i32,
+}
+
+/// Mylist of a user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "mylists"]
+#[primary_key(mylist_id)]
+pub struct NewMylist<'a> {
+    /// Row ID.
+    pub mylist_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// User ID of the owner.
+    pub user_id: i32,
+    /// Mylist name.
+    pub name: &'a str,
+    /// Position of the mylist in the user's mylists (ascending).
+    pub position: i32,
+}
+
+/// Phrase registered to a mylist.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "mylist_entries"]
+#[primary_key(mylist_entry_id)]
+pub struct NewMylistEntry<'a> {
+    /// Row ID.
+    pub mylist_entry_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// Mylist ID.
+    pub mylist_id: i32,
+    /// GoodPhrase ID.
+    pub good_phrase_id: i32,
+}
+
+/// Change of an entry made by an admin user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "audit_log"]
+#[primary_key(audit_log_id)]
+pub struct NewAuditLog<'a> {
+    /// Row ID.
+    pub audit_log_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// Name of the admin user who made the change.
+    pub user_name: &'a str,
+    /// Kind of the change.
+    pub action: &'a str,
+    /// Name of the table the changed row belongs to.
+    pub table_name: &'a str,
+    /// Row ID of the changed row.
+    pub row_id: i32,
+    /// JSON object of the changed fields before the change.
+    pub old_values: Option<&'a str>,
+    /// JSON object of the changed fields after the change.
+    pub new_values: Option<&'a str>,
+}
+
+/// Revision (snapshot) of a phrase.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Identifiable, Insertable)]
+#[table_name = "good_phrase_revisions"]
+#[primary_key(good_phrase_revision_id)]
+pub struct NewGoodPhraseRevision<'a> {
+    /// Row ID.
+    pub good_phrase_revision_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// Phrase ID.
+    pub good_phrase_id: i32,
+    /// Name of the admin user who made the revision.
+    pub user_name: &'a str,
+    /// Title.
+    pub title: &'a str,
+    /// Phrase.
+    pub phrase: &'a str,
+    /// Person ID of the author.
+    pub person_id: i32,
+    /// URL of the phrase if exists.
+    pub url: Option<&'a str>,
+    /// Whether the phrase is deleted.
+    pub deleted: bool,
+    /// UTC datetime the phrase is published at (if known).
+    pub published_at: Option<&'a NaiveDateTime>,
+    /// Comma-separated tag IDs of the phrase.
+    pub tag_ids: &'a str,
+}
+
+/// User of the admin web UI.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Identifiable, Insertable)]
+#[table_name = "admin_users"]
+#[primary_key(admin_user_id)]
+pub struct NewAdminUser<'a> {
+    /// Row ID.
+    pub admin_user_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// User name.
+    pub name: &'a str,
+    /// Password hash.
+    pub password_hash: &'a str,
+    /// Role.
+    pub role: &'a str,
+}
+
+/// Bearer token of an admin user for the admin API.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Identifiable, Insertable)]
+#[table_name = "admin_api_tokens"]
+#[primary_key(admin_api_token_id)]
+pub struct NewAdminApiToken<'a> {
+    /// Row ID.
+    pub admin_api_token_id: Option<i32>,
+    /// UTC datetime the row is created at.
+    pub created_at: &'a NaiveDateTime,
+    /// UTC datetime the row is last modified at.
+    pub modified_at: &'a NaiveDateTime,
+    /// Admin user ID of the owner.
+    pub admin_user_id: i32,
+    /// Digest of the token.
+    pub token_digest: &'a str,
+    /// Label of the token.
+    pub label: &'a str,
+}
@@ -0,0 +1,18 @@
+//! Request body types.
+
+/// Name of a mylist to create or rename.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Mylist {
+    /// Mylist name.
+    pub name: String,
+}
+
+/// New order of mylists.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MylistOrder {
+    /// Mylist IDs in the new order.
+    ///
+    /// Mylists not listed here are placed after the listed ones, keeping their
+    /// relative order.
+    pub mylist_ids: Vec<i32>,
+}
@@ -0,0 +1,242 @@
+//! Mylist service.
+//!
+//! Mylists are owned by app users identified by `X-Device-Token` header.
+
+use actix_web::error::Error;
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json, Path};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures::future::{self, Future};
+
+use app::{require_device_token, AppState, JsonError};
+use db::{
+    self, CreateMylist, DeleteMylist, GetMylist, GetMylists, RenameMylist, ReorderMylists,
+    SetMylistEntry,
+};
+
+pub mod form;
+pub mod response;
+
+/// Maximum length (in characters) of a mylist name.
+const MAX_NAME_LEN: usize = 100;
+
+/// Processes the request for the user's mylists.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::index()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    req.state()
+        .db()
+        .send(GetMylists { device_token })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => Err(db_error("mylist::index()", e)),
+        }).responder()
+}
+
+/// Processes the request to create a mylist.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn create(
+    req: HttpRequest<AppState>,
+    form: Json<form::Mylist>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::create()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let name = match validate_name(&form.name) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    req.state()
+        .db()
+        .send(CreateMylist { device_token, name })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Created().json(contents)),
+            Err(e) => Err(db_error("mylist::create()", e)),
+        }).responder()
+}
+
+/// Processes the request to reorder the user's mylists.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn reorder(
+    req: HttpRequest<AppState>,
+    form: Json<form::MylistOrder>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::reorder()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = ReorderMylists {
+        device_token,
+        mylist_ids: form.into_inner().mylist_ids,
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => Err(db_error("mylist::reorder()", e)),
+        }).responder()
+}
+
+/// Processes the request for the mylist with its phrases.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn show(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::show()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = GetMylist {
+        device_token,
+        mylist_id: path.into_inner(),
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => Err(db_error("mylist::show()", e)),
+        }).responder()
+}
+
+/// Processes the request to rename the mylist.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn rename(
+    path: Path<i32>,
+    req: HttpRequest<AppState>,
+    form: Json<form::Mylist>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::rename()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let name = match validate_name(&form.name) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = RenameMylist {
+        device_token,
+        mylist_id: path.into_inner(),
+        name,
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(contents) => Ok(HttpResponse::Ok().json(contents)),
+            Err(e) => Err(db_error("mylist::rename()", e)),
+        }).responder()
+}
+
+/// Processes the request to delete the mylist.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn delete(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::delete()`: {:?}", req);
+    let device_token = match require_device_token(&req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = DeleteMylist {
+        device_token,
+        mylist_id: path.into_inner(),
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(()) => Ok(HttpResponse::NoContent().finish()),
+            Err(e) => Err(db_error("mylist::delete()", e)),
+        }).responder()
+}
+
+/// Processes the request to add the phrase to the mylist.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn add_phrase(
+    path: Path<(i32, i32)>,
+    req: HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::add_phrase()`: {:?}", req);
+    let (mylist_id, good_phrase_id) = path.into_inner();
+    set_entry(mylist_id, good_phrase_id, true, &req)
+}
+
+/// Processes the request to remove the phrase from the mylist.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn remove_phrase(
+    path: Path<(i32, i32)>,
+    req: HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `mylist::remove_phrase()`: {:?}", req);
+    let (mylist_id, good_phrase_id) = path.into_inner();
+    set_entry(mylist_id, good_phrase_id, false, &req)
+}
+
+/// Internal implementation for `add_phrase()` and `remove_phrase()`.
+fn set_entry(
+    mylist_id: i32,
+    good_phrase_id: i32,
+    contained: bool,
+    req: &HttpRequest<AppState>,
+) -> FutureResponse<HttpResponse> {
+    let device_token = match require_device_token(req) {
+        Ok(v) => v,
+        Err(e) => return Box::new(future::err(e)),
+    };
+    let msg = SetMylistEntry {
+        device_token,
+        mylist_id,
+        good_phrase_id,
+        contained,
+    };
+    req.state()
+        .db()
+        .send(msg)
+        .from_err()
+        .and_then(|res| match res {
+            Ok(()) => Ok(HttpResponse::NoContent().finish()),
+            Err(e) => Err(db_error("mylist::set_entry()", e)),
+        }).responder()
+}
+
+/// Returns the trimmed mylist name if it is valid.
+fn validate_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(JsonError::bad_request("Mylist name is empty").into());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(JsonError::bad_request("Mylist name is too long").into());
+    }
+    Ok(name.to_owned())
+}
+
+/// Converts the DB error into the HTTP error.
+fn db_error(context: &str, e: db::Error) -> Error {
+    match e {
+        db::Error::Db(DieselError::NotFound) => {
+            debug!("`{}`: Mylist or phrase not found.", context);
+            JsonError::not_found("Mylist or phrase not found").into()
+        }
+        db::Error::Db(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            debug!("`{}`: Duplicate mylist name.", context);
+            JsonError::conflict("Mylist with the same name already exists").into()
+        }
+        e => {
+            error!("`{}`: {}", context, e);
+            JsonError::internal("DB error").into()
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Response types.
+
+use chrono::{DateTime, Local};
+
+use good_phrase::response::GoodPhrase;
+
+/// A mylist.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Mylist {
+    /// Internal ID for DB.
+    pub internal_id: i32,
+    /// Mylist name.
+    pub name: String,
+    /// Datetime when the mylist is created.
+    pub created: DateTime<Local>,
+    /// IDs of phrases registered to the mylist, in the order of registration.
+    pub good_phrase_ids: Vec<i32>,
+}
+
+/// A mylist with its phrases.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MylistWithPhrases {
+    /// Internal ID for DB.
+    pub internal_id: i32,
+    /// Mylist name.
+    pub name: String,
+    /// Datetime when the mylist is created.
+    pub created: DateTime<Local>,
+    /// Phrases registered to the mylist, in the order of registration.
+    pub good_phrases: Vec<GoodPhrase>,
+}
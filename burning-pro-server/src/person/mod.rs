@@ -0,0 +1,106 @@
+//! Person service.
+
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Path};
+use chrono::{DateTime, FixedOffset};
+use diesel;
+use futures::future::Future;
+
+use app::{AppState, JsonError};
+use db::{self, GoodPhraseCountQuery, PersonQuery, PersonUrlQuery};
+use models;
+
+pub mod response;
+
+/// Processes the request for persons.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `person::index()`: {:?}", req);
+    let db = req.state().db();
+    db.send(PersonQuery::Visible)
+        .join3(
+            db.send(PersonUrlQuery::All),
+            db.send(GoodPhraseCountQuery::ByPerson),
+        ).from_err()
+        .and_then(|(persons, urls, counts)| {
+            match persons.and_then(|p| urls.and_then(|u| counts.map(|c| (p, u, c)))) {
+                Ok((mut persons, urls, counts)) => {
+                    persons.sort_by_key(|person| person.person_id);
+                    let mut urls_by_person = HashMap::new();
+                    for url in urls {
+                        urls_by_person
+                            .entry(url.person_id)
+                            .or_insert_with(Vec::new)
+                            .push(url);
+                    }
+                    let contents = persons
+                        .into_iter()
+                        .map(|person| {
+                            let urls = urls_by_person
+                                .remove(&person.person_id)
+                                .unwrap_or_default();
+                            to_response(person, urls, &counts)
+                        }).collect::<Vec<_>>();
+                    Ok(HttpResponse::Ok().json(contents))
+                }
+                Err(e) => {
+                    error!("`person::index()`: {}", e);
+                    Err(JsonError::internal("DB error").into())
+                }
+            }
+        }).responder()
+}
+
+/// Processes the request for the person.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn show(path: Path<i32>, req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `person::show()`: {:?}", req);
+    let person_id = path.into_inner();
+    let db = req.state().db();
+    db.send(PersonQuery::PersonId(person_id))
+        .join3(
+            db.send(PersonUrlQuery::PersonId(person_id)),
+            db.send(GoodPhraseCountQuery::PersonId(person_id)),
+        ).from_err()
+        .and_then(|(persons, urls, counts)| {
+            match persons.and_then(|p| urls.and_then(|u| counts.map(|c| (p, u, c)))) {
+                Ok((mut persons, urls, counts)) => match persons.pop() {
+                    Some(ref person) if person.retracted_at.is_some() => {
+                        debug!("Person is retracted.");
+                        Err(JsonError::not_found("Person not found").into())
+                    }
+                    Some(person) => Ok(HttpResponse::Ok().json(to_response(person, urls, &counts))),
+                    None => Err(JsonError::not_found("Person not found").into()),
+                },
+                Err(db::Error::Db(diesel::result::Error::NotFound)) => {
+                    debug!("Person not found.");
+                    Err(JsonError::not_found("Person not found").into())
+                }
+                Err(e) => {
+                    error!("`person::show()`: {}", e);
+                    Err(JsonError::internal("DB error").into())
+                }
+            }
+        }).responder()
+}
+
+/// Builds the response object for the person.
+pub(crate) fn to_response(
+    person: models::Person,
+    urls: Vec<models::PersonUrl>,
+    counts: &HashMap<i32, i64>,
+) -> response::Person {
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+
+    response::Person {
+        internal_id: person.person_id,
+        created: DateTime::from_utc(person.created_at, tz_offset),
+        real_name: person.real_name,
+        display_name: person.display_name,
+        url: urls.into_iter().map(|url| url.url).collect(),
+        twitter: person.twitter,
+        phrase_count: counts.get(&person.person_id).map_or(0, |&count| count as u64),
+    }
+}
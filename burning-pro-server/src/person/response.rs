@@ -0,0 +1,22 @@
+//! Response types.
+
+use chrono::{DateTime, Local};
+
+/// A person.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Person {
+    /// Internal ID for DB.
+    pub internal_id: i32,
+    /// Datetime when the entry is created.
+    pub created: DateTime<Local>,
+    /// Real name.
+    pub real_name: Option<String>,
+    /// Display name.
+    pub display_name: String,
+    /// URLs of web pages of the person.
+    pub url: Vec<String>,
+    /// Twitter account.
+    pub twitter: Option<String>,
+    /// Number of phrases of the person.
+    pub phrase_count: u64,
+}
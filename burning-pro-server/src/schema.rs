@@ -1,3 +1,73 @@
+table! {
+    admin_api_tokens (admin_api_token_id) {
+        admin_api_token_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        admin_user_id -> Integer,
+        token_digest -> Text,
+        label -> Text,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    admin_users (admin_user_id) {
+        admin_user_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        name -> Text,
+        password_hash -> Text,
+        role -> Text,
+    }
+}
+
+table! {
+    audit_log (audit_log_id) {
+        audit_log_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        user_name -> Text,
+        action -> Text,
+        table_name -> Text,
+        row_id -> Integer,
+        old_values -> Nullable<Text>,
+        new_values -> Nullable<Text>,
+    }
+}
+
+table! {
+    good_phrase_events (good_phrase_event_id) {
+        good_phrase_event_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        good_phrase_id -> Integer,
+        user_id -> Nullable<Integer>,
+        kind -> Text,
+    }
+}
+
+table! {
+    good_phrase_favorites (good_phrase_favorite_id) {
+        good_phrase_favorite_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        user_id -> Integer,
+        good_phrase_id -> Integer,
+    }
+}
+
+table! {
+    good_phrase_request_tags (good_phrase_request_tag_id) {
+        good_phrase_request_tag_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        good_phrase_request_id -> Integer,
+        good_phrase_tag_id -> Nullable<Integer>,
+        name -> Nullable<Text>,
+    }
+}
+
 table! {
     good_phrase_requests (good_phrase_request_id) {
         good_phrase_request_id -> Integer,
@@ -6,6 +76,28 @@ table! {
         url -> Nullable<Text>,
         deleted -> Bool,
         published_at -> Nullable<Timestamp>,
+        status -> Text,
+        good_phrase_id -> Nullable<Integer>,
+        reject_reason -> Nullable<Text>,
+        moderated_at -> Nullable<Timestamp>,
+        receipt_token_digest -> Nullable<Text>,
+    }
+}
+
+table! {
+    good_phrase_revisions (good_phrase_revision_id) {
+        good_phrase_revision_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        good_phrase_id -> Integer,
+        user_name -> Text,
+        title -> Text,
+        phrase -> Text,
+        person_id -> Integer,
+        url -> Nullable<Text>,
+        deleted -> Bool,
+        published_at -> Nullable<Timestamp>,
+        tag_ids -> Text,
     }
 }
 
@@ -16,6 +108,17 @@ table! {
         modified_at -> Timestamp,
         name -> Text,
         description -> Nullable<Text>,
+        retracted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    good_phrase_uses (good_phrase_use_id) {
+        good_phrase_use_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        user_id -> Nullable<Integer>,
+        good_phrase_id -> Integer,
     }
 }
 
@@ -30,6 +133,7 @@ table! {
         url -> Nullable<Text>,
         deleted -> Bool,
         published_at -> Nullable<Timestamp>,
+        retracted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -43,6 +147,27 @@ table! {
     }
 }
 
+table! {
+    mylist_entries (mylist_entry_id) {
+        mylist_entry_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        mylist_id -> Integer,
+        good_phrase_id -> Integer,
+    }
+}
+
+table! {
+    mylists (mylist_id) {
+        mylist_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        user_id -> Integer,
+        name -> Text,
+        position -> Integer,
+    }
+}
+
 table! {
     person_urls (person_url_id) {
         person_url_id -> Integer,
@@ -61,19 +186,68 @@ table! {
         real_name -> Nullable<Text>,
         display_name -> Text,
         twitter -> Nullable<Text>,
+        retracted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    tombstones (tombstone_id) {
+        tombstone_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        table_name -> Text,
+        row_id -> Integer,
+        parent_id -> Nullable<Integer>,
+        linked_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    users (user_id) {
+        user_id -> Integer,
+        created_at -> Timestamp,
+        modified_at -> Timestamp,
+        device_token -> Text,
     }
 }
 
+joinable!(admin_api_tokens -> admin_users (admin_user_id));
+joinable!(good_phrase_events -> good_phrases (good_phrase_id));
+joinable!(good_phrase_events -> users (user_id));
+joinable!(good_phrase_favorites -> good_phrases (good_phrase_id));
+joinable!(good_phrase_favorites -> users (user_id));
+joinable!(good_phrase_request_tags -> good_phrase_requests (good_phrase_request_id));
+joinable!(good_phrase_request_tags -> good_phrase_tags (good_phrase_tag_id));
+joinable!(good_phrase_requests -> good_phrases (good_phrase_id));
+joinable!(good_phrase_revisions -> good_phrases (good_phrase_id));
+joinable!(good_phrase_revisions -> persons (person_id));
+joinable!(good_phrase_uses -> good_phrases (good_phrase_id));
+joinable!(good_phrase_uses -> users (user_id));
 joinable!(good_phrases -> persons (person_id));
 joinable!(good_phrases_and_tags -> good_phrase_tags (good_phrase_tag_id));
 joinable!(good_phrases_and_tags -> good_phrases (good_phrase_id));
+joinable!(mylist_entries -> good_phrases (good_phrase_id));
+joinable!(mylist_entries -> mylists (mylist_id));
+joinable!(mylists -> users (user_id));
 joinable!(person_urls -> persons (person_id));
 
 allow_tables_to_appear_in_same_query!(
+    admin_api_tokens,
+    admin_users,
+    audit_log,
+    good_phrase_events,
+    good_phrase_favorites,
+    good_phrase_request_tags,
     good_phrase_requests,
+    good_phrase_revisions,
     good_phrase_tags,
+    good_phrase_uses,
     good_phrases,
     good_phrases_and_tags,
+    mylist_entries,
+    mylists,
     person_urls,
     persons,
+    tombstones,
+    users,
 );
@@ -0,0 +1,100 @@
+//! Sync service.
+
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Query};
+use chrono::{DateTime, FixedOffset};
+use futures::future::Future;
+
+use app::{device_token, AppState, JsonError};
+use db::{Changes, GetChanges, GoodPhraseCountQuery};
+use models;
+use person;
+use tag;
+
+pub mod query;
+pub mod response;
+
+/// Processes the request for changes since the previous sync.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(
+    req: HttpRequest<AppState>,
+    query: Query<query::Changes>,
+) -> FutureResponse<HttpResponse> {
+    debug!("request for `sync::index()`: {:?}", req);
+    let msg = GetChanges {
+        since: query.since.map(|since| since.naive_utc()),
+        device_token: device_token(&req),
+    };
+    let db = req.state().db();
+    db.send(msg)
+        .join3(
+            db.send(GoodPhraseCountQuery::ByPerson),
+            db.send(GoodPhraseCountQuery::ByTag),
+        ).from_err()
+        .and_then(|(changes, person_counts, tag_counts)| {
+            match changes.and_then(|c| person_counts.and_then(|p| tag_counts.map(|t| (c, p, t)))) {
+                Ok((changes, person_counts, tag_counts)) => {
+                    let contents = to_response(changes, &person_counts, &tag_counts);
+                    Ok(HttpResponse::Ok().json(contents))
+                }
+                Err(e) => {
+                    error!("`sync::index()`: {}", e);
+                    Err(JsonError::internal("DB error").into())
+                }
+            }
+        }).responder()
+}
+
+/// Builds the response object for the changes.
+fn to_response(
+    changes: Changes,
+    person_counts: &HashMap<i32, i64>,
+    tag_counts: &HashMap<i32, i64>,
+) -> response::Changes {
+    // Local offset.
+    let tz_offset = FixedOffset::east(9 * 60 * 60);
+
+    let mut tombstones = response::Tombstones::default();
+    for tombstone in changes.tombstones {
+        add_tombstone(&mut tombstones, tombstone);
+    }
+    response::Changes {
+        watermark: DateTime::from_utc(changes.watermark, tz_offset),
+        good_phrases: changes.good_phrases,
+        persons: changes
+            .persons
+            .into_iter()
+            .map(|(person, urls)| person::to_response(person, urls, person_counts))
+            .collect(),
+        tags: changes
+            .tags
+            .into_iter()
+            .map(|tag| tag::to_response(tag, tag_counts))
+            .collect(),
+        tombstones,
+    }
+}
+
+/// Adds the tombstone to the response object.
+///
+/// Tombstones of rows not exposed to clients (such as person URLs) are
+/// ignored, because their parents are returned as updated entries.
+fn add_tombstone(tombstones: &mut response::Tombstones, tombstone: models::Tombstone) {
+    match tombstone.table_name.as_str() {
+        "good_phrases" => tombstones.good_phrases.push(tombstone.row_id),
+        "persons" => tombstones.persons.push(tombstone.row_id),
+        "good_phrase_tags" => tombstones.tags.push(tombstone.row_id),
+        "good_phrases_and_tags" => {
+            if let (Some(good_phrase_id), Some(tag_id)) =
+                (tombstone.parent_id, tombstone.linked_id)
+            {
+                tombstones.good_phrase_tags.push(response::GoodPhraseTag {
+                    good_phrase_id,
+                    tag_id,
+                });
+            }
+        }
+        _ => {}
+    }
+}
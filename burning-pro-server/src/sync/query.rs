@@ -0,0 +1,13 @@
+//! Query parameter types.
+
+use chrono::{DateTime, FixedOffset};
+
+/// Query parameters for changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Changes {
+    /// Watermark returned by the previous sync.
+    ///
+    /// This should be RFC 3339 datetime string.
+    /// If not specified, all rows are returned.
+    pub since: Option<DateTime<FixedOffset>>,
+}
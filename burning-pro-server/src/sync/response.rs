@@ -0,0 +1,52 @@
+//! Response types.
+
+use chrono::{DateTime, Local};
+
+use good_phrase::response::GoodPhrase;
+use person::response::Person;
+use tag::response::Tag;
+
+/// Changes since the previous sync.
+///
+/// Clients should apply tombstones before created or updated entries, because
+/// an entry may be deleted and then recreated with the same ID (or retracted
+/// and then restored).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Changes {
+    /// Datetime to be used as `since` of the next sync.
+    ///
+    /// Entries near the watermark may be returned again in the next sync.
+    pub watermark: DateTime<Local>,
+    /// Created or updated good_phrases.
+    pub good_phrases: Vec<GoodPhrase>,
+    /// Created or updated persons.
+    pub persons: Vec<Person>,
+    /// Created or updated tags.
+    pub tags: Vec<Tag>,
+    /// Deleted or retracted entries.
+    pub tombstones: Tombstones,
+}
+
+/// Deleted or retracted entries.
+#[derive(
+    Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Tombstones {
+    /// Internal IDs of deleted good_phrases.
+    pub good_phrases: Vec<i32>,
+    /// Internal IDs of deleted persons.
+    pub persons: Vec<i32>,
+    /// Internal IDs of deleted tags.
+    pub tags: Vec<i32>,
+    /// Removed tags of good_phrases.
+    pub good_phrase_tags: Vec<GoodPhraseTag>,
+}
+
+/// A tag removed from a good_phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GoodPhraseTag {
+    /// Internal ID of the good_phrase.
+    pub good_phrase_id: i32,
+    /// Internal ID of the tag.
+    pub tag_id: i32,
+}
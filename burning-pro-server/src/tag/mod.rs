@@ -0,0 +1,48 @@
+//! Tag service.
+
+use std::collections::HashMap;
+
+use actix_web::{AsyncResponder, FutureResponse, HttpRequest, HttpResponse};
+use futures::future::Future;
+
+use app::{AppState, JsonError};
+use db::{GoodPhraseCountQuery, GoodPhraseTagQuery};
+use models;
+
+pub mod response;
+
+/// Processes the request for tags.
+#[allow(unknown_lints, needless_pass_by_value)]
+pub fn index(req: HttpRequest<AppState>) -> FutureResponse<HttpResponse> {
+    debug!("request for `tag::index()`: {:?}", req);
+    let db = req.state().db();
+    db.send(GoodPhraseTagQuery::Visible)
+        .join(db.send(GoodPhraseCountQuery::ByTag))
+        .from_err()
+        .and_then(|(tags, counts)| match tags.and_then(|t| counts.map(|c| (t, c))) {
+            Ok((mut tags, counts)) => {
+                tags.sort_by_key(|tag| tag.good_phrase_tag_id);
+                let contents = tags
+                    .into_iter()
+                    .map(|tag| to_response(tag, &counts))
+                    .collect::<Vec<_>>();
+                Ok(HttpResponse::Ok().json(contents))
+            }
+            Err(e) => {
+                error!("`tag::index()`: {}", e);
+                Err(JsonError::internal("DB error").into())
+            }
+        }).responder()
+}
+
+/// Builds the response object for the tag.
+pub(crate) fn to_response(tag: models::GoodPhraseTag, counts: &HashMap<i32, i64>) -> response::Tag {
+    response::Tag {
+        phrase_count: counts
+            .get(&tag.good_phrase_tag_id)
+            .map_or(0, |&count| count as u64),
+        internal_id: tag.good_phrase_tag_id,
+        name: tag.name,
+        description: tag.description,
+    }
+}
@@ -0,0 +1,14 @@
+//! Response types.
+
+/// A tag.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Tag {
+    /// Internal ID for DB.
+    pub internal_id: i32,
+    /// Tag name.
+    pub name: String,
+    /// Tag description.
+    pub description: Option<String>,
+    /// Number of phrases with the tag.
+    pub phrase_count: u64,
+}